@@ -644,6 +644,24 @@ pub fn get_control_value(id: i32, control_type: ASIControlType) -> Result<(i32,
     Ok((pl_value, ASIBool::from_raw(pb_auto).to_bool()))
 }
 
+/// Set controls property value and auto value
+/// it will return success and set the max value or min value if the value is beyond the boundary
+pub fn set_control_value(
+    id: i32,
+    control_type: ASIControlType,
+    value: i32,
+    auto: bool,
+) -> Result<(), ASIError> {
+    unsafe {
+        ASIError::from_raw(ASISetControlValue(
+            id,
+            control_type as i32,
+            value as ::std::os::raw::c_long,
+            ASIBool::from_bool(auto) as i32,
+        ))
+    }
+}
+
 /// Set the ROI area before capture.
 /// You must stop capture before call it.
 /// The width and height is the value after binning.
@@ -0,0 +1,115 @@
+use super::{asi_api::ASIControlType, camera::ASIStatus};
+
+/// Frames skipped after a change so that the next measurement uses the new settings.
+const SETTLE_FRAMES: u32 = 2;
+const MAX_ITERATIONS: u32 = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutoExposureState {
+    Idle,
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// Adjusts the exposure, then the gain, until the spectrum peak reaches a target level.
+/// This replaces the SDK auto exposure which targets the mean brightness of the frame.
+pub struct AutoExposure {
+    /// Target peak level relative to the full scale.
+    pub target: f32,
+    pub tolerance: f32,
+    pub state: AutoExposureState,
+    settle: u32,
+    iterations: u32,
+}
+
+impl AutoExposure {
+    pub fn new() -> Self {
+        Self {
+            target: 0.8,
+            tolerance: 0.05,
+            state: AutoExposureState::Idle,
+            settle: 0,
+            iterations: 0,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.state = AutoExposureState::Running;
+        self.settle = SETTLE_FRAMES;
+        self.iterations = 0;
+    }
+
+    /// Feed the measurement of a new frame, returns the control values to apply.
+    pub fn step(
+        &mut self,
+        peak: f32,
+        saturated: f32,
+        status: &ASIStatus,
+    ) -> Vec<(ASIControlType, i32)> {
+        if self.state != AutoExposureState::Running {
+            return vec![];
+        }
+        if self.settle > 0 {
+            self.settle -= 1;
+            return vec![];
+        }
+        if saturated < 1e-4 && (peak - self.target).abs() <= self.tolerance {
+            self.state = AutoExposureState::Done;
+            return vec![];
+        }
+        self.iterations += 1;
+        if self.iterations > MAX_ITERATIONS {
+            self.state = AutoExposureState::Failed("Did not converge.".to_string());
+            return vec![];
+        }
+
+        let (Some(exposure_caps), Some(gain_caps)) = (
+            status.control(ASIControlType::Exposure),
+            status.control(ASIControlType::Gain),
+        ) else {
+            self.state = AutoExposureState::Failed("Camera has no exposure control.".to_string());
+            return vec![];
+        };
+
+        let mut ratio = (self.target / peak.max(1e-3)).clamp(0.1, 10.);
+        if saturated >= 1e-4 {
+            // the peak is unknown while clipping
+            ratio = ratio.min(0.5);
+        }
+
+        let mut changes = vec![];
+        let mut gain = status.gain;
+        let mut exposure = status.exposure;
+
+        // lower the gain before the exposure to keep the noise down
+        if ratio < 1. && gain > gain_caps.min_value {
+            // ASI gain is in 0.1 dB
+            gain = (gain as f32 + 200. * ratio.log10()).round() as i32;
+            gain = gain.max(gain_caps.min_value);
+            ratio /= 10_f32.powf((gain - status.gain) as f32 / 200.);
+        }
+
+        exposure = ((exposure as f32 * ratio).round() as i32)
+            .clamp(exposure_caps.min_value, exposure_caps.max_value);
+        ratio /= exposure as f32 / status.exposure.max(1) as f32;
+
+        if ratio > 1.01 && gain < gain_caps.max_value {
+            gain = (gain as f32 + 200. * ratio.log10()).round() as i32;
+            gain = gain.min(gain_caps.max_value);
+        }
+
+        if exposure != status.exposure {
+            changes.push((ASIControlType::Exposure, exposure));
+        }
+        if gain != status.gain {
+            changes.push((ASIControlType::Gain, gain));
+        }
+        if changes.is_empty() {
+            self.state =
+                AutoExposureState::Failed("Exposure and gain at their limits.".to_string());
+        }
+        self.settle = SETTLE_FRAMES;
+        changes
+    }
+}
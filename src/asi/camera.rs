@@ -0,0 +1,330 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+
+use super::asi_api::{self, ASICameraInfo, ASIControlCaps, ASIControlType, ASIError, ASIImageType};
+use crate::{
+    frame::{Frame, FrameSource},
+    ui::app::ConnectionStatus,
+};
+
+/// Interval between readings of the sensor temperature.
+const TEMPERATURE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct ASIStatus {
+    pub connected_cams: Vec<ASICameraInfo>,
+    pub connection_status: ConnectionStatus,
+    pub camera: Option<ASICameraInfo>,
+    pub controls: Vec<ASIControlCaps>,
+    /// Exposure time in microseconds.
+    pub exposure: i32,
+    pub gain: i32,
+    pub bin: i32,
+    pub image_type: ASIImageType,
+    /// Sensor temperature in °C.
+    pub temperature: Option<f64>,
+    pub frame: Option<Arc<Frame>>,
+    /// UTC time `frame` was received.
+    pub frame_time: Option<DateTime<Utc>>,
+    /// Incremented for every received frame.
+    pub frame_count: u64,
+    pub error: Option<String>,
+}
+
+impl ASIStatus {
+    pub fn control(&self, control_type: ASIControlType) -> Option<&ASIControlCaps> {
+        self.controls
+            .iter()
+            .find(|caps| caps.control_type as i32 == control_type as i32)
+    }
+}
+
+/// Captured frame with the UTC time it was received.
+pub type TimedFrame = (Arc<Frame>, DateTime<Utc>);
+
+/// Bounded queue of the frames to record. Frames arriving while it is full are counted
+/// and dropped, so a slow disk neither holds up the capture nor fills the memory.
+pub struct RecordQueue {
+    pub sender: SyncSender<TimedFrame>,
+    pub dropped: Arc<AtomicUsize>,
+}
+
+pub enum CameraCommand {
+    Control(ASIControlType, i32),
+    Format {
+        bin: i32,
+        image_type: ASIImageType,
+    },
+    /// Also queue every frame for a recorder, or stop with `None`.
+    Record(Option<RecordQueue>),
+    Disconnect,
+}
+
+/// Runs the video capture loop of an ASI camera on a worker thread.
+/// The latest frame is published in the status.
+pub struct CameraDriver {
+    status: Arc<Mutex<ASIStatus>>,
+    sender: Option<Sender<CameraCommand>>,
+}
+
+impl CameraDriver {
+    pub fn new() -> Self {
+        let mut driver = Self {
+            status: Arc::new(Mutex::new(ASIStatus {
+                connected_cams: vec![],
+                connection_status: ConnectionStatus::Unconnected,
+                camera: None,
+                controls: vec![],
+                exposure: 0,
+                gain: 0,
+                bin: 1,
+                image_type: ASIImageType::Raw8,
+                temperature: None,
+                frame: None,
+                frame_time: None,
+                frame_count: 0,
+                error: None,
+            })),
+            sender: None,
+        };
+        driver.refresh();
+        driver
+    }
+
+    pub fn status(&self) -> MutexGuard<'_, ASIStatus> {
+        self.status.lock().unwrap()
+    }
+
+    pub fn refresh(&mut self) {
+        let cams = (0..asi_api::get_num_of_connected_cameras())
+            .filter_map(|index| asi_api::get_camera_property(index).ok())
+            .collect();
+        self.status().connected_cams = cams;
+    }
+
+    pub fn connect(&mut self, camera_id: i32, ctx: egui::Context) {
+        let Some(info) = self
+            .status()
+            .connected_cams
+            .iter()
+            .find(|cam| cam.camera_id == camera_id)
+            .cloned()
+        else {
+            return;
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        self.sender = Some(sender);
+        {
+            let mut status = self.status();
+            status.connection_status = ConnectionStatus::Connecting;
+            status.error = None;
+        }
+
+        let status = self.status.clone();
+        thread::spawn(move || {
+            let result = open(&info, &status).and_then(|_| {
+                status.lock().unwrap().connection_status = ConnectionStatus::Connected;
+                ctx.request_repaint();
+                run(&info, receiver, &status, &ctx)
+            });
+            let _ = asi_api::stop_video_capture(info.camera_id);
+            let _ = asi_api::close_camera(info.camera_id);
+
+            let mut status = status.lock().unwrap();
+            if let Err(e) = result {
+                log::error!("{}: {}", info.name, e);
+                status.error = Some(e.to_string());
+            }
+            status.connection_status = ConnectionStatus::Unconnected;
+            status.camera = None;
+            status.controls.clear();
+            status.temperature = None;
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            self.status().connection_status = ConnectionStatus::Disconnecting;
+            let _ = sender.send(CameraCommand::Disconnect);
+        }
+    }
+
+    pub fn send(&self, command: CameraCommand) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(command);
+        }
+    }
+}
+
+impl FrameSource for CameraDriver {
+    fn latest(&self) -> (Option<Arc<Frame>>, Option<DateTime<Utc>>, u64) {
+        let status = self.status();
+        (status.frame.clone(), status.frame_time, status.frame_count)
+    }
+}
+
+fn open(info: &ASICameraInfo, status: &Mutex<ASIStatus>) -> Result<(), Box<dyn Error>> {
+    let id = info.camera_id;
+    asi_api::open_camera(id)?;
+    asi_api::init_camera(id)?;
+
+    let controls = (0..asi_api::get_num_of_controls(id)?)
+        .map(|index| asi_api::get_control_caps(id, index))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (exposure, _) = asi_api::get_control_value(id, ASIControlType::Exposure)?;
+    let (gain, _) = asi_api::get_control_value(id, ASIControlType::Gain)?;
+
+    let image_type = if info
+        .supported_video_format
+        .iter()
+        .any(|format| matches!(format, ASIImageType::Raw16))
+    {
+        ASIImageType::Raw16
+    } else {
+        ASIImageType::Raw8
+    };
+
+    let mut status = status.lock().unwrap();
+    status.camera = Some(info.clone());
+    status.controls = controls;
+    status.exposure = exposure;
+    status.gain = gain;
+    status.bin = 1;
+    status.image_type = image_type;
+    Ok(())
+}
+
+/// The SDK requires the width to be a multiple of 8 and the height a multiple of 2.
+fn start_capture(
+    info: &ASICameraInfo,
+    bin: i32,
+    image_type: ASIImageType,
+) -> Result<(usize, usize), ASIError> {
+    let width = info.max_width / bin / 8 * 8;
+    let height = info.max_height / bin / 2 * 2;
+    asi_api::set_roi_format(info.camera_id, width, height, bin, image_type)?;
+    asi_api::start_video_capture(info.camera_id)?;
+    Ok((width as usize, height as usize))
+}
+
+fn run(
+    info: &ASICameraInfo,
+    receiver: Receiver<CameraCommand>,
+    status: &Mutex<ASIStatus>,
+    ctx: &egui::Context,
+) -> Result<(), Box<dyn Error>> {
+    let id = info.camera_id;
+    let bayer_pattern = info.is_color_cam.then_some(info.bayer_pattern);
+    let (mut bin, mut image_type) = {
+        let status = status.lock().unwrap();
+        (status.bin, status.image_type)
+    };
+    let (mut width, mut height) = start_capture(info, bin, image_type)?;
+    let mut recorder: Option<RecordQueue> = None;
+    let has_temperature = status
+        .lock()
+        .unwrap()
+        .control(ASIControlType::Temperature)
+        .is_some();
+    let mut temperature_read: Option<Instant> = None;
+
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(CameraCommand::Control(control_type, value)) => {
+                    // a rejected value keeps the previous one and the capture running
+                    let result = asi_api::set_control_value(id, control_type, value, false)
+                        .and_then(|_| asi_api::get_control_value(id, control_type));
+                    let mut status = status.lock().unwrap();
+                    match result {
+                        Ok((value, _)) => {
+                            match control_type {
+                                ASIControlType::Exposure => status.exposure = value,
+                                ASIControlType::Gain => status.gain = value,
+                                _ => {}
+                            }
+                            status.error = None;
+                        }
+                        Err(e) => {
+                            log::warn!("{}: {:?} {}: {}", info.name, control_type, value, e);
+                            status.error = Some(e.to_string());
+                        }
+                    }
+                }
+                Ok(CameraCommand::Format {
+                    bin: new_bin,
+                    image_type: new_image_type,
+                }) => {
+                    asi_api::stop_video_capture(id)?;
+                    (bin, image_type) = (new_bin, new_image_type);
+                    (width, height) = start_capture(info, bin, image_type)?;
+                    let mut status = status.lock().unwrap();
+                    status.bin = bin;
+                    status.image_type = image_type;
+                }
+                Ok(CameraCommand::Record(sender)) => recorder = sender,
+                Ok(CameraCommand::Disconnect) | Err(TryRecvError::Disconnected) => return Ok(()),
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        let exposure_ms = status.lock().unwrap().exposure / 1000;
+        let buffer = vec![0u8; width * height * Frame::bytes_per_pixel(image_type)];
+        let data = match asi_api::get_video_data(id, buffer, exposure_ms * 2 + 500) {
+            Ok(data) => data,
+            Err(ASIError::Timeout) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let time = Utc::now();
+        let frame = Arc::new(Frame::from_raw(
+            width,
+            height,
+            image_type,
+            data,
+            bayer_pattern,
+        ));
+        if let Some(queue) = &recorder {
+            match queue.sender.try_send((frame.clone(), time)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    queue.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Disconnected(_)) => recorder = None,
+            }
+        }
+        // the temperature changes slowly, spare the SDK a call per frame
+        let temperature = if has_temperature
+            && temperature_read.is_none_or(|read| read.elapsed() > TEMPERATURE_INTERVAL)
+        {
+            temperature_read = Some(Instant::now());
+            // the SDK reports tenths of a degree
+            asi_api::get_control_value(id, ASIControlType::Temperature)
+                .ok()
+                .map(|(value, _)| value as f64 / 10.)
+        } else {
+            None
+        };
+        let mut status = status.lock().unwrap();
+        status.frame = Some(frame);
+        status.frame_time = Some(time);
+        status.frame_count += 1;
+        if temperature.is_some() {
+            status.temperature = temperature;
+        }
+        ctx.request_repaint();
+    }
+}
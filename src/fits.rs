@@ -0,0 +1,1009 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+
+use crate::{
+    frame::{Frame, Pixels},
+    imaging::solar_image::SolarImage,
+    ser::SerMetadata,
+    spectrum::dispersion::Dispersion,
+};
+
+/// File extensions of FITS files.
+pub const EXTENSIONS: [&str; 3] = ["fits", "fit", "fts"];
+/// Size of the header and data blocks in bytes.
+const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+/// Characters of a string value between its quotes, after the name and the `= `.
+const TEXT_SIZE: usize = CARD_SIZE - 12;
+
+#[derive(Debug)]
+pub enum FitsError {
+    Io(io::Error),
+    /// The data does not fill the axes.
+    ShapeMismatch,
+    NotFits,
+    /// The header or the data ends early.
+    Truncated,
+    MissingKeyword(&'static str),
+    UnsupportedBitpix(i64),
+    /// Neither the primary array nor an image extension has data.
+    NoImage,
+}
+
+impl Display for FitsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FitsError::Io(e) => write!(f, "{}", e),
+            FitsError::ShapeMismatch => write!(f, "The image size does not match its data."),
+            FitsError::NotFits => write!(f, "Not a FITS file."),
+            FitsError::Truncated => write!(f, "The FITS file is truncated."),
+            FitsError::MissingKeyword(name) => write!(f, "The FITS header has no {}.", name),
+            FitsError::UnsupportedBitpix(bitpix) => {
+                write!(f, "Unsupported FITS BITPIX of {}.", bitpix)
+            }
+            FitsError::NoImage => write!(f, "The FITS file has no image."),
+        }
+    }
+}
+
+impl Error for FitsError {}
+
+impl From<io::Error> for FitsError {
+    fn from(e: io::Error) -> Self {
+        FitsError::Io(e)
+    }
+}
+
+/// Value of a header keyword.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Logical(bool),
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Logical(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl Value {
+    /// Fixed format of the value field, right aligned to column 30 except for text.
+    fn format(&self) -> String {
+        match self {
+            Value::Logical(value) => format!("{:>20}", if *value { "T" } else { "F" }),
+            Value::Integer(value) => format!("{:>20}", value),
+            Value::Real(value) => format!("{:>20}", format_real(*value)),
+            Value::Text(text) => {
+                // cut before quoting so that the closing quote stays on the card, without
+                // splitting a doubled quote
+                let mut quoted = String::new();
+                for c in text
+                    .chars()
+                    .filter(|c| c.is_ascii() && !c.is_ascii_control())
+                {
+                    let escaped = if c == '\'' { 2 } else { 1 };
+                    if quoted.len() + escaped > TEXT_SIZE {
+                        break;
+                    }
+                    match c {
+                        '\'' => quoted.push_str("''"),
+                        c => quoted.push(c),
+                    }
+                }
+                format!("'{:<8}'", quoted)
+            }
+        }
+    }
+}
+
+/// Value of the field after the `= ` of a card, with the comment after the `/`.
+fn parse_value(field: &str) -> Option<(Value, String)> {
+    let field = field.trim_start();
+    let (value, rest) = if let Some(quoted) = field.strip_prefix('\'') {
+        // quotes are doubled inside strings
+        let mut text = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        let mut end = None;
+        while let Some((i, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().is_some_and(|(_, next)| *next == '\'') {
+                    chars.next();
+                } else {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            text.push(c);
+        }
+        (
+            Value::Text(text.trim_end().to_string()),
+            &quoted[end.unwrap_or(quoted.len())..],
+        )
+    } else {
+        let (token, rest) = field
+            .split_once('/')
+            .map_or((field, ""), |(token, _)| (token, &field[token.len()..]));
+        let token = token.trim();
+        let value = match token {
+            "T" => Value::Logical(true),
+            "F" => Value::Logical(false),
+            _ => match token.parse::<i64>() {
+                Ok(value) => Value::Integer(value),
+                // Fortran style exponents
+                Err(_) => Value::Real(token.replace(['D', 'd'], "E").parse().ok()?),
+            },
+        };
+        (value, rest)
+    };
+    let comment = rest
+        .trim_start()
+        .strip_prefix('/')
+        .map_or(String::new(), |comment| comment.trim().to_string());
+    Some((value, comment))
+}
+
+/// Real with a decimal point, in exponent notation when very large or small.
+fn format_real(value: f64) -> String {
+    if !value.is_finite() {
+        return "0.0".to_string();
+    }
+    if value == 0. || (1e-4..1e12).contains(&value.abs()) {
+        let text = value.to_string();
+        if text.contains('.') {
+            text
+        } else {
+            text + ".0"
+        }
+    } else {
+        let text = format!("{:E}", value);
+        match text.split_once('E') {
+            Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                format!("{}.0E{}", mantissa, exponent)
+            }
+            _ => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyword {
+    pub name: String,
+    pub value: Value,
+    pub comment: String,
+}
+
+impl Keyword {
+    fn card(&self) -> [u8; CARD_SIZE] {
+        let mut text = format!("{:<8}= {}", self.name, self.value.format());
+        if !self.comment.is_empty() {
+            text += " / ";
+            text += &self.comment;
+        }
+        let mut card = [b' '; CARD_SIZE];
+        for (byte, c) in card.iter_mut().zip(text.chars().filter(char::is_ascii)) {
+            *byte = c as u8;
+        }
+        card
+    }
+}
+
+/// Keywords of a header besides the ones describing the data layout.
+#[derive(Debug, Clone, Default)]
+pub struct FitsHeader {
+    pub keywords: Vec<Keyword>,
+}
+
+impl FitsHeader {
+    /// Set a keyword, replacing an earlier value.
+    pub fn set(&mut self, name: &str, value: impl Into<Value>, comment: &str) {
+        let keyword = Keyword {
+            name: name.to_ascii_uppercase(),
+            value: value.into(),
+            comment: comment.to_string(),
+        };
+        match self.keywords.iter_mut().find(|k| k.name == keyword.name) {
+            Some(existing) => *existing = keyword,
+            None => self.keywords.push(keyword),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.keywords
+            .iter()
+            .find(|keyword| keyword.name == name)
+            .map(|keyword| &keyword.value)
+    }
+
+    /// Numeric value of a keyword, integer or real.
+    pub fn real(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Read the cards of a header unit from `offset` up to the `END` card. Returns the
+    /// header and the offset of the data that follows.
+    fn parse(bytes: &[u8], offset: usize) -> Result<(Self, usize), FitsError> {
+        let mut header = Self::default();
+        let mut offset = offset;
+        loop {
+            let block = bytes
+                .get(offset..offset + BLOCK_SIZE)
+                .ok_or(FitsError::Truncated)?;
+            offset += BLOCK_SIZE;
+            for card in block.chunks_exact(CARD_SIZE) {
+                // COMMENT, HISTORY and other cards without a value are skipped
+                let Some(card) = std::str::from_utf8(card)
+                    .ok()
+                    .filter(|card| card.is_ascii())
+                else {
+                    continue;
+                };
+                let name = card[..8].trim_end();
+                if name == "END" {
+                    return Ok((header, offset));
+                }
+                if &card[8..10] != "= " {
+                    continue;
+                }
+                if let Some((value, comment)) = parse_value(&card[10..]) {
+                    header.keywords.push(Keyword {
+                        name: name.to_string(),
+                        value,
+                        comment,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Samples of an image, stored with the narrowest matching `BITPIX`.
+#[derive(Debug, Clone)]
+pub enum FitsData {
+    U8(Vec<u8>),
+    /// Stored as signed 16 bit integers offset by `BZERO = 32768`.
+    U16(Vec<u16>),
+    F32(Vec<f32>),
+    /// Also the scaled values of the other integer types when reading.
+    F64(Vec<f64>),
+}
+
+impl FitsData {
+    fn len(&self) -> usize {
+        match self {
+            FitsData::U8(data) => data.len(),
+            FitsData::U16(data) => data.len(),
+            FitsData::F32(data) => data.len(),
+            FitsData::F64(data) => data.len(),
+        }
+    }
+
+    fn bitpix(&self) -> i64 {
+        match self {
+            FitsData::U8(_) => 8,
+            FitsData::U16(_) => 16,
+            FitsData::F32(_) => -32,
+            FitsData::F64(_) => -64,
+        }
+    }
+
+    /// Decode big endian samples, applying `BZERO` and `BSCALE`.
+    fn decode(bytes: &[u8], bitpix: i64, zero: f64, scale: f64) -> Result<Self, FitsError> {
+        let unscaled = zero == 0. && scale == 1.;
+        let scaled = |raw: f64| zero + scale * raw;
+        Ok(match bitpix {
+            8 if unscaled => FitsData::U8(bytes.to_vec()),
+            8 => FitsData::F64(bytes.iter().map(|b| scaled(*b as f64)).collect()),
+            16 if zero == 32768. && scale == 1. => FitsData::U16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| (i16::from_be_bytes([b[0], b[1]]) as i32 + 32768) as u16)
+                    .collect(),
+            ),
+            16 => FitsData::F64(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| scaled(i16::from_be_bytes([b[0], b[1]]) as f64))
+                    .collect(),
+            ),
+            32 => FitsData::F64(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| scaled(i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64))
+                    .collect(),
+            ),
+            -32 if unscaled => FitsData::F32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            ),
+            -32 => FitsData::F64(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| scaled(f32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64))
+                    .collect(),
+            ),
+            -64 => FitsData::F64(
+                bytes
+                    .chunks_exact(8)
+                    .map(|b| scaled(f64::from_be_bytes(b.try_into().unwrap())))
+                    .collect(),
+            ),
+            bitpix => return Err(FitsError::UnsupportedBitpix(bitpix)),
+        })
+    }
+
+    /// Physical values of the samples.
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            FitsData::U8(data) => data.iter().map(|value| *value as f64).collect(),
+            FitsData::U16(data) => data.iter().map(|value| *value as f64).collect(),
+            FitsData::F32(data) => data.iter().map(|value| *value as f64).collect(),
+            FitsData::F64(data) => data.clone(),
+        }
+    }
+
+    /// Rows of every plane in reverse order.
+    fn flip_rows(&self, width: usize, height: usize) -> Self {
+        match self {
+            FitsData::U8(data) => FitsData::U8(flip_planes(data, width, height)),
+            FitsData::U16(data) => FitsData::U16(flip_planes(data, width, height)),
+            FitsData::F32(data) => FitsData::F32(flip_planes(data, width, height)),
+            FitsData::F64(data) => FitsData::F64(flip_planes(data, width, height)),
+        }
+    }
+
+    /// Big endian bytes of the data.
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            FitsData::U8(data) => data.clone(),
+            FitsData::U16(data) => data
+                .iter()
+                .flat_map(|value| ((*value as i32 - 32768) as i16).to_be_bytes())
+                .collect(),
+            FitsData::F32(data) => data.iter().flat_map(|value| value.to_be_bytes()).collect(),
+            FitsData::F64(data) => data.iter().flat_map(|value| value.to_be_bytes()).collect(),
+        }
+    }
+}
+
+/// Primary array of a FITS file. The first axis runs along the rows, and the rows go
+/// from the bottom of the image up as FITS viewers display them.
+#[derive(Debug, Clone)]
+pub struct FitsImage {
+    /// Length of the axes, `NAXIS1` first.
+    pub shape: Vec<usize>,
+    pub data: FitsData,
+    pub header: FitsHeader,
+}
+
+/// Rows of a row major image in reverse order.
+fn flip_rows<T: Clone>(values: &[T], width: usize) -> Vec<T> {
+    if width == 0 {
+        return vec![];
+    }
+    values.rchunks_exact(width).flatten().cloned().collect()
+}
+
+fn flip_planes<T: Clone>(values: &[T], width: usize, height: usize) -> Vec<T> {
+    if width * height == 0 {
+        return values.to_vec();
+    }
+    values
+        .chunks_exact(width * height)
+        .flat_map(|plane| flip_rows(plane, width))
+        .collect()
+}
+
+/// Whether a file has one of the FITS extensions.
+pub fn is_fits(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXTENSIONS
+                .iter()
+                .any(|fits| extension.eq_ignore_ascii_case(fits))
+        })
+}
+
+/// Keywords describing the layout of the data, not kept in [`FitsImage::header`].
+const LAYOUT_KEYWORDS: [&str; 7] = [
+    "SIMPLE", "XTENSION", "BITPIX", "EXTEND", "BZERO", "BSCALE", "PCOUNT",
+];
+
+impl FitsImage {
+    /// Raw camera frame, colour frames as three planes of red, green and blue.
+    pub fn from_frame(frame: &Frame) -> Self {
+        let (width, height) = (frame.width, frame.height);
+        let (shape, data) = match &frame.pixels {
+            Pixels::Mono8(pixels) => (vec![width, height], FitsData::U8(flip_rows(pixels, width))),
+            Pixels::Mono16(pixels) => {
+                (vec![width, height], FitsData::U16(flip_rows(pixels, width)))
+            }
+            Pixels::Bgr24(pixels) => {
+                let rows = flip_rows(pixels, width * 3);
+                let planes = [2, 1, 0]
+                    .iter()
+                    .flat_map(|channel| rows.iter().skip(*channel).step_by(3).copied())
+                    .collect();
+                (vec![width, height, 3], FitsData::U8(planes))
+            }
+        };
+        Self {
+            shape,
+            data,
+            header: FitsHeader::default(),
+        }
+    }
+
+    pub fn from_solar_image(image: &SolarImage) -> Self {
+        Self {
+            shape: vec![image.width, image.height],
+            data: FitsData::F32(flip_rows(&image.values, image.width)),
+            header: FitsHeader::default(),
+        }
+    }
+
+    /// Spectrum profile, with a wavelength axis linearised at the centre when the
+    /// dispersion is known.
+    pub fn spectrum(values: &[f64], dispersion: Option<&Dispersion>) -> Self {
+        let mut header = FitsHeader::default();
+        if let Some(dispersion) = dispersion {
+            let centre = (values.len() as f64 - 1.) / 2.;
+            header.set("CTYPE1", "WAVE", "Wavelength axis");
+            header.set("CUNIT1", "Angstrom", "");
+            // FITS pixels count from 1
+            header.set("CRPIX1", centre + 1., "Reference pixel");
+            header.set(
+                "CRVAL1",
+                dispersion.wavelength(centre),
+                "[Angstrom] Wavelength at the reference pixel",
+            );
+            header.set(
+                "CDELT1",
+                dispersion.angstrom_per_pixel(centre),
+                "[Angstrom] Dispersion at the reference pixel",
+            );
+            for (i, coefficient) in dispersion.coefficients.iter().enumerate() {
+                header.set(
+                    &format!("DISPCO{}", i),
+                    *coefficient,
+                    &format!("Dispersion polynomial, x^{} from pixel 0", i),
+                );
+            }
+        }
+        Self {
+            shape: vec![values.len()],
+            data: FitsData::F32(values.iter().map(|value| *value as f32).collect()),
+            header,
+        }
+    }
+
+    /// Read the primary array, or the first image extension when the primary header has
+    /// no data. Integer samples other than unsigned 8 and 16 bits are scaled to reals.
+    pub fn open(path: &Path) -> Result<Self, FitsError> {
+        let bytes = fs::read(path)?;
+        if !bytes.starts_with(b"SIMPLE  =") {
+            return Err(FitsError::NotFits);
+        }
+
+        let mut offset = 0;
+        loop {
+            let (mut header, data_offset) = FitsHeader::parse(&bytes, offset)?;
+            let integer = |name: &'static str| match header.get(name) {
+                Some(Value::Integer(value)) => Ok(*value),
+                _ => Err(FitsError::MissingKeyword(name)),
+            };
+            let bitpix = integer("BITPIX")?;
+            let axes = integer("NAXIS")?.max(0) as usize;
+            let shape = (1..=axes)
+                .map(|i| match header.get(&format!("NAXIS{}", i)) {
+                    Some(Value::Integer(len)) => Ok((*len).max(0) as usize),
+                    _ => Err(FitsError::MissingKeyword("NAXISn")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            // axes larger than the address space are a corrupt header
+            let count = if shape.is_empty() {
+                0
+            } else {
+                shape
+                    .iter()
+                    .try_fold(1usize, |count, len| count.checked_mul(*len))
+                    .ok_or(FitsError::ShapeMismatch)?
+            };
+            let sample_size = bitpix.unsigned_abs() as usize / 8;
+            let data_size = count
+                .checked_mul(sample_size)
+                .ok_or(FitsError::ShapeMismatch)?;
+            // extensions may also have a heap after the data
+            let size = data_size
+                .checked_add(header.real("PCOUNT").unwrap_or(0.) as usize)
+                .ok_or(FitsError::ShapeMismatch)?;
+            let is_image = offset == 0 || header.text("XTENSION") == Some("IMAGE");
+
+            if count > 0 && is_image {
+                let zero = header.real("BZERO").unwrap_or(0.);
+                let scale = header.real("BSCALE").unwrap_or(1.);
+                let bytes = data_offset
+                    .checked_add(data_size)
+                    .and_then(|end| bytes.get(data_offset..end))
+                    .ok_or(FitsError::Truncated)?;
+                let mut data = FitsData::decode(bytes, bitpix, zero, scale)?;
+                if shape.len() > 1 {
+                    data = data.flip_rows(shape[0], shape[1]);
+                }
+                header.keywords.retain(|keyword| {
+                    !LAYOUT_KEYWORDS.contains(&keyword.name.as_str())
+                        && !keyword.name.starts_with("NAXIS")
+                        && keyword.name != "GCOUNT"
+                });
+                return Ok(Self {
+                    shape,
+                    data,
+                    header,
+                });
+            }
+
+            offset = match data_offset.checked_add(size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE) {
+                Some(next) if next < bytes.len() => next,
+                _ => return Err(FitsError::NoImage),
+            };
+        }
+    }
+
+    /// Wavelength of the pixels along the first axis, from the polynomial of the files
+    /// written here or a linear WCS. Without `CUNIT1`, the unit is guessed from the
+    /// magnitude like for the atlases.
+    pub fn dispersion(&self) -> Option<Dispersion> {
+        let header = &self.header;
+        let coefficients: Vec<f64> = (0..)
+            .map_while(|i| header.real(&format!("DISPCO{}", i)))
+            .collect();
+        if coefficients.len() > 1 {
+            return Some(Dispersion { coefficients });
+        }
+
+        if let Some(ctype) = header.text("CTYPE1") {
+            let ctype = ctype.to_ascii_uppercase();
+            if !["WAV", "LAMBDA", "LINEAR"]
+                .iter()
+                .any(|kind| ctype.contains(kind))
+            {
+                return None;
+            }
+        }
+        let value = header.real("CRVAL1")?;
+        let delta = header.real("CDELT1").or_else(|| header.real("CD1_1"))?;
+        // the first pixel when missing, as most spectrum writers mean
+        let reference = header.real("CRPIX1").unwrap_or(1.);
+        let unit = match header.text("CUNIT1").map(|unit| unit.to_ascii_lowercase()) {
+            Some(unit) if unit == "nm" => 10.,
+            Some(unit) if unit == "um" => 1e4,
+            Some(unit) if unit == "m" => 1e10,
+            Some(_) => 1.,
+            None if value.abs() < 1e-3 => 1e10,
+            None if value.abs() < 1200. => 10.,
+            None => 1.,
+        };
+        // FITS pixels count from 1
+        Some(Dispersion {
+            coefficients: vec![(value - delta * (reference - 1.)) * unit, delta * unit],
+        })
+    }
+
+    /// Date of the observation from `DATE-OBS`.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        let text = self.header.text("DATE-OBS")?;
+        let text = text.trim_end_matches('Z');
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .map(|date| date.and_utc())
+    }
+
+    /// Values scaled to `0..=top`. Whole numbers already in that range are kept, others
+    /// are stretched from `DATAMIN..DATAMAX`, or the range of the data without them.
+    fn scaled_values(&self, top: f64) -> Vec<f64> {
+        let values = self.data.values();
+        if values
+            .iter()
+            .all(|value| value.fract() == 0. && (0. ..=top).contains(value))
+        {
+            return values;
+        }
+        let finite = values.iter().copied().filter(|value| value.is_finite());
+        let min = self
+            .header
+            .real("DATAMIN")
+            .unwrap_or_else(|| finite.clone().fold(f64::INFINITY, f64::min));
+        let max = self
+            .header
+            .real("DATAMAX")
+            .unwrap_or_else(|| finite.fold(f64::NEG_INFINITY, f64::max));
+        let range = max - min;
+        values
+            .iter()
+            .map(|value| match (value - min) / range * top {
+                value if value.is_finite() => value.round().clamp(0., top),
+                _ => 0.,
+            })
+            .collect()
+    }
+
+    /// Camera frame of a two dimensional image, or of three planes of red, green and
+    /// blue. Other samples than 16 bits for mono and 8 bits for colour are scaled by
+    /// their range.
+    pub fn to_frame(&self) -> Option<Frame> {
+        let (width, height, planes) = match self.shape[..] {
+            [width, height] => (width, height, 1),
+            [width, height, 1] => (width, height, 1),
+            [width, height, 3] => (width, height, 3),
+            _ => return None,
+        };
+        let pixels = match (&self.data, planes) {
+            (FitsData::U8(data), 1) => Pixels::Mono8(data.clone()),
+            (FitsData::U16(data), 1) => Pixels::Mono16(data.clone()),
+            (_, 1) => Pixels::Mono16(
+                self.scaled_values(u16::MAX as f64)
+                    .iter()
+                    .map(|value| *value as u16)
+                    .collect(),
+            ),
+            (_, _) => {
+                let values = self.scaled_values(u8::MAX as f64);
+                let len = width * height;
+                Pixels::Bgr24(
+                    (0..len)
+                        .flat_map(|i| [2, 1, 0].map(|channel| values[channel * len + i] as u8))
+                        .collect(),
+                )
+            }
+        };
+        Some(Frame {
+            width,
+            height,
+            pixels,
+            bayer_pattern: None,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FitsError> {
+        if self.shape.iter().product::<usize>() != self.data.len() {
+            return Err(FitsError::ShapeMismatch);
+        }
+
+        let mut layout = FitsHeader::default();
+        layout.set("SIMPLE", true, "Conforms to the FITS standard");
+        layout.set("BITPIX", self.data.bitpix(), "");
+        layout.set("NAXIS", self.shape.len(), "");
+        for (i, len) in self.shape.iter().enumerate() {
+            layout.set(&format!("NAXIS{}", i + 1), *len, "");
+        }
+        if let FitsData::U16(_) = self.data {
+            layout.set("BZERO", 32768i64, "Unsigned 16 bit samples");
+            layout.set("BSCALE", 1i64, "");
+        }
+
+        let mut header: Vec<u8> = layout
+            .keywords
+            .iter()
+            .chain(&self.header.keywords)
+            .flat_map(|keyword| keyword.card())
+            .collect();
+        let mut end = [b' '; CARD_SIZE];
+        end[..3].copy_from_slice(b"END");
+        header.extend(end);
+        header.resize(header.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, b' ');
+
+        let mut data = self.data.bytes();
+        data.resize(data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&header)?;
+        file.write_all(&data)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Observation and camera settings recorded in the headers of exported files.
+#[derive(Debug, Clone, Default)]
+pub struct Acquisition {
+    pub date: Option<DateTime<Utc>>,
+    /// Exposure time in seconds.
+    pub exposure: Option<f64>,
+    /// Gain setting of the camera.
+    pub gain: Option<i32>,
+    /// Sensor temperature in °C.
+    pub temperature: Option<f64>,
+    pub bin: Option<i32>,
+    /// Observed wavelength in Å.
+    pub wavelength: Option<f64>,
+    pub camera: Option<String>,
+    pub metadata: SerMetadata,
+}
+
+impl Acquisition {
+    /// Add the known settings to a header.
+    pub fn write(&self, header: &mut FitsHeader) {
+        header.set(
+            "DATE",
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+                .trim_end_matches('Z'),
+            "File creation date (UTC)",
+        );
+        if let Some(date) = self.date {
+            header.set(
+                "DATE-OBS",
+                date.to_rfc3339_opts(SecondsFormat::Millis, true)
+                    .trim_end_matches('Z'),
+                "Observation date (UTC)",
+            );
+        }
+        if let Some(exposure) = self.exposure {
+            header.set("EXPTIME", exposure, "[s] Exposure time");
+        }
+        if let Some(gain) = self.gain {
+            header.set("GAIN", gain, "Camera gain setting");
+        }
+        if let Some(temperature) = self.temperature {
+            header.set("CCD-TEMP", temperature, "[C] Sensor temperature");
+        }
+        if let Some(bin) = self.bin {
+            header.set("BINNING", bin, "Binning factor");
+            header.set("XBINNING", bin, "");
+            header.set("YBINNING", bin, "");
+        }
+        if let Some(wavelength) = self.wavelength {
+            header.set("WAVELNTH", wavelength, "[Angstrom] Observed wavelength");
+            header.set("WAVEUNIT", -10i64, "Wavelength unit 10^-10 m");
+        }
+        let metadata = &self.metadata;
+        for (name, text, comment) in [
+            ("INSTRUME", &metadata.instrument, "Instrument"),
+            ("TELESCOP", &metadata.telescope, "Telescope"),
+            ("OBSERVER", &metadata.observer, "Observer"),
+            ("SITENAME", &metadata.site, "Observing site"),
+        ] {
+            if !text.is_empty() {
+                header.set(name, text.as_str(), comment);
+            }
+        }
+        if let Some(camera) = &self.camera {
+            header.set("DETECTOR", camera.as_str(), "Camera");
+        }
+        header.set(
+            "CREATOR",
+            concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")),
+            "",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Save and open again, checking the file is whole blocks.
+    fn round_trip(name: &str, image: &FitsImage) -> FitsImage {
+        let path = std::env::temp_dir().join(format!("{}_{}.fits", name, std::process::id()));
+        image.save(&path).unwrap();
+        let len = fs::metadata(&path).unwrap().len() as usize;
+        let read = FitsImage::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(len % BLOCK_SIZE, 0);
+        let read = read.unwrap();
+        assert_eq!(read.shape, image.shape);
+        read
+    }
+
+    fn frame(pixels: Pixels) -> Frame {
+        Frame {
+            width: 4,
+            height: 3,
+            pixels,
+            bayer_pattern: None,
+        }
+    }
+
+    #[test]
+    fn mono8_frames_round_trip() {
+        let pixels: Vec<u8> = (0..12).map(|i| i * 20).collect();
+        let read = round_trip(
+            "mono8",
+            &FitsImage::from_frame(&frame(Pixels::Mono8(pixels.clone()))),
+        );
+        let Some(Frame {
+            pixels: Pixels::Mono8(read),
+            ..
+        }) = read.to_frame()
+        else {
+            panic!("not a Mono8 frame");
+        };
+        assert_eq!(read, pixels);
+    }
+
+    #[test]
+    fn mono16_frames_round_trip_with_bzero() {
+        let pixels: Vec<u16> = [0, 1, 32767, 32768, 32769, 65535]
+            .into_iter()
+            .cycle()
+            .take(12)
+            .collect();
+        let read = round_trip(
+            "mono16",
+            &FitsImage::from_frame(&frame(Pixels::Mono16(pixels.clone()))),
+        );
+        assert_eq!(read.header.get("BZERO"), None);
+        let Some(Frame {
+            pixels: Pixels::Mono16(read),
+            ..
+        }) = read.to_frame()
+        else {
+            panic!("not a Mono16 frame");
+        };
+        assert_eq!(read, pixels);
+    }
+
+    #[test]
+    fn solar_images_round_trip() {
+        let image = SolarImage {
+            width: 4,
+            height: 3,
+            values: (0..12).map(|i| i as f32 * 1.5 - 3.).collect(),
+        };
+        let read = round_trip("solar", &FitsImage::from_solar_image(&image));
+        let FitsData::F32(values) = read.data else {
+            panic!("not F32 data");
+        };
+        // stored bottom up and flipped back when reading
+        assert_eq!(values, image.values);
+    }
+
+    #[test]
+    fn real_images_are_scaled_to_the_frame_depth() {
+        let image = SolarImage {
+            width: 4,
+            height: 3,
+            values: (0..12).map(|i| i as f32 / 11.).collect(),
+        };
+        let read = round_trip("normalized", &FitsImage::from_solar_image(&image));
+        let Some(Frame {
+            pixels: Pixels::Mono16(pixels),
+            ..
+        }) = read.to_frame()
+        else {
+            panic!("not a Mono16 frame");
+        };
+        let expected: Vec<u16> = image
+            .values
+            .iter()
+            .map(|value| (*value as f64 * 65535.).round() as u16)
+            .collect();
+        assert_eq!(pixels, expected);
+
+        let mut colour = FitsImage {
+            shape: vec![2, 1, 3],
+            data: FitsData::U16(vec![0, 1000, 2000, 3000, 4000, 4000]),
+            header: FitsHeader::default(),
+        };
+        colour.header.set("DATAMIN", 0., "");
+        colour.header.set("DATAMAX", 4000., "");
+        let Some(Frame {
+            pixels: Pixels::Bgr24(pixels),
+            ..
+        }) = round_trip("colour", &colour).to_frame()
+        else {
+            panic!("not a Bgr24 frame");
+        };
+        // blue, green and red of each pixel
+        assert_eq!(pixels, vec![255, 128, 0, 255, 191, 64]);
+    }
+
+    #[test]
+    fn spectra_round_trip_with_their_wavelength_axis() {
+        let values: Vec<f64> = (0..101).map(|i| (i as f64 * 0.1).sin()).collect();
+        let dispersion = Dispersion {
+            coefficients: vec![6512.5, 0.125],
+        };
+        let read = round_trip("spectrum", &FitsImage::spectrum(&values, Some(&dispersion)));
+        assert_eq!(read.header.real("CRVAL1"), Some(6512.5 + 0.125 * 50.));
+        assert_eq!(read.header.real("CDELT1"), Some(0.125));
+        let read_dispersion = read.dispersion().unwrap();
+        for pixel in [0., 50., 100.] {
+            assert!(
+                (read_dispersion.wavelength(pixel) - dispersion.wavelength(pixel)).abs() < 1e-9
+            );
+        }
+        for (read, value) in read.data.values().iter().zip(&values) {
+            assert_eq!(*read, *value as f32 as f64);
+        }
+    }
+
+    #[test]
+    fn long_strings_keep_their_closing_quote() {
+        for text in [
+            "x".repeat(100),
+            "it's ".repeat(20),
+            format!("{}'", "y".repeat(67)),
+        ] {
+            let keyword = Keyword {
+                name: "OBSERVER".to_string(),
+                value: Value::Text(text.clone()),
+                comment: "a comment that does not fit".to_string(),
+            };
+            let card = keyword.card();
+            let card = std::str::from_utf8(&card).unwrap();
+            let Some((Value::Text(parsed), _)) = parse_value(&card[10..]) else {
+                panic!("{} is not a string", card);
+            };
+            assert!(parsed.len() <= TEXT_SIZE);
+            assert!(text.starts_with(&parsed));
+            assert_eq!(card.trim_end().rfind('\''), Some(card.trim_end().len() - 1));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::asi::asi_api::{ASIBayerPattern, ASIImageType};
+
+/// Provider of frames to the analysis tools, the live camera or a replayed recording.
+pub trait FrameSource {
+    /// Latest frame, the UTC time it was captured when known and the number of frames
+    /// delivered so far, which changes with every new frame.
+    fn latest(&self) -> (Option<Arc<Frame>>, Option<DateTime<Utc>>, u64);
+}
+
+#[derive(Debug, Clone)]
+pub enum Pixels {
+    Mono8(Vec<u8>),
+    Mono16(Vec<u16>),
+    /// Interleaved in the B, G, R order delivered by the ASI SDK.
+    Bgr24(Vec<u8>),
+}
+
+/// A single image from the camera, with pixels in the sensor orientation.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Pixels,
+    /// Set when the pixels are an undebayered colour mosaic.
+    pub bayer_pattern: Option<ASIBayerPattern>,
+}
+
+impl Frame {
+    /// Decode a buffer filled by [`crate::asi::asi_api::get_video_data`].
+    pub fn from_raw(
+        width: usize,
+        height: usize,
+        image_type: ASIImageType,
+        data: Vec<u8>,
+        bayer_pattern: Option<ASIBayerPattern>,
+    ) -> Self {
+        let (pixels, bayer_pattern) = match image_type {
+            ASIImageType::Raw16 => (
+                Pixels::Mono16(
+                    data.chunks_exact(2)
+                        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                        .collect(),
+                ),
+                bayer_pattern,
+            ),
+            ASIImageType::Raw8 => (Pixels::Mono8(data), bayer_pattern),
+            ASIImageType::Rgb24 => (Pixels::Bgr24(data), None),
+            _ => (Pixels::Mono8(data), None),
+        };
+
+        Self {
+            width,
+            height,
+            pixels,
+            bayer_pattern,
+        }
+    }
+
+    pub fn bytes_per_pixel(image_type: ASIImageType) -> usize {
+        match image_type {
+            ASIImageType::Raw16 => 2,
+            ASIImageType::Rgb24 => 3,
+            _ => 1,
+        }
+    }
+
+    /// Full scale of a pixel in ADU.
+    pub fn max_value(&self) -> f32 {
+        match self.pixels {
+            Pixels::Mono16(_) => u16::MAX as f32,
+            _ => u8::MAX as f32,
+        }
+    }
+
+    /// Pixel value in ADU, colour pixels are averaged over their channels.
+    pub fn value(&self, x: usize, y: usize) -> f32 {
+        let i = y * self.width + x;
+        match &self.pixels {
+            Pixels::Mono8(p) => p[i] as f32,
+            Pixels::Mono16(p) => p[i] as f32,
+            Pixels::Bgr24(p) => (p[i * 3] as f32 + p[i * 3 + 1] as f32 + p[i * 3 + 2] as f32) / 3.,
+        }
+    }
+
+    /// Resample into a `width` by `height` frame with bilinear interpolation, `source`
+    /// gives the position in this frame of each output pixel. Pixels mapped outside are
+    /// black and the result is no longer a bayer mosaic.
+    pub fn remap(
+        &self,
+        width: usize,
+        height: usize,
+        source: impl Fn(usize, usize) -> (f32, f32),
+    ) -> Self {
+        let channels = match self.pixels {
+            Pixels::Bgr24(_) => 3,
+            _ => 1,
+        };
+        let channel = |i: usize| match &self.pixels {
+            Pixels::Mono8(p) => p[i] as f32,
+            Pixels::Mono16(p) => p[i] as f32,
+            Pixels::Bgr24(p) => p[i] as f32,
+        };
+
+        let mut values = vec![0f32; width * height * channels];
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(x, y);
+                if !(0. ..=(self.width - 1) as f32).contains(&sx)
+                    || !(0. ..=(self.height - 1) as f32).contains(&sy)
+                {
+                    continue;
+                }
+                let (x0, y0) = (sx as usize, sy as usize);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let (tx, ty) = (sx - x0 as f32, sy - y0 as f32);
+                for c in 0..channels {
+                    let at = |x: usize, y: usize| channel((y * self.width + x) * channels + c);
+                    let top = at(x0, y0) * (1. - tx) + at(x1, y0) * tx;
+                    let bottom = at(x0, y1) * (1. - tx) + at(x1, y1) * tx;
+                    values[(y * width + x) * channels + c] = top * (1. - ty) + bottom * ty;
+                }
+            }
+        }
+
+        let pixels = match self.pixels {
+            Pixels::Mono8(_) => Pixels::Mono8(values.iter().map(|v| v.round() as u8).collect()),
+            Pixels::Mono16(_) => Pixels::Mono16(values.iter().map(|v| v.round() as u16).collect()),
+            Pixels::Bgr24(_) => Pixels::Bgr24(values.iter().map(|v| v.round() as u8).collect()),
+        };
+        Self {
+            width,
+            height,
+            pixels,
+            bayer_pattern: None,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use super::{rgb_image::RgbImage, solar_image::SolarImage};
+
+/// Blue for -1 through white for 0 to red for 1.
+pub fn diverging(value: f32) -> [u8; 3] {
+    let value = value.clamp(-1., 1.);
+    let fade = ((1. - value.abs()) * 255.).round() as u8;
+    if value < 0. {
+        [fade, fade, 255]
+    } else {
+        [255, fade, fade]
+    }
+}
+
+/// Colour image of a signed image, with `-limit` and `limit` at full blue and red, or
+/// red and blue when `reversed`.
+pub fn diverging_image(image: &SolarImage, limit: f32, reversed: bool) -> RgbImage {
+    let limit = if reversed { -1. } else { 1. } * limit.max(1e-6);
+    RgbImage {
+        width: image.width,
+        height: image.height,
+        pixels: image
+            .values
+            .iter()
+            .flat_map(|value| diverging(value / limit))
+            .collect(),
+    }
+}
+
+/// False colours of monochrome solar images, named after the lines they are used for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Palette {
+    Grey,
+    /// Red to orange of H-alpha.
+    HAlpha,
+    /// Violet of Ca II H and K.
+    Calcium,
+    /// Blue of H-beta and the lines below 5000 Å.
+    Blue,
+    /// Green of the Mg I b triplet and the iron lines around it.
+    Green,
+    /// Yellow of the sodium D lines and He I D3.
+    Yellow,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 6] = [
+        Palette::Grey,
+        Palette::HAlpha,
+        Palette::Calcium,
+        Palette::Blue,
+        Palette::Green,
+        Palette::Yellow,
+    ];
+
+    /// Conventional palette of a line.
+    pub fn for_wavelength(wavelength: f64) -> Self {
+        match wavelength {
+            w if w < 4100. => Palette::Calcium,
+            w if w < 5000. => Palette::Blue,
+            w if w < 5600. => Palette::Green,
+            w if w < 6200. => Palette::Yellow,
+            _ => Palette::HAlpha,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Grey => "Grey",
+            Palette::HAlpha => "H-alpha",
+            Palette::Calcium => "Calcium",
+            Palette::Blue => "Blue",
+            Palette::Green => "Green",
+            Palette::Yellow => "Yellow",
+        }
+    }
+
+    /// Colours at 1/3 and 2/3 of the range, from black at 0 to the last one at 1.
+    fn stops(self) -> [[f32; 3]; 3] {
+        match self {
+            Palette::Grey => [[85., 85., 85.], [170., 170., 170.], [255., 255., 255.]],
+            Palette::HAlpha => [[120., 10., 0.], [255., 120., 20.], [255., 235., 190.]],
+            Palette::Calcium => [[60., 0., 110.], [150., 60., 220.], [235., 210., 255.]],
+            Palette::Blue => [[0., 40., 120.], [40., 140., 255.], [220., 240., 255.]],
+            Palette::Green => [[0., 90., 20.], [80., 220., 90.], [230., 255., 230.]],
+            Palette::Yellow => [[120., 80., 0.], [255., 200., 40.], [255., 250., 220.]],
+        }
+    }
+
+    /// Colour of a value from 0 to 1.
+    pub fn color(self, value: f32) -> [u8; 3] {
+        let stops = self.stops();
+        let position = value.clamp(0., 1.) * 3.;
+        let index = (position as usize).min(2);
+        let from = if index == 0 {
+            [0.; 3]
+        } else {
+            stops[index - 1]
+        };
+        let t = position - index as f32;
+        let mut color = [0; 3];
+        for (channel, (from, to)) in color.iter_mut().zip(from.iter().zip(&stops[index])) {
+            *channel = (from + (to - from) * t).round() as u8;
+        }
+        color
+    }
+}
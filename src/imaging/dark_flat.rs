@@ -0,0 +1,136 @@
+use std::{error::Error, fmt::Display, path::Path, sync::Arc};
+
+use crate::{
+    fits::{FitsError, FitsImage},
+    frame::{Frame, Pixels},
+};
+
+/// Averaged dark or flat frame made by stacking software, in the sensor orientation.
+#[derive(Debug, Clone)]
+pub struct MasterFrame {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+}
+
+impl MasterFrame {
+    /// First plane of a FITS image.
+    pub fn open(path: &Path) -> Result<Self, FitsError> {
+        let image = FitsImage::open(path)?;
+        let [width, height, ..] = image.shape[..] else {
+            return Err(FitsError::NoImage);
+        };
+        Ok(Self {
+            name: path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string()),
+            width,
+            height,
+            values: image
+                .data
+                .values()
+                .iter()
+                .take(width * height)
+                .map(|value| *value as f32)
+                .collect(),
+        })
+    }
+
+    /// Scaled to a mean of 1, as flats divide the frames.
+    pub fn normalized(mut self) -> Self {
+        let finite = self.values.iter().filter(|value| value.is_finite());
+        let mean = finite.clone().sum::<f32>() / finite.count().max(1) as f32;
+        if mean > 0. {
+            self.values.iter_mut().for_each(|value| *value /= mean);
+        }
+        self
+    }
+
+    fn matches(&self, frame: &Frame) -> bool {
+        self.width == frame.width && self.height == frame.height
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DarkFlatError {
+    /// The masters are of another size than the frames.
+    SizeMismatch,
+    /// Only mono and raw Bayer frames are corrected.
+    Colour,
+}
+
+impl Display for DarkFlatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DarkFlatError::SizeMismatch => {
+                write!(f, "The masters do not match the size of the frames.")
+            }
+            DarkFlatError::Colour => write!(f, "Colour frames can not be corrected."),
+        }
+    }
+}
+
+impl Error for DarkFlatError {}
+
+/// Masters applied to the live frames, shared with the reconstruction of recorded scans.
+#[derive(Debug, Clone, Default)]
+pub struct Masters {
+    pub dark: Option<Arc<MasterFrame>>,
+    /// Normalized to a mean of 1.
+    pub flat: Option<Arc<MasterFrame>>,
+}
+
+impl Masters {
+    pub fn is_empty(&self) -> bool {
+        self.dark.is_none() && self.flat.is_none()
+    }
+
+    /// See [`correct`].
+    pub fn correct(&self, frame: &Frame) -> Result<Frame, DarkFlatError> {
+        correct(frame, self.dark.as_deref(), self.flat.as_deref())
+    }
+}
+
+/// Subtract the dark and divide by the normalized flat, rounding to the sample type of
+/// the frame.
+pub fn correct(
+    frame: &Frame,
+    dark: Option<&MasterFrame>,
+    flat: Option<&MasterFrame>,
+) -> Result<Frame, DarkFlatError> {
+    if dark.is_some_and(|dark| !dark.matches(frame))
+        || flat.is_some_and(|flat| !flat.matches(frame))
+    {
+        return Err(DarkFlatError::SizeMismatch);
+    }
+    let value = |i: usize, raw: f32| {
+        let value = raw - dark.map_or(0., |dark| dark.values[i]);
+        // dead pixels of the flat are left as they are
+        match flat.map(|flat| flat.values[i]) {
+            Some(gain) if gain > 0.01 => value / gain,
+            _ => value,
+        }
+    };
+    let pixels = match &frame.pixels {
+        Pixels::Mono8(pixels) => Pixels::Mono8(
+            pixels
+                .iter()
+                .enumerate()
+                .map(|(i, raw)| value(i, *raw as f32).round().clamp(0., u8::MAX as f32) as u8)
+                .collect(),
+        ),
+        Pixels::Mono16(pixels) => Pixels::Mono16(
+            pixels
+                .iter()
+                .enumerate()
+                .map(|(i, raw)| value(i, *raw as f32).round().clamp(0., u16::MAX as f32) as u16)
+                .collect(),
+        ),
+        Pixels::Bgr24(_) => return Err(DarkFlatError::Colour),
+    };
+    Ok(Frame {
+        pixels,
+        ..frame.clone()
+    })
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+use super::solar_image::SolarImage;
+
+/// Grey levels of the CLAHE histograms.
+const BINS: usize = 256;
+/// Half width in pixels of the blend between the disk and the prominence layers.
+const BLEND: f64 = 2.;
+
+/// Solar disk in a corrected image.
+#[derive(Debug, Clone, Copy)]
+pub struct Disk {
+    pub centre: (f64, f64),
+    pub radius: f64,
+}
+
+/// Post-processing bringing out the disk details and the faint prominences together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Enhancement {
+    pub enabled: bool,
+    /// Hide the disk behind a mask of `mask_radius` disk radii.
+    pub coronagraph: bool,
+    pub mask_radius: f64,
+    /// Linear limb darkening coefficient divided out of the disk, 0 for none.
+    pub limb_darkening: f32,
+    /// Exponent of the disk layer, below 1 brightens the faint features.
+    pub disk_gamma: f32,
+    /// Gain and exponent of the layer outside the limb.
+    pub prominence_gain: f32,
+    pub prominence_gamma: f32,
+    /// Unsharp mask amount, 0 for none, and its gaussian sigma in pixels.
+    pub sharpen: f32,
+    pub sharpen_radius: f32,
+    /// Contrast limited adaptive histogram equalization on a grid of `clahe_tiles`
+    /// tiles on each side, histograms clipped at `clahe_clip` times their mean.
+    pub clahe: bool,
+    pub clahe_tiles: usize,
+    pub clahe_clip: f32,
+}
+
+impl Default for Enhancement {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            coronagraph: false,
+            mask_radius: 1.01,
+            limb_darkening: 0.,
+            disk_gamma: 1.,
+            prominence_gain: 5.,
+            prominence_gamma: 0.7,
+            sharpen: 0.,
+            sharpen_radius: 2.,
+            clahe: false,
+            clahe_tiles: 8,
+            clahe_clip: 2.,
+        }
+    }
+}
+
+impl Enhancement {
+    /// Enhanced image from 0 to 1. The radial steps need the `disk`, without it the
+    /// whole image is stretched as the disk.
+    pub fn apply(&self, image: &SolarImage, disk: Option<Disk>) -> SolarImage {
+        let background = image.percentile(0.05);
+        let level = (image.percentile(0.99) - background).max(1e-6);
+        let mut enhanced = image.clone();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let value = ((image.value(x, y) - background) / level).max(0.);
+                let value = match disk {
+                    Some(disk) => self.layers(value, x as f64, y as f64, disk),
+                    None => value.powf(self.disk_gamma),
+                };
+                enhanced.set(x, y, value);
+            }
+        }
+        if self.sharpen > 0. {
+            enhanced = unsharp_mask(&enhanced, self.sharpen_radius, self.sharpen);
+        }
+        if self.clahe {
+            enhanced = clahe(&enhanced, self.clahe_tiles, self.clahe_clip);
+        }
+        for value in &mut enhanced.values {
+            *value = value.clamp(0., 1.);
+        }
+        enhanced
+    }
+
+    /// Value of a normalized pixel once the disk and prominence layers are stretched
+    /// and merged.
+    fn layers(&self, value: f32, x: f64, y: f64, disk: Disk) -> f32 {
+        let distance = (x - disk.centre.0).hypot(y - disk.centre.1);
+        let r = distance / disk.radius.max(1.);
+
+        let disk_layer = if r < 1. {
+            // I/I0 = 1 - u (1 - μ) with μ the cosine of the angle from disk centre
+            let mu = (1. - r * r).sqrt() as f32;
+            let darkening = 1. - self.limb_darkening.clamp(0., 1.) * (1. - mu);
+            (value / darkening.max(0.05)).powf(self.disk_gamma)
+        } else {
+            value.powf(self.disk_gamma)
+        };
+        let prominence_layer = (value * self.prominence_gain)
+            .min(1.)
+            .powf(self.prominence_gamma);
+
+        let outside = |edge: f64| smoothstep((distance - edge) / BLEND);
+        let merged = disk_layer + (prominence_layer - disk_layer) * outside(disk.radius);
+        if self.coronagraph {
+            merged * outside(disk.radius * self.mask_radius)
+        } else {
+            merged
+        }
+    }
+}
+
+/// 0 below -1, 1 above 1 and smooth in between.
+fn smoothstep(t: f64) -> f32 {
+    let t = ((t + 1.) / 2.).clamp(0., 1.);
+    (t * t * (3. - 2. * t)) as f32
+}
+
+/// Separable gaussian blur, the edges extended.
+fn gaussian_blur(image: &SolarImage, sigma: f32) -> SolarImage {
+    if sigma <= 0. || image.width == 0 || image.height == 0 {
+        return image.clone();
+    }
+    let half = (3. * sigma).ceil() as isize;
+    let kernel: Vec<f32> = (-half..=half)
+        .map(|i| (-(i * i) as f32 / (2. * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+
+    let pass = |image: &SolarImage, horizontal: bool| {
+        let mut blurred = SolarImage::new(image.width, image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let sum: f32 = kernel
+                    .iter()
+                    .zip(-half..=half)
+                    .map(|(weight, i)| {
+                        let value = if horizontal {
+                            let x = (x as isize + i).clamp(0, image.width as isize - 1);
+                            image.value(x as usize, y)
+                        } else {
+                            let y = (y as isize + i).clamp(0, image.height as isize - 1);
+                            image.value(x, y as usize)
+                        };
+                        weight * value
+                    })
+                    .sum();
+                blurred.set(x, y, sum / total);
+            }
+        }
+        blurred
+    };
+    pass(&pass(image, true), false)
+}
+
+/// Add `amount` times the difference to a blurred copy.
+fn unsharp_mask(image: &SolarImage, sigma: f32, amount: f32) -> SolarImage {
+    let blurred = gaussian_blur(image, sigma);
+    let mut sharpened = image.clone();
+    for (value, blurred) in sharpened.values.iter_mut().zip(&blurred.values) {
+        *value += amount * (*value - blurred);
+    }
+    sharpened
+}
+
+/// Contrast limited adaptive histogram equalization of an image from 0 to 1. Each tile
+/// gets the equalization of its clipped histogram, interpolated between tile centres.
+fn clahe(image: &SolarImage, tiles: usize, clip: f32) -> SolarImage {
+    let tiles = tiles.clamp(1, image.width.min(image.height).max(1));
+    let (tile_width, tile_height) = (
+        image.width as f64 / tiles as f64,
+        image.height as f64 / tiles as f64,
+    );
+    let bin = |value: f32| ((value.clamp(0., 1.) * (BINS - 1) as f32).round()) as usize;
+
+    // cumulative distribution of every tile
+    let mut mappings = vec![vec![0f32; BINS]; tiles * tiles];
+    for ty in 0..tiles {
+        for tx in 0..tiles {
+            let (x0, x1) = (
+                (tx as f64 * tile_width) as usize,
+                ((tx + 1) as f64 * tile_width) as usize,
+            );
+            let (y0, y1) = (
+                (ty as f64 * tile_height) as usize,
+                ((ty + 1) as f64 * tile_height) as usize,
+            );
+            let mut histogram = vec![0f32; BINS];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    histogram[bin(image.value(x, y))] += 1.;
+                }
+            }
+            let count: f32 = histogram.iter().sum();
+            if count == 0. {
+                continue;
+            }
+            // the counts above the limit are spread over all the bins
+            let limit = (clip.max(1.) * count / BINS as f32).max(1.);
+            let excess: f32 = histogram.iter().map(|h| (h - limit).max(0.)).sum();
+            let mut cumulative = 0.;
+            for (mapping, h) in mappings[ty * tiles + tx].iter_mut().zip(&histogram) {
+                cumulative += h.min(limit) + excess / BINS as f32;
+                *mapping = cumulative / count;
+            }
+        }
+    }
+
+    let mut equalized = image.clone();
+    for y in 0..image.height {
+        // position between the centres of the neighbouring tiles
+        let fy = ((y as f64 + 0.5) / tile_height - 0.5).clamp(0., (tiles - 1) as f64);
+        let (ty0, ty1) = (fy as usize, (fy as usize + 1).min(tiles - 1));
+        let wy = (fy - ty0 as f64) as f32;
+        for x in 0..image.width {
+            let fx = ((x as f64 + 0.5) / tile_width - 0.5).clamp(0., (tiles - 1) as f64);
+            let (tx0, tx1) = (fx as usize, (fx as usize + 1).min(tiles - 1));
+            let wx = (fx - tx0 as f64) as f32;
+            let b = bin(image.value(x, y));
+            let at = |tx: usize, ty: usize| mappings[ty * tiles + tx][b];
+            let top = at(tx0, ty0) * (1. - wx) + at(tx1, ty0) * wx;
+            let bottom = at(tx0, ty1) * (1. - wx) + at(tx1, ty1) * wx;
+            equalized.set(x, y, top * (1. - wy) + bottom * wy);
+        }
+    }
+    equalized
+}
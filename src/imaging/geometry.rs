@@ -0,0 +1,253 @@
+use std::{error::Error, fmt::Display};
+
+use super::solar_image::SolarImage;
+use crate::spectrum::fit;
+
+/// Fraction of the way from the sky background to the disk level taken as the limb.
+const LIMB_LEVEL: f32 = 0.25;
+/// Fewest limb points for an ellipse fit.
+const MIN_POINTS: usize = 10;
+
+#[derive(Debug, Clone)]
+pub enum GeometryError {
+    TooFewPoints(usize),
+    NotAnEllipse,
+}
+
+impl Display for GeometryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeometryError::TooFewPoints(count) => {
+                write!(f, "Only {} limb points were found.", count)
+            }
+            GeometryError::NotAnEllipse => write!(f, "The limb does not fit an ellipse."),
+        }
+    }
+}
+
+impl Error for GeometryError {}
+
+/// Intensity taken as the edge of the disk.
+pub fn limb_level(image: &SolarImage) -> f32 {
+    let background = image.percentile(0.05);
+    let disk = image.percentile(0.99);
+    background + LIMB_LEVEL * (disk - background)
+}
+
+/// Sub-pixel positions where the rows and the columns cross the limb level. Crossings
+/// at the image border are skipped, the disk is cut there.
+pub fn limb_points(image: &SolarImage) -> Vec<(f64, f64)> {
+    let level = limb_level(image);
+
+    // first crossing from each end of a line of pixels
+    let crossings = |len: usize, at: &dyn Fn(usize) -> f32| {
+        let crossing = |i: usize, j: usize| {
+            let (a, b) = (at(i), at(j));
+            i as f64 + (level - a) as f64 / (b - a) as f64 * (j as f64 - i as f64)
+        };
+        let first = (1..len).find(|i| at(*i) >= level)?;
+        let last = (0..len - 1).rev().find(|i| at(*i) >= level)?;
+        (at(0) < level && at(len - 1) < level)
+            .then(|| (crossing(first - 1, first), crossing(last + 1, last)))
+    };
+
+    let mut points = vec![];
+    if image.width < 3 || image.height < 3 {
+        return points;
+    }
+    for y in 0..image.height {
+        if let Some((left, right)) = crossings(image.width, &|x| image.value(x, y)) {
+            points.push((left, y as f64));
+            points.push((right, y as f64));
+        }
+    }
+    for x in 0..image.width {
+        if let Some((top, bottom)) = crossings(image.height, &|y| image.value(x, y)) {
+            points.push((x as f64, top));
+            points.push((x as f64, bottom));
+        }
+    }
+    points
+}
+
+/// Ellipse `a (x-x0)² + b (x-x0)(y-y0) + c (y-y0)² = 1`.
+#[derive(Debug, Clone)]
+pub struct Ellipse {
+    pub centre: (f64, f64),
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl Ellipse {
+    /// Least squares conic through the points, refitted once without the points off by
+    /// more than 3 sigma.
+    pub fn fit(points: &[(f64, f64)]) -> Result<Self, GeometryError> {
+        let ellipse = Self::fit_conic(points)?;
+        let residuals: Vec<f64> = points.iter().map(|p| ellipse.residual(*p)).collect();
+        let sigma = (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len() as f64).sqrt();
+        let kept: Vec<(f64, f64)> = points
+            .iter()
+            .zip(&residuals)
+            .filter(|(_, residual)| residual.abs() <= 3. * sigma)
+            .map(|(point, _)| *point)
+            .collect();
+        Self::fit_conic(&kept)
+    }
+
+    fn fit_conic(points: &[(f64, f64)]) -> Result<Self, GeometryError> {
+        if points.len() < MIN_POINTS {
+            return Err(GeometryError::TooFewPoints(points.len()));
+        }
+        // centred and scaled coordinates keep the normal equations well conditioned
+        let n = points.len() as f64;
+        let (mx, my) = points
+            .iter()
+            .fold((0., 0.), |(x, y), p| (x + p.0 / n, y + p.1 / n));
+        let scale = points
+            .iter()
+            .map(|p| ((p.0 - mx).powi(2) + (p.1 - my).powi(2)).sqrt())
+            .sum::<f64>()
+            / n;
+        if scale <= 0. {
+            return Err(GeometryError::NotAnEllipse);
+        }
+
+        // A x² + B xy + C y² + D x + E y = 1
+        let mut matrix = vec![vec![0.; 5]; 5];
+        let mut rhs = vec![0.; 5];
+        for (x, y) in points {
+            let (x, y) = ((x - mx) / scale, (y - my) / scale);
+            let row = [x * x, x * y, y * y, x, y];
+            for i in 0..5 {
+                for j in 0..5 {
+                    matrix[i][j] += row[i] * row[j];
+                }
+                rhs[i] += row[i];
+            }
+        }
+        let p = fit::solve(matrix, rhs).ok_or(GeometryError::NotAnEllipse)?;
+        let (a, b, c, d, e) = (p[0], p[1], p[2], p[3], p[4]);
+        let determinant = 4. * a * c - b * b;
+        if determinant <= 0. {
+            return Err(GeometryError::NotAnEllipse);
+        }
+        let x0 = (b * e - 2. * c * d) / determinant;
+        let y0 = (b * d - 2. * a * e) / determinant;
+        // value of the quadratic part on the ellipse once centred
+        let k = 1. + (a * x0 * x0 + b * x0 * y0 + c * y0 * y0);
+        if k <= 0. || a <= 0. {
+            return Err(GeometryError::NotAnEllipse);
+        }
+        let scale2 = scale * scale * k;
+        Ok(Self {
+            centre: (mx + x0 * scale, my + y0 * scale),
+            a: a / scale2,
+            b: b / scale2,
+            c: c / scale2,
+        })
+    }
+
+    /// Algebraic distance of a point from the ellipse.
+    fn residual(&self, (x, y): (f64, f64)) -> f64 {
+        let (x, y) = (x - self.centre.0, y - self.centre.1);
+        self.a * x * x + self.b * x * y + self.c * y * y - 1.
+    }
+}
+
+/// Shear and stretch of a reconstructed disk along the scan direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Geometry {
+    /// Angle of the shear in degrees, from the tilt of the slit to the scan direction.
+    pub tilt: f64,
+    /// Width over height of the disk, from a scan speed mismatch.
+    pub ratio: f64,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self {
+            tilt: 0.,
+            ratio: 1.,
+        }
+    }
+}
+
+impl Geometry {
+    /// Shear and ratio that turn the ellipse into a circle, and its radius in pixels.
+    pub fn from_ellipse(ellipse: &Ellipse) -> (Self, f64) {
+        // x = x' * ratio + shear * y maps the circle x'² + y² = r² onto the ellipse
+        let shear = -ellipse.b / (2. * ellipse.a);
+        let circle = ellipse.c - ellipse.b * ellipse.b / (4. * ellipse.a);
+        let ratio = (circle / ellipse.a).sqrt();
+        (
+            Self {
+                tilt: shear.atan().to_degrees(),
+                ratio,
+            },
+            1. / circle.sqrt(),
+        )
+    }
+
+    /// Remove the shear about `centre` and scale the columns to a ratio of 1, keeping
+    /// `centre` in the middle of the result.
+    pub fn correct(&self, image: &SolarImage, centre: (f64, f64)) -> SolarImage {
+        let shear = self.tilt.to_radians().tan();
+        let ratio = self.ratio.max(1e-3);
+        let width = (image.width as f64 / ratio).round().max(1.) as usize;
+        let middle = (width as f64 - 1.) / 2.;
+        let mut corrected = SolarImage::new(width, image.height);
+        for y in 0..image.height {
+            let dy = y as f64 - centre.1;
+            for x in 0..width {
+                let source = centre.0 + (x as f64 - middle) * ratio + shear * dy;
+                corrected.set(x, y, image.sample(source, y as f64));
+            }
+        }
+        corrected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Disk of `radius` sheared by `shear` and stretched by `ratio` along the rows, with
+    /// an anti-aliased limb.
+    fn disk(centre: (f64, f64), radius: f64, shear: f64, ratio: f64) -> SolarImage {
+        let mut image = SolarImage::new(300, 240);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let dy = y as f64 - centre.1;
+                let dx = (x as f64 - centre.0 - shear * dy) / ratio;
+                let inside = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0., 1.);
+                image.set(x, y, (50. + 1000. * inside) as f32);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn ellipses_of_known_disks_are_fitted() {
+        let (centre, radius, shear, ratio) = ((152.3, 118.7), 80., 0.15, 1.2);
+        let image = disk(centre, radius, shear, ratio);
+        let ellipse = Ellipse::fit(&limb_points(&image)).unwrap();
+        assert!((ellipse.centre.0 - centre.0).abs() < 0.2, "{:?}", ellipse);
+        assert!((ellipse.centre.1 - centre.1).abs() < 0.2, "{:?}", ellipse);
+
+        let (geometry, fitted_radius) = Geometry::from_ellipse(&ellipse);
+        assert!((geometry.tilt - shear.atan().to_degrees()).abs() < 0.2);
+        assert!((geometry.ratio - ratio).abs() < 0.005);
+        // the limb level is a quarter of the way up the anti-aliased edge
+        assert!((fitted_radius - radius - 0.25).abs() < 0.15);
+    }
+
+    #[test]
+    fn too_few_points_are_not_an_ellipse() {
+        let points = [(0., 1.), (1., 0.), (0., -1.)];
+        assert!(matches!(
+            Ellipse::fit(&points),
+            Err(GeometryError::TooFewPoints(3))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    asi::asi_api::ASIBayerPattern,
+    frame::{Frame, Pixels},
+};
+
+/// Pixels at or above this fraction of the full scale count as saturated.
+pub const SATURATION: f32 = 0.99;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Luminance,
+    Red,
+    Green,
+    Blue,
+}
+
+impl Channel {
+    /// Colour filter over a pixel of a bayer mosaic.
+    pub fn at(bayer_pattern: ASIBayerPattern, x: usize, y: usize) -> Self {
+        let layout = match bayer_pattern {
+            ASIBayerPattern::RG => [Channel::Red, Channel::Green, Channel::Green, Channel::Blue],
+            ASIBayerPattern::BG => [Channel::Blue, Channel::Green, Channel::Green, Channel::Red],
+            ASIBayerPattern::GR => [Channel::Green, Channel::Red, Channel::Blue, Channel::Green],
+            ASIBayerPattern::GB => [Channel::Green, Channel::Blue, Channel::Red, Channel::Green],
+        };
+        layout[(y % 2) * 2 + x % 2]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelHistogram {
+    pub channel: Channel,
+    pub counts: Vec<u32>,
+    pub total: u64,
+    pub saturated: u64,
+    pub black_clipped: u64,
+}
+
+impl ChannelHistogram {
+    fn new(channel: Channel, bins: usize) -> Self {
+        Self {
+            channel,
+            counts: vec![0; bins],
+            total: 0,
+            saturated: 0,
+            black_clipped: 0,
+        }
+    }
+
+    /// Upper edge of the bin below which `fraction` of the pixels lie, relative to the full scale.
+    pub fn percentile(&self, fraction: f64) -> f32 {
+        let threshold = (self.total as f64 * fraction).ceil() as u64;
+        let mut sum = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            sum += *count as u64;
+            if sum >= threshold {
+                return (i + 1) as f32 / self.counts.len() as f32;
+            }
+        }
+        1.
+    }
+}
+
+/// Distribution of the pixel values of a frame, per colour channel for colour cameras.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub channels: Vec<ChannelHistogram>,
+    /// Full scale of the frame in ADU.
+    pub max_value: f32,
+}
+
+impl Histogram {
+    pub fn compute(frame: &Frame, bins: usize) -> Self {
+        let max_value = frame.max_value();
+        let saturation = max_value * SATURATION;
+        let scale = bins as f32 / (max_value + 1.);
+
+        let mut channels = match (&frame.pixels, frame.bayer_pattern) {
+            (Pixels::Bgr24(_), _) | (_, Some(_)) => vec![
+                ChannelHistogram::new(Channel::Red, bins),
+                ChannelHistogram::new(Channel::Green, bins),
+                ChannelHistogram::new(Channel::Blue, bins),
+            ],
+            _ => vec![ChannelHistogram::new(Channel::Luminance, bins)],
+        };
+        let index = |channel: Channel| match channel {
+            Channel::Luminance | Channel::Red => 0,
+            Channel::Green => 1,
+            Channel::Blue => 2,
+        };
+
+        let mut add = |channel: Channel, value: f32| {
+            let histogram = &mut channels[index(channel)];
+            histogram.counts[(value * scale) as usize] += 1;
+            histogram.total += 1;
+            if value >= saturation {
+                histogram.saturated += 1;
+            } else if value <= 0. {
+                histogram.black_clipped += 1;
+            }
+        };
+
+        match &frame.pixels {
+            Pixels::Bgr24(p) => {
+                for bgr in p.chunks_exact(3) {
+                    add(Channel::Blue, bgr[0] as f32);
+                    add(Channel::Green, bgr[1] as f32);
+                    add(Channel::Red, bgr[2] as f32);
+                }
+            }
+            _ => {
+                for y in 0..frame.height {
+                    for x in 0..frame.width {
+                        let channel = match frame.bayer_pattern {
+                            Some(bayer_pattern) => Channel::at(bayer_pattern, x, y),
+                            None => Channel::Luminance,
+                        };
+                        add(channel, frame.value(x, y));
+                    }
+                }
+            }
+        }
+
+        Self {
+            channels,
+            max_value,
+        }
+    }
+
+    fn fraction(&self, count: impl Fn(&ChannelHistogram) -> u64) -> f32 {
+        let total: u64 = self.channels.iter().map(|c| c.total).sum();
+        self.channels.iter().map(count).sum::<u64>() as f32 / total.max(1) as f32
+    }
+
+    /// Fraction of the pixels at the full scale.
+    pub fn saturated(&self) -> f32 {
+        self.fraction(|c| c.saturated)
+    }
+
+    /// Fraction of the pixels at zero.
+    pub fn black_clipped(&self) -> f32 {
+        self.fraction(|c| c.black_clipped)
+    }
+
+    /// Brightest level of the frame relative to the full scale, ignoring hot pixels.
+    pub fn peak(&self) -> f32 {
+        self.channels
+            .iter()
+            .map(|c| c.percentile(0.999))
+            .fold(0., f32::max)
+    }
+}
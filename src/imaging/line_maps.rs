@@ -0,0 +1,280 @@
+use super::{solar_image::SolarImage, spectroheliogram::Extraction};
+use crate::{
+    frame::Frame,
+    spectrum::{
+        doppler::SPEED_OF_LIGHT,
+        fit,
+        line_fit::{LineFit, LineShape},
+        profile::Profile,
+    },
+};
+
+/// Model fitted to the line at every pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineModel {
+    /// Gaussian over a linear background, using the whole window.
+    Gaussian,
+    /// Parabola through the deepest pixel and its neighbours, much faster.
+    Parabola,
+}
+
+/// Line at one position along the slit of one frame.
+#[derive(Debug, Clone, Copy)]
+pub struct LineMeasurement {
+    /// Line centre from the line of the extraction in pixels.
+    pub shift: f64,
+    /// Full width at half depth in pixels.
+    pub fwhm: f64,
+    /// Fraction of the continuum absorbed at the centre.
+    pub depth: f64,
+}
+
+/// Parabola through the 5 pixels around the deepest one, the width from the half depth
+/// crossings.
+fn fit_parabola(values: &[f64]) -> Option<(f64, f64, f64)> {
+    let (core, _) = values
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))?;
+    if core < 2 || core + 2 >= values.len() {
+        return None;
+    }
+    let x: Vec<f64> = (-2..=2).map(|i| i as f64).collect();
+    let p = fit::polyfit(&x, &values[core - 2..=core + 2], 2)?;
+    if p[2] <= 0. {
+        return None;
+    }
+    let vertex = (-p[1] / (2. * p[2])).clamp(-2., 2.);
+    let min = fit::polyval(&p, vertex);
+
+    let max = |values: &[f64]| values.iter().cloned().fold(f64::MIN, f64::max);
+    let continuum = (max(&values[..=core]) + max(&values[core..])) / 2.;
+    if continuum <= min {
+        return None;
+    }
+    let half = (continuum + min) / 2.;
+    let crossing = |i: usize, j: usize| {
+        i as f64 + (half - values[i]) / (values[j] - values[i]) * (j as f64 - i as f64)
+    };
+    let left = (0..core).rev().find(|i| values[*i] >= half)?;
+    let right = (core + 1..values.len()).find(|i| values[*i] >= half)?;
+    let fwhm = crossing(right - 1, right) - crossing(left + 1, left);
+    Some((core as f64 + vertex, fwhm, 1. - min / continuum))
+}
+
+/// Fit the line within `half_window` pixels of the extraction line at every position
+/// along the slit of a frame.
+pub fn measure(
+    frame: &Frame,
+    extraction: &Extraction,
+    half_window: usize,
+    model: LineModel,
+) -> Vec<Option<LineMeasurement>> {
+    let (len, slit_len) = extraction.orientation.dimensions(frame);
+    (0..slit_len)
+        .map(|slit| {
+            let position = extraction.position(slit as f64);
+            let start = (position.round() as isize - half_window as isize).max(0) as usize;
+            let end = (start + 2 * half_window).min(len.checked_sub(1)?);
+            let values: Vec<f64> = (start..=end)
+                .map(|i| extraction.orientation.value(frame, i, slit) as f64)
+                .collect();
+            let (centre, fwhm, depth) = match model {
+                LineModel::Gaussian => {
+                    let guess = position - start as f64;
+                    let fit =
+                        LineFit::fit(&Profile { values }, guess, half_window, LineShape::Gaussian)?;
+                    let continuum = fit.background.0;
+                    if fit.amplitude >= 0. || continuum <= 0. {
+                        return None;
+                    }
+                    (fit.centre, fit.fwhm, -fit.amplitude / continuum)
+                }
+                LineModel::Parabola => fit_parabola(&values)?,
+            };
+            Some(LineMeasurement {
+                shift: start as f64 + centre - position,
+                fwhm,
+                depth,
+            })
+        })
+        .collect()
+}
+
+/// Line shift, width and depth at every pixel of a scan, one column per frame. Pixels
+/// where the fit failed have a depth of 0.
+#[derive(Debug, Clone, Default)]
+pub struct LineMaps {
+    pub shift: SolarImage,
+    pub fwhm: SolarImage,
+    pub depth: SolarImage,
+}
+
+impl LineMaps {
+    pub fn new(frames: usize, slit_len: usize) -> Self {
+        Self {
+            shift: SolarImage::new(frames, slit_len),
+            fwhm: SolarImage::new(frames, slit_len),
+            depth: SolarImage::new(frames, slit_len),
+        }
+    }
+
+    /// Store the measurements of frame `x`.
+    pub fn set_column(&mut self, x: usize, column: &[Option<LineMeasurement>]) {
+        if x >= self.shift.width {
+            return;
+        }
+        for (y, measurement) in column.iter().enumerate().take(self.shift.height) {
+            if let Some(measurement) = measurement {
+                self.shift.set(x, y, measurement.shift as f32);
+                self.fwhm.set(x, y, measurement.fwhm as f32);
+                self.depth.set(x, y, measurement.depth as f32);
+            }
+        }
+    }
+
+    /// Shift in pixels relative to the median over the disk, since the absolute line
+    /// position is not known to better than the calibration. Failed fits are 0.
+    pub fn relative_shift(&self) -> SolarImage {
+        let mut shifts: Vec<f32> = self
+            .shift
+            .values
+            .iter()
+            .zip(&self.depth.values)
+            .filter(|(_, depth)| **depth > 0.)
+            .map(|(shift, _)| *shift)
+            .collect();
+        shifts.sort_by(f32::total_cmp);
+        let median = shifts.get(shifts.len() / 2).copied().unwrap_or(0.);
+
+        let mut relative = self.shift.clone();
+        for (value, depth) in relative.values.iter_mut().zip(&self.depth.values) {
+            *value = if *depth > 0. { *value - median } else { 0. };
+        }
+        relative
+    }
+
+    /// Line of sight velocity in km/s relative to the disk, positive away from the
+    /// observer. `angstrom_per_pixel` and `wavelength` are those at the line.
+    pub fn velocity(&self, angstrom_per_pixel: f64, wavelength: f64) -> SolarImage {
+        let scale = (angstrom_per_pixel / wavelength * SPEED_OF_LIGHT) as f32;
+        let mut velocity = self.relative_shift();
+        velocity.values.iter_mut().for_each(|value| *value *= scale);
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::Pixels, spectrum::profile::SlitOrientation};
+
+    const FRAMES: usize = 12;
+    const SLIT_LEN: usize = 30;
+    const LINE: f64 = 60.;
+
+    /// Known shift, width and depth of the line at frame `x` and slit position `y`.
+    fn truth(x: usize, y: usize) -> LineMeasurement {
+        LineMeasurement {
+            shift: 0.8 * (x as f64 * 0.5).sin() + 0.02 * y as f64,
+            fwhm: 4. + 0.05 * y as f64,
+            depth: 0.3 + 0.02 * x as f64,
+        }
+    }
+
+    /// Slit frames of a scan with a gaussian absorption line.
+    fn cube() -> Vec<Frame> {
+        (0..FRAMES)
+            .map(|x| {
+                let width = 120;
+                let pixels = (0..SLIT_LEN)
+                    .flat_map(|y| {
+                        let line = truth(x, y);
+                        (0..width).map(move |i| {
+                            let offset = (i as f64 - LINE - line.shift) / line.fwhm;
+                            let profile = 1.
+                                - line.depth
+                                    * (-4. * std::f64::consts::LN_2 * offset * offset).exp();
+                            (20000. * profile).round() as u16
+                        })
+                    })
+                    .collect();
+                Frame {
+                    width,
+                    height: SLIT_LEN,
+                    pixels: Pixels::Mono16(pixels),
+                    bayer_pattern: None,
+                }
+            })
+            .collect()
+    }
+
+    fn maps(model: LineModel) -> LineMaps {
+        let extraction = Extraction {
+            orientation: SlitOrientation::Vertical,
+            line: LINE,
+            offsets: vec![0.],
+            width: 1,
+            smile: None,
+        };
+        let mut maps = LineMaps::new(FRAMES, SLIT_LEN);
+        for (x, frame) in cube().iter().enumerate() {
+            maps.set_column(x, &measure(frame, &extraction, 12, model));
+        }
+        maps
+    }
+
+    /// Largest error of each map.
+    fn errors(maps: &LineMaps) -> (f64, f64, f64) {
+        let mut errors = (0f64, 0f64, 0f64);
+        for x in 0..FRAMES {
+            for y in 0..SLIT_LEN {
+                let line = truth(x, y);
+                errors.0 = errors
+                    .0
+                    .max((maps.shift.value(x, y) as f64 - line.shift).abs());
+                errors.1 = errors
+                    .1
+                    .max((maps.fwhm.value(x, y) as f64 - line.fwhm).abs());
+                errors.2 = errors
+                    .2
+                    .max((maps.depth.value(x, y) as f64 - line.depth).abs());
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn gaussian_fits_recover_the_line_maps() {
+        let maps = maps(LineModel::Gaussian);
+        let (shift, fwhm, depth) = errors(&maps);
+        assert!(
+            shift < 0.01 && fwhm < 0.02 && depth < 0.005,
+            "{} {} {}",
+            shift,
+            fwhm,
+            depth
+        );
+
+        // velocities are relative to the median shift over the disk
+        let mut shifts = maps.shift.values.clone();
+        shifts.sort_by(f32::total_cmp);
+        let median = shifts[shifts.len() / 2] as f64;
+        let scale = 0.1 / 6562.8 * SPEED_OF_LIGHT;
+        let velocity = maps.velocity(0.1, 6562.8);
+        let expected = (truth(3, 7).shift - median) * scale;
+        assert!((velocity.value(3, 7) as f64 - expected).abs() < 0.01 * scale);
+    }
+
+    #[test]
+    fn parabola_fits_approximate_the_line_maps() {
+        let (shift, fwhm, depth) = errors(&maps(LineModel::Parabola));
+        assert!(
+            shift < 0.1 && fwhm < 0.3 && depth < 0.03,
+            "{} {} {}",
+            shift,
+            fwhm,
+            depth
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod colormap;
+pub mod dark_flat;
+pub mod enhancement;
+pub mod geometry;
+pub mod histogram;
+pub mod line_maps;
+pub mod presentation;
+pub mod rgb_image;
+pub mod solar_image;
+pub mod spectroheliogram;
+pub mod stretch;
+pub mod transversalium;
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{colormap::Palette, rgb_image::RgbImage, solar_image::SolarImage};
+use crate::ser::SerMetadata;
+
+/// Position angle of the solar north pole from the celestial north in degrees, positive
+/// to the east, from the low precision formulas of Meeus.
+pub fn p_angle(date: DateTime<Utc>) -> f64 {
+    let jd = date.timestamp_millis() as f64 / 86_400_000. + 2_440_587.5;
+    let t = (jd - 2_451_545.) / 36525.;
+
+    // apparent longitude of the Sun
+    let l0 = 280.46646 + 36000.76983 * t;
+    let m = (357.52911 + 35999.05029 * t).to_radians();
+    let centre =
+        (1.914602 - 0.004817 * t) * m.sin() + 0.019993 * (2. * m).sin() + 0.000289 * (3. * m).sin();
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let longitude = (l0 + centre - 0.00569 - 0.00478 * omega.sin()).to_radians();
+    let obliquity = (23.439291 - 0.0130042 * t + 0.00256 * omega.cos()).to_radians();
+
+    // longitude of the ascending node of the solar equator and its inclination
+    let node = (73.6667 + 1.3958333 * (jd - 2_396_758.) / 36525.).to_radians();
+    let inclination = 7.25f64.to_radians();
+
+    let x = (-longitude.cos() * obliquity.tan()).atan();
+    let y = (-(longitude - node).cos() * inclination.tan()).atan();
+    (x + y).to_degrees()
+}
+
+/// How images are coloured, oriented and labelled for display and export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Presentation {
+    /// Show the rendering in the image window instead of the raw values.
+    pub preview: bool,
+    /// Colour map, the preset of the line when `None`.
+    pub palette: Option<Palette>,
+    /// Display gamma, above 1 brightens the faint features.
+    pub gamma: f32,
+    pub negative: bool,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Counterclockwise rotation in degrees, applied after the flips.
+    pub rotation: f64,
+    /// Also rotate by the P angle of the scan date so that the solar north is up, for
+    /// images with the celestial north up.
+    pub solar_north: bool,
+    /// Label the date, the line and the instrument and observer of the SER metadata.
+    pub annotate: bool,
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        Self {
+            preview: false,
+            palette: None,
+            gamma: 1.,
+            negative: false,
+            flip_horizontal: false,
+            flip_vertical: false,
+            rotation: 0.,
+            solar_north: false,
+            annotate: true,
+        }
+    }
+}
+
+impl Presentation {
+    /// Colours of an image, scaled so that the brightest pixel is at full scale.
+    pub fn colorize(&self, image: &SolarImage, palette: Palette) -> RgbImage {
+        let scale = 1. / image.max().max(1e-6);
+        let exponent = 1. / self.gamma.max(0.05);
+        RgbImage {
+            width: image.width,
+            height: image.height,
+            pixels: image
+                .values
+                .iter()
+                .flat_map(|value| {
+                    let value = (value * scale).clamp(0., 1.).powf(exponent);
+                    palette.color(if self.negative { 1. - value } else { value })
+                })
+                .collect(),
+        }
+    }
+
+    /// Total counterclockwise rotation in degrees.
+    pub fn angle(&self, date: Option<DateTime<Utc>>) -> f64 {
+        match date.filter(|_| self.solar_north) {
+            Some(date) => self.rotation - p_angle(date),
+            None => self.rotation,
+        }
+    }
+
+    /// Flip and rotate an image.
+    pub fn orient(&self, image: &RgbImage, date: Option<DateTime<Utc>>) -> RgbImage {
+        image
+            .flip(self.flip_horizontal, self.flip_vertical)
+            .rotate(self.angle(date))
+    }
+
+    /// Position in the oriented image of a point of an image of `width` by `height`.
+    pub fn orient_point(
+        &self,
+        (x, y): (f64, f64),
+        width: usize,
+        height: usize,
+        date: Option<DateTime<Utc>>,
+    ) -> (f64, f64) {
+        let x = if self.flip_horizontal {
+            width as f64 - 1. - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical {
+            height as f64 - 1. - y
+        } else {
+            y
+        };
+        let angle = self.angle(date);
+        let (rotated_width, rotated_height) = if angle.rem_euclid(360.) == 0. {
+            (width, height)
+        } else {
+            RgbImage::rotated_size(width, height, angle)
+        };
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (dx, dy) = (x - (width as f64 - 1.) / 2., y - (height as f64 - 1.) / 2.);
+        (
+            (rotated_width as f64 - 1.) / 2. + dx * cos + dy * sin,
+            (rotated_height as f64 - 1.) / 2. - dx * sin + dy * cos,
+        )
+    }
+
+    /// Lines of the text overlay.
+    pub fn labels(
+        &self,
+        date: Option<DateTime<Utc>>,
+        line: &str,
+        metadata: &SerMetadata,
+    ) -> Vec<String> {
+        let mut labels = vec![];
+        if let Some(date) = date {
+            labels.push(date.format("%Y-%m-%d %H:%M UT").to_string());
+        }
+        labels.push(line.to_string());
+        labels.extend(
+            [&metadata.instrument, &metadata.observer]
+                .into_iter()
+                .filter(|text| !text.is_empty())
+                .cloned(),
+        );
+        labels
+    }
+}
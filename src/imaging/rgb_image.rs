@@ -0,0 +1,172 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+
+use crate::frame::{Frame, Pixels};
+
+/// 8 bit colour image for display and export.
+#[derive(Debug, Clone, Default)]
+pub struct RgbImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row major red, green and blue samples.
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let i = (y * self.width + x) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&color);
+    }
+
+    /// Mirror left to right and top to bottom.
+    pub fn flip(&self, horizontal: bool, vertical: bool) -> Self {
+        let mut flipped = Self::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let source_x = if horizontal { self.width - 1 - x } else { x };
+                let source_y = if vertical { self.height - 1 - y } else { y };
+                flipped.set(x, y, self.pixel(source_x, source_y));
+            }
+        }
+        flipped
+    }
+
+    /// Size of the image rotated by `angle` degrees.
+    pub fn rotated_size(width: usize, height: usize, angle: f64) -> (usize, usize) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (w, h) = (width as f64, height as f64);
+        (
+            (w * cos.abs() + h * sin.abs()).round().max(1.) as usize,
+            (w * sin.abs() + h * cos.abs()).round().max(1.) as usize,
+        )
+    }
+
+    /// Rotate counterclockwise by `angle` degrees about the centre, with bilinear
+    /// interpolation. The result is enlarged to hold the corners, filled with black.
+    pub fn rotate(&self, angle: f64) -> Self {
+        if angle.rem_euclid(360.) == 0. || self.width == 0 || self.height == 0 {
+            return self.clone();
+        }
+        let (width, height) = Self::rotated_size(self.width, self.height, angle);
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (cx, cy) = (
+            (self.width as f64 - 1.) / 2.,
+            (self.height as f64 - 1.) / 2.,
+        );
+        let (ox, oy) = ((width as f64 - 1.) / 2., (height as f64 - 1.) / 2.);
+
+        let mut rotated = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // inverse rotation, y pointing down
+                let (dx, dy) = (x as f64 - ox, y as f64 - oy);
+                let sx = cx + dx * cos - dy * sin;
+                let sy = cy + dx * sin + dy * cos;
+                if sx < 0. || sy < 0. || sx > self.width as f64 - 1. || sy > self.height as f64 - 1.
+                {
+                    continue;
+                }
+                let (x0, y0) = (sx as usize, sy as usize);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let (tx, ty) = (sx - x0 as f64, sy - y0 as f64);
+                let (a, b, c, d) = (
+                    self.pixel(x0, y0),
+                    self.pixel(x1, y0),
+                    self.pixel(x0, y1),
+                    self.pixel(x1, y1),
+                );
+                let mut color = [0; 3];
+                for k in 0..3 {
+                    let top = a[k] as f64 * (1. - tx) + b[k] as f64 * tx;
+                    let bottom = c[k] as f64 * (1. - tx) + d[k] as f64 * tx;
+                    color[k] = (top * (1. - ty) + bottom * ty).round() as u8;
+                }
+                rotated.set(x, y, color);
+            }
+        }
+        rotated
+    }
+
+    /// Draw lines of text from the bottom left corner, `size` pixels high. Returns
+    /// `false` when the font cannot be read.
+    pub fn draw_text(&mut self, font: &[u8], lines: &[String], size: f32, color: [u8; 3]) -> bool {
+        let Ok(font) = FontRef::try_from_slice(font) else {
+            return false;
+        };
+        let scale = PxScale::from(size);
+        let scaled = font.as_scaled(scale);
+        let line_height = scaled.height() + scaled.line_gap();
+        let margin = size / 2.;
+        let top = self.height as f32 - margin - line_height * lines.len() as f32;
+
+        for (i, line) in lines.iter().enumerate() {
+            let baseline = top + line_height * i as f32 + scaled.ascent();
+            let mut caret = margin;
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, baseline));
+                caret += scaled.h_advance(id);
+                let Some(outline) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let x = bounds.min.x as i64 + gx as i64;
+                    let y = bounds.min.y as i64 + gy as i64;
+                    if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+                        return;
+                    }
+                    let (x, y) = (x as usize, y as usize);
+                    let background = self.pixel(x, y);
+                    let mut blended = [0; 3];
+                    for k in 0..3 {
+                        blended[k] = (background[k] as f32 * (1. - coverage)
+                            + color[k] as f32 * coverage)
+                            .round() as u8;
+                    }
+                    self.set(x, y, blended);
+                });
+            }
+        }
+        true
+    }
+
+    pub fn to_frame(&self) -> Frame {
+        let mut bgr = self.pixels.clone();
+        for pixel in bgr.chunks_exact_mut(3) {
+            pixel.swap(0, 2);
+        }
+        Frame {
+            width: self.width,
+            height: self.height,
+            pixels: Pixels::Bgr24(bgr),
+            bayer_pattern: None,
+        }
+    }
+
+    pub fn save_png(&self, path: &Path) -> std::io::Result<()> {
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            self.width as u32,
+            self.height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
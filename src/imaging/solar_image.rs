@@ -0,0 +1,76 @@
+use crate::frame::{Frame, Pixels};
+
+/// Reconstructed image of the Sun, one column per frame of the scan and one row per
+/// position along the slit.
+#[derive(Debug, Clone, Default)]
+pub struct SolarImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row major intensities in ADU.
+    pub values: Vec<f32>,
+}
+
+impl SolarImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            values: vec![0.; width * height],
+        }
+    }
+
+    pub fn value(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+
+    /// Bilinear interpolation, zero outside of the image.
+    pub fn sample(&self, x: f64, y: f64) -> f32 {
+        if self.width == 0
+            || self.height == 0
+            || !(0. ..=(self.width - 1) as f64).contains(&x)
+            || !(0. ..=(self.height - 1) as f64).contains(&y)
+        {
+            return 0.;
+        }
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = ((x - x0 as f64) as f32, (y - y0 as f64) as f32);
+        let top = self.value(x0, y0) * (1. - tx) + self.value(x1, y0) * tx;
+        let bottom = self.value(x0, y1) * (1. - tx) + self.value(x1, y1) * tx;
+        top * (1. - ty) + bottom * ty
+    }
+
+    /// Value below which `fraction` of the pixels are.
+    pub fn percentile(&self, fraction: f64) -> f32 {
+        if self.values.is_empty() {
+            return 0.;
+        }
+        let mut sorted = self.values.clone();
+        sorted.sort_by(f32::total_cmp);
+        sorted[((sorted.len() - 1) as f64 * fraction.clamp(0., 1.)).round() as usize]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: f32) {
+        self.values[y * self.width + x] = value;
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().cloned().fold(0., f32::max)
+    }
+
+    /// 16 bit frame scaled so that the brightest pixel is at full scale, for display.
+    pub fn to_frame(&self) -> Frame {
+        let scale = u16::MAX as f32 / self.max().max(1e-6);
+        Frame {
+            width: self.width,
+            height: self.height,
+            pixels: Pixels::Mono16(
+                self.values
+                    .iter()
+                    .map(|value| (value.max(0.) * scale).round() as u16)
+                    .collect(),
+            ),
+            bayer_pattern: None,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use super::solar_image::SolarImage;
+use crate::{
+    frame::Frame,
+    spectrum::{profile::SlitOrientation, smile::Smile},
+};
+
+#[derive(Debug, Clone)]
+pub enum ReconstructionError {
+    /// The frames of a scan must all have the same slit length.
+    SlitLength { expected: usize, found: usize },
+}
+
+impl Display for ReconstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconstructionError::SlitLength { expected, found } => write!(
+                f,
+                "The slit is {} px long but the scan started with {} px.",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for ReconstructionError {}
+
+/// Where the intensity is read in each frame of a scan.
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    pub orientation: SlitOrientation,
+    /// Line core along the dispersion axis at the reference position of the smile.
+    pub line: f64,
+    /// Offsets from the line core in pixels of the images built in the same pass,
+    /// 0 for the core and away from it for the wings and the continuum.
+    pub offsets: Vec<f64>,
+    /// Pixels averaged along the dispersion axis.
+    pub width: usize,
+    /// Curve followed along the slit, a straight line at `line` when `None`.
+    pub smile: Option<Smile>,
+}
+
+impl Extraction {
+    /// Line core along the dispersion axis at a position along the slit.
+    pub fn position(&self, slit: f64) -> f64 {
+        let curvature = self
+            .smile
+            .as_ref()
+            .filter(|smile| smile.orientation == self.orientation)
+            .map_or(0., |smile| {
+                smile.centre(slit) - smile.centre(smile.reference)
+            });
+        self.line + curvature
+    }
+
+    /// Intensity at `offset` from the line at every position along the slit,
+    /// interpolated along the dispersion axis.
+    pub fn column(&self, frame: &Frame, offset: f64) -> Vec<f32> {
+        let (len, slit_len) = self.orientation.dimensions(frame);
+        let width = self.width.max(1);
+        (0..slit_len)
+            .map(|slit| {
+                let centre = self.position(slit as f64) + offset - (width - 1) as f64 / 2.;
+                let sum: f32 = (0..width)
+                    .map(|k| {
+                        let x = (centre + k as f64).clamp(0., (len - 1) as f64);
+                        let x0 = (x as usize).min(len.saturating_sub(2));
+                        let t = (x - x0 as f64) as f32;
+                        let at = |x: usize| self.orientation.value(frame, x, slit);
+                        at(x0) * (1. - t) + at((x0 + 1).min(len - 1)) * t
+                    })
+                    .sum();
+                sum / width as f32
+            })
+            .collect()
+    }
+}
+
+/// Solar images built column by column from the frames of a slit scan, one for each
+/// offset of the extraction.
+pub struct Spectroheliogram {
+    pub extraction: Extraction,
+    slit_len: usize,
+    /// Columns of every frame for each offset, shared with the snapshots.
+    columns: Vec<Vec<Arc<[f32]>>>,
+}
+
+impl Spectroheliogram {
+    pub fn new(extraction: Extraction) -> Self {
+        Self {
+            columns: vec![vec![]; extraction.offsets.len()],
+            extraction,
+            slit_len: 0,
+        }
+    }
+
+    /// Add the next frame of the scan.
+    pub fn push(&mut self, frame: &Frame) -> Result<(), ReconstructionError> {
+        let (_, slit_len) = self.extraction.orientation.dimensions(frame);
+        if self.is_empty() {
+            self.slit_len = slit_len;
+        } else if slit_len != self.slit_len {
+            return Err(ReconstructionError::SlitLength {
+                expected: self.slit_len,
+                found: slit_len,
+            });
+        }
+        for (columns, offset) in self.columns.iter_mut().zip(&self.extraction.offsets) {
+            columns.push(self.extraction.column(frame, *offset).into());
+        }
+        Ok(())
+    }
+
+    /// Number of frames in the scan.
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, |columns| columns.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Columns of the frames so far, taken without copying the values so that the images
+    /// can be assembled away from the scan.
+    pub fn snapshot(&self) -> ScanSnapshot {
+        ScanSnapshot {
+            slit_len: self.slit_len,
+            columns: self.columns.clone(),
+        }
+    }
+}
+
+/// Frames of a scan at one point of its progress, see [`Spectroheliogram::snapshot`].
+#[derive(Clone, Default)]
+pub struct ScanSnapshot {
+    slit_len: usize,
+    columns: Vec<Vec<Arc<[f32]>>>,
+}
+
+impl ScanSnapshot {
+    /// Number of frames in the scan.
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, |columns| columns.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Image of each offset, in the order of the extraction.
+    pub fn images(&self) -> Vec<SolarImage> {
+        self.columns
+            .iter()
+            .map(|columns| {
+                let mut image = SolarImage::new(columns.len(), self.slit_len);
+                for (x, column) in columns.iter().enumerate() {
+                    for (y, value) in column.iter().enumerate() {
+                        image.set(x, y, *value);
+                    }
+                }
+                image
+            })
+            .collect()
+    }
+}
+
+/// `(red - blue) / (red + blue)` of two wing images, positive where the line is shifted
+/// to the blue and the red wing brightens.
+pub fn doppler(red: &SolarImage, blue: &SolarImage) -> SolarImage {
+    let mut image = SolarImage::new(red.width.min(blue.width), red.height.min(blue.height));
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let (r, b) = (red.value(x, y), blue.value(x, y));
+            if r + b > 0. {
+                image.set(x, y, (r - b) / (r + b));
+            }
+        }
+    }
+    image
+}
@@ -0,0 +1,70 @@
+use crate::frame::{Frame, Pixels};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stretch {
+    Linear,
+    Log,
+    Asinh,
+}
+
+impl Stretch {
+    /// Map a value normalized between the black and white points to the display range.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Stretch::Linear => t,
+            Stretch::Log => (1. + 1000. * t).ln() / 1001_f32.ln(),
+            Stretch::Asinh => (10. * t).asinh() / 10_f32.asinh(),
+        }
+    }
+}
+
+/// Black and white points in ADU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    pub black: f32,
+    pub white: f32,
+}
+
+impl Levels {
+    /// Levels clipping the darkest and brightest `clip` fraction of the pixels.
+    pub fn auto(frame: &Frame, clip: f32) -> Self {
+        let mut samples = sample(frame, 100_000);
+        if samples.is_empty() {
+            return Self {
+                black: 0.,
+                white: 1.,
+            };
+        }
+        samples.sort_unstable_by(f32::total_cmp);
+        let at = |q: f32| samples[((samples.len() - 1) as f32 * q).round() as usize];
+        let black = at(clip);
+        let white = at(1. - clip).max(black + 1.);
+        Self { black, white }
+    }
+
+    pub fn normalize(&self, value: f32) -> f32 {
+        (value - self.black) / (self.white - self.black).max(f32::EPSILON)
+    }
+}
+
+/// Evenly spaced pixel values, at most `max` of them.
+fn sample(frame: &Frame, max: usize) -> Vec<f32> {
+    let count = frame.width * frame.height;
+    let step = (count / max).max(1);
+    (0..count)
+        .step_by(step)
+        .map(|i| frame.value(i % frame.width, i / frame.width))
+        .collect()
+}
+
+/// Lookup table from ADU to display value for every possible pixel value of the frame.
+pub fn lut(frame: &Frame, stretch: Stretch, levels: Levels) -> Vec<u8> {
+    let len = match frame.pixels {
+        Pixels::Mono16(_) => u16::MAX as usize + 1,
+        _ => u8::MAX as usize + 1,
+    };
+    (0..len)
+        .map(|value| (stretch.apply(levels.normalize(value as f32)) * 255.).round() as u8)
+        .collect()
+}
@@ -0,0 +1,170 @@
+use super::{geometry, solar_image::SolarImage};
+use crate::spectrum::fit;
+
+/// Rows on each side in the local fit giving the banding free disk profile.
+const HALF_WINDOW: usize = 12;
+/// Fewest disk pixels in a row for its banding to be measured.
+const MIN_PIXELS: usize = 10;
+/// Largest number of refits rejecting streaked rows.
+const CLIP_ITERATIONS: usize = 5;
+
+fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+/// Line through the points with the median of the pairwise slopes, insensitive to a
+/// few streaked rows.
+fn theil_sen(rows: &[(f64, f64)], at: f64) -> Option<Vec<f64>> {
+    let mut slopes = vec![];
+    for (i, a) in rows.iter().enumerate() {
+        for b in &rows[i + 1..] {
+            slopes.push(((b.1 - a.1) / (b.0 - a.0)) as f32);
+        }
+    }
+    let slope = median(&mut slopes)? as f64;
+    let mut intercepts: Vec<f32> = rows
+        .iter()
+        .map(|(x, y)| (y - slope * (x - at)) as f32)
+        .collect();
+    Some(vec![median(&mut intercepts)? as f64, slope])
+}
+
+/// Quadratic through the neighbouring rows evaluated at the middle one. It starts from
+/// a robust line and is refitted without the rows off by more than 3 robust sigmas so
+/// that streaks do not pull it.
+fn smooth(rows: &[(f64, f64)], at: f64) -> Option<f64> {
+    let fit = |rows: &[(f64, f64)]| {
+        let (x, y): (Vec<f64>, Vec<f64>) = rows.iter().map(|(x, y)| (x - at, *y)).unzip();
+        fit::polyfit(&x, &y, 2)
+    };
+    let mut kept = rows.to_vec();
+    let mut coefficients = theil_sen(&kept, at)?;
+    for _ in 0..CLIP_ITERATIONS {
+        let residual = |(x, y): &(f64, f64)| y - fit::polyval(&coefficients, x - at);
+        let mut deviations: Vec<f32> = kept.iter().map(|row| residual(row).abs() as f32).collect();
+        let sigma = 1.4826 * median(&mut deviations)? as f64;
+        let inliers: Vec<(f64, f64)> = kept
+            .iter()
+            .filter(|row| residual(row).abs() <= 3. * sigma)
+            .copied()
+            .collect();
+        if inliers.len() == kept.len() && coefficients.len() > 2 {
+            break;
+        }
+        match fit(&inliers) {
+            Some(refitted) => coefficients = refitted,
+            None => break,
+        }
+        kept = inliers;
+    }
+    Some(coefficients[0])
+}
+
+/// Multiplicative pattern of each row left by dust on the slit: the median of the row
+/// inside the limb over a local fit of the neighbouring rows. Rows off the disk are 1.
+pub fn banding(image: &SolarImage) -> Vec<f32> {
+    let level = geometry::limb_level(image);
+    let rows: Vec<Option<f32>> = (0..image.height)
+        .map(|y| {
+            let mut disk: Vec<f32> = (0..image.width)
+                .map(|x| image.value(x, y))
+                .filter(|value| *value >= level)
+                .collect();
+            (disk.len() >= MIN_PIXELS)
+                .then(|| median(&mut disk))
+                .flatten()
+        })
+        .collect();
+
+    (0..image.height)
+        .map(|y| {
+            let Some(row) = rows[y] else {
+                return 1.;
+            };
+            let start = y.saturating_sub(HALF_WINDOW);
+            let end = (y + HALF_WINDOW).min(image.height - 1);
+            let neighbours: Vec<(f64, f64)> = (start..=end)
+                .filter_map(|i| rows[i].map(|row| (i as f64, row as f64)))
+                .collect();
+            match smooth(&neighbours, y as f64) {
+                Some(smooth) if smooth > 0. => row / smooth as f32,
+                _ => 1.,
+            }
+        })
+        .collect()
+}
+
+/// Divide each row by its banding, `strength` from 0 for none to 1 for all of it.
+pub fn remove(image: &SolarImage, banding: &[f32], strength: f32) -> SolarImage {
+    let mut corrected = image.clone();
+    for (y, pattern) in banding.iter().enumerate().take(image.height) {
+        let gain = pattern.max(1e-3).powf(strength);
+        for x in 0..image.width {
+            corrected.set(x, y, image.value(x, y) / gain);
+        }
+    }
+    corrected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTRE: (f64, f64) = (150., 120.);
+    const RADIUS: f64 = 100.;
+    const STREAK: usize = 131;
+
+    /// Limb darkened disk with a gain of a few percent on each row and a dark streak.
+    fn banded_disk() -> (SolarImage, Vec<f32>) {
+        let gains: Vec<f32> = (0..240)
+            .map(|y| match y {
+                STREAK => 0.7,
+                // a deterministic pattern in place of dust
+                y => 1. + 0.02 * ((y * 7919 % 13) as f32 / 6. - 1.),
+            })
+            .collect();
+        let mut image = SolarImage::new(300, 240);
+        for (y, gain) in gains.iter().enumerate() {
+            for x in 0..image.width {
+                let rho =
+                    ((x as f64 - CENTRE.0).powi(2) + (y as f64 - CENTRE.1).powi(2)).sqrt() / RADIUS;
+                let value = if rho < 1. {
+                    1000. * (0.6 + 0.4 * (1. - rho * rho).sqrt()) * *gain as f64
+                } else {
+                    20.
+                };
+                image.set(x, y, value as f32);
+            }
+        }
+        (image, gains)
+    }
+
+    #[test]
+    fn row_gains_and_streaks_are_measured() {
+        let (image, gains) = banded_disk();
+        let banding = banding(&image);
+        // the disk profile changes too fast near the poles for the local fit
+        for y in (CENTRE.1 - 0.7 * RADIUS) as usize..(CENTRE.1 + 0.7 * RADIUS) as usize {
+            assert!(
+                (banding[y] - gains[y]).abs() < 0.01,
+                "row {}: {} for {}",
+                y,
+                banding[y],
+                gains[y]
+            );
+        }
+        assert_eq!(banding[5], 1.);
+
+        let corrected = remove(&image, &banding, 1.);
+        let centre = CENTRE.0 as usize;
+        let (above, streak) = (
+            corrected.value(centre, STREAK - 1),
+            corrected.value(centre, STREAK),
+        );
+        assert!((streak / above - 1.).abs() < 0.01, "{} {}", streak, above);
+    }
+}
@@ -4,6 +4,13 @@ use eframe::egui;
 use ui::app::App;
 
 mod asi;
+mod fits;
+mod frame;
+mod imaging;
+mod replay;
+mod ser;
+mod solex;
+mod spectrum;
 mod ui;
 
 fn main() -> Result<(), eframe::Error> {
@@ -19,7 +26,7 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(|cc| {
             // This gives us image support:
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Box::<App>::new(App::new())
+            Box::new(App::new(cc))
         }),
     )
 }
@@ -0,0 +1,177 @@
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+
+use crate::{
+    frame::{Frame, FrameSource},
+    ser::{SerHeader, SerReader},
+};
+
+/// Interval between frames of files without time stamps.
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(40);
+
+#[derive(Clone)]
+pub struct ReplayStatus {
+    pub name: String,
+    pub header: SerHeader,
+    /// Index of the published frame.
+    pub position: usize,
+    pub playing: bool,
+    /// Playback rate relative to the recording.
+    pub speed: f64,
+    /// Start over at the end instead of pausing.
+    pub looping: bool,
+    pub frame: Option<Arc<Frame>>,
+    /// Incremented for every published frame.
+    pub frame_count: u64,
+    /// UTC time of the published frame.
+    pub time: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+pub enum ReplayCommand {
+    Play,
+    Pause,
+    Seek(usize),
+    Speed(f64),
+    Looping(bool),
+}
+
+/// Plays the frames of a SER file on a worker thread at the pace they were recorded,
+/// publishing them like the camera driver.
+pub struct ReplayDriver {
+    status: Arc<Mutex<ReplayStatus>>,
+    sender: Sender<ReplayCommand>,
+}
+
+impl ReplayDriver {
+    pub fn open(path: &Path, ctx: egui::Context) -> Result<Self, String> {
+        let reader = SerReader::open(path).map_err(|e| e.to_string())?;
+        if reader.header.frame_count == 0 {
+            return Err("The file has no frames.".to_string());
+        }
+        let status = Arc::new(Mutex::new(ReplayStatus {
+            name: path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string()),
+            header: reader.header.clone(),
+            position: 0,
+            playing: true,
+            speed: 1.,
+            looping: false,
+            frame: None,
+            frame_count: 0,
+            time: None,
+            error: None,
+        }));
+        let (sender, receiver) = mpsc::channel();
+        {
+            let status = status.clone();
+            thread::spawn(move || run(reader, receiver, &status, &ctx));
+        }
+        Ok(Self { status, sender })
+    }
+
+    pub fn status(&self) -> MutexGuard<'_, ReplayStatus> {
+        self.status.lock().unwrap()
+    }
+
+    pub fn send(&self, command: ReplayCommand) {
+        let _ = self.sender.send(command);
+    }
+}
+
+impl FrameSource for ReplayDriver {
+    fn latest(&self) -> (Option<Arc<Frame>>, Option<DateTime<Utc>>, u64) {
+        let status = self.status();
+        (status.frame.clone(), status.time, status.frame_count)
+    }
+}
+
+/// Time to the next frame, from the trailer time stamps when they are there.
+fn interval(reader: &SerReader, index: usize) -> Duration {
+    match (reader.timestamp(index), reader.timestamp(index + 1)) {
+        (Some(from), Some(to)) => (to - from)
+            .to_std()
+            .ok()
+            .filter(|interval| *interval < Duration::from_secs(10))
+            .unwrap_or(DEFAULT_INTERVAL),
+        _ => DEFAULT_INTERVAL,
+    }
+}
+
+/// Publish frames until the driver is dropped.
+fn run(
+    reader: SerReader,
+    receiver: Receiver<ReplayCommand>,
+    status: &Mutex<ReplayStatus>,
+    ctx: &egui::Context,
+) {
+    let total = reader.header.frame_count;
+    let mut show = Some(0);
+    loop {
+        if let Some(index) = show.take() {
+            let frame = reader.frame(index);
+            let mut status = status.lock().unwrap();
+            status.position = index;
+            status.time = reader.timestamp(index);
+            match frame {
+                Ok(frame) => {
+                    status.frame = Some(Arc::new(frame));
+                    status.frame_count += 1;
+                }
+                Err(e) => {
+                    status.error = Some(e.to_string());
+                    status.playing = false;
+                }
+            }
+            ctx.request_repaint();
+        }
+
+        let (playing, position, speed) = {
+            let status = status.lock().unwrap();
+            (status.playing, status.position, status.speed)
+        };
+        // wait for the next frame, or for a command when paused
+        let command = if playing {
+            let wait = interval(&reader, position).div_f64(speed.max(0.01));
+            receiver.recv_timeout(wait)
+        } else {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+
+        let mut status = status.lock().unwrap();
+        match command {
+            Ok(ReplayCommand::Play) => {
+                status.playing = true;
+                if status.position + 1 >= total {
+                    show = Some(0);
+                }
+            }
+            Ok(ReplayCommand::Pause) => status.playing = false,
+            Ok(ReplayCommand::Seek(index)) => show = Some(index.min(total - 1)),
+            Ok(ReplayCommand::Speed(speed)) => status.speed = speed,
+            Ok(ReplayCommand::Looping(looping)) => status.looping = looping,
+            Err(RecvTimeoutError::Timeout) => {
+                if position + 1 < total {
+                    show = Some(position + 1);
+                } else if status.looping {
+                    show = Some(0);
+                } else {
+                    status.playing = false;
+                    ctx.request_repaint();
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
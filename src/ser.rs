@@ -0,0 +1,511 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Local, Utc};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asi::asi_api::ASIBayerPattern,
+    frame::{Frame, Pixels},
+};
+
+/// Size of the SER header in bytes.
+pub const HEADER_SIZE: u64 = 178;
+const FILE_ID: &[u8; 14] = b"LUCAM-RECORDER";
+/// 100 ns ticks from the year 1 of the SER dates to the Unix epoch.
+const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+
+/// 100 ns ticks since the year 1 of a SER date.
+fn ticks(date: DateTime<Utc>) -> i64 {
+    date.timestamp_micros() * 10 + UNIX_EPOCH_TICKS
+}
+
+/// Date of a SER time stamp, `None` for the zero of files without one.
+fn date(ticks: i64) -> Option<DateTime<Utc>> {
+    (ticks > UNIX_EPOCH_TICKS)
+        .then(|| DateTime::from_timestamp_micros((ticks - UNIX_EPOCH_TICKS) / 10))
+        .flatten()
+}
+
+#[derive(Debug)]
+pub enum SerError {
+    Io(io::Error),
+    NotSer,
+    UnsupportedColor(i32),
+    UnsupportedDepth(i32),
+    FrameOutOfRange(usize),
+    /// Frames of a recording must all have the size and format of the first one.
+    FrameMismatch,
+}
+
+impl Display for SerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerError::Io(e) => write!(f, "{}", e),
+            SerError::NotSer => write!(f, "Not a SER file."),
+            SerError::UnsupportedColor(id) => write!(f, "Unsupported SER colour format {}.", id),
+            SerError::UnsupportedDepth(depth) => {
+                write!(f, "Unsupported SER pixel depth of {} bits.", depth)
+            }
+            SerError::FrameOutOfRange(index) => write!(f, "There is no frame {}.", index),
+            SerError::FrameMismatch => {
+                write!(f, "The frame size or format changed during the recording.")
+            }
+        }
+    }
+}
+
+impl Error for SerError {}
+
+impl From<io::Error> for SerError {
+    fn from(e: io::Error) -> Self {
+        SerError::Io(e)
+    }
+}
+
+/// Pixel layout of a SER file, the `ColorID` field.
+#[derive(Debug, Clone, Copy)]
+pub enum SerColor {
+    Mono,
+    Bayer(ASIBayerPattern),
+    Rgb,
+    Bgr,
+}
+
+impl SerColor {
+    pub fn from_id(id: i32) -> Option<Self> {
+        Some(match id {
+            0 => SerColor::Mono,
+            8 => SerColor::Bayer(ASIBayerPattern::RG),
+            9 => SerColor::Bayer(ASIBayerPattern::GR),
+            10 => SerColor::Bayer(ASIBayerPattern::GB),
+            11 => SerColor::Bayer(ASIBayerPattern::BG),
+            100 => SerColor::Rgb,
+            101 => SerColor::Bgr,
+            _ => return None,
+        })
+    }
+
+    pub fn id(self) -> i32 {
+        match self {
+            SerColor::Mono => 0,
+            SerColor::Bayer(ASIBayerPattern::RG) => 8,
+            SerColor::Bayer(ASIBayerPattern::GR) => 9,
+            SerColor::Bayer(ASIBayerPattern::GB) => 10,
+            SerColor::Bayer(ASIBayerPattern::BG) => 11,
+            SerColor::Rgb => 100,
+            SerColor::Bgr => 101,
+        }
+    }
+
+    pub fn planes(self) -> usize {
+        match self {
+            SerColor::Rgb | SerColor::Bgr => 3,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SerHeader {
+    pub color: SerColor,
+    /// 16 bit samples are little endian. The specification inverts the meaning of the
+    /// flag but capture programs write 0 for little endian, which is followed here.
+    pub little_endian: bool,
+    pub width: usize,
+    pub height: usize,
+    /// Significant bits per sample, samples of more than 8 bits take two bytes.
+    pub pixel_depth: usize,
+    pub frame_count: usize,
+    /// Start of the recording, when the file has it.
+    pub date: Option<DateTime<Utc>>,
+}
+
+impl SerHeader {
+    fn parse(bytes: &[u8; HEADER_SIZE as usize]) -> Result<Self, SerError> {
+        if &bytes[..14] != FILE_ID {
+            return Err(SerError::NotSer);
+        }
+        let int = |offset: usize| {
+            i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+        };
+
+        let color_id = int(18);
+        let color = SerColor::from_id(color_id).ok_or(SerError::UnsupportedColor(color_id))?;
+        let ticks = i64::from_le_bytes(bytes[170..178].try_into().unwrap_or_default());
+        let date = date(ticks);
+        let pixel_depth = int(34);
+        if !(1..=16).contains(&pixel_depth) {
+            return Err(SerError::UnsupportedDepth(pixel_depth));
+        }
+        let (width, height) = (int(26), int(30));
+        if width <= 0 || height <= 0 {
+            return Err(SerError::NotSer);
+        }
+        let header = Self {
+            color,
+            little_endian: int(22) == 0,
+            width: width as usize,
+            height: height as usize,
+            pixel_depth: pixel_depth as usize,
+            frame_count: int(38).max(0) as usize,
+            date,
+        };
+        // frames larger than the address space are a corrupt header
+        header.checked_frame_size().ok_or(SerError::NotSer)?;
+        Ok(header)
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        if self.pixel_depth > 8 {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn checked_frame_size(&self) -> Option<usize> {
+        self.width
+            .checked_mul(self.height)?
+            .checked_mul(self.color.planes() * self.bytes_per_sample())
+    }
+
+    /// Bytes per frame, never 0 and without overflow for parsed headers.
+    pub fn frame_size(&self) -> usize {
+        self.width * self.height * self.color.planes() * self.bytes_per_sample()
+    }
+}
+
+/// Random access to the frames of a SER video, mapped in memory.
+pub struct SerReader {
+    pub header: SerHeader,
+    data: Mmap,
+}
+
+impl SerReader {
+    pub fn open(path: &Path) -> Result<Self, SerError> {
+        let file = File::open(path)?;
+        // the file is only read, other programs must not truncate it while it is open
+        let data = unsafe { Mmap::map(&file)? };
+        let bytes: &[u8; HEADER_SIZE as usize] = data
+            .get(..HEADER_SIZE as usize)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(SerError::NotSer)?;
+        let mut header = SerHeader::parse(bytes)?;
+        // files cut short by an interrupted recording
+        let available = (data.len() as u64 - HEADER_SIZE) / header.frame_size() as u64;
+        header.frame_count = header.frame_count.min(available as usize);
+        Ok(Self { header, data })
+    }
+
+    /// UTC time of a frame from the trailer, when the file has one.
+    pub fn timestamp(&self, index: usize) -> Option<DateTime<Utc>> {
+        let header = &self.header;
+        if index >= header.frame_count {
+            return None;
+        }
+        let trailer = HEADER_SIZE as usize + header.frame_count * header.frame_size();
+        let offset = trailer + index * 8;
+        date(i64::from_le_bytes(
+            self.data.get(offset..offset + 8)?.try_into().ok()?,
+        ))
+    }
+
+    /// Decode a frame. Samples of less than 16 bits are scaled to the full 16 bit range
+    /// like the camera delivers them, and 16 bit colour is reduced to 8 bits.
+    pub fn frame(&self, index: usize) -> Result<Frame, SerError> {
+        let header = &self.header;
+        if index >= header.frame_count {
+            return Err(SerError::FrameOutOfRange(index));
+        }
+        let size = header.frame_size();
+        let start = HEADER_SIZE as usize + index * size;
+        let data = &self.data[start..start + size];
+
+        let samples = || {
+            let shift = 16 - header.pixel_depth;
+            data.chunks_exact(2).map(move |b| {
+                let sample = if header.little_endian {
+                    u16::from_le_bytes([b[0], b[1]])
+                } else {
+                    u16::from_be_bytes([b[0], b[1]])
+                };
+                sample << shift
+            })
+        };
+        let pixels = match (header.color, header.bytes_per_sample()) {
+            (SerColor::Mono | SerColor::Bayer(_), 1) => Pixels::Mono8(data.to_vec()),
+            (SerColor::Mono | SerColor::Bayer(_), _) => Pixels::Mono16(samples().collect()),
+            (SerColor::Bgr, 1) => Pixels::Bgr24(data.to_vec()),
+            (SerColor::Bgr, _) => Pixels::Bgr24(samples().map(|v| (v >> 8) as u8).collect()),
+            (SerColor::Rgb, bytes) => {
+                let mut bgr: Vec<u8> = if bytes == 1 {
+                    data.to_vec()
+                } else {
+                    samples().map(|v| (v >> 8) as u8).collect()
+                };
+                for pixel in bgr.chunks_exact_mut(3) {
+                    pixel.swap(0, 2);
+                }
+                Pixels::Bgr24(bgr)
+            }
+        };
+        Ok(Frame {
+            width: header.width,
+            height: header.height,
+            pixels,
+            bayer_pattern: match header.color {
+                SerColor::Bayer(pattern) => Some(pattern),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Text fields of the SER header, truncated to 40 bytes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerMetadata {
+    pub observer: String,
+    pub instrument: String,
+    pub telescope: String,
+    /// Observing site, only written to FITS files.
+    pub site: String,
+}
+
+/// Writes frames to a SER video, with the UTC time of every frame in the trailer.
+pub struct SerWriter {
+    file: BufWriter<File>,
+    metadata: SerMetadata,
+    /// Layout of the first frame, that the others must match.
+    header: Option<SerHeader>,
+    timestamps: Vec<i64>,
+}
+
+impl SerWriter {
+    pub fn create(path: &Path, metadata: SerMetadata) -> Result<Self, SerError> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            metadata,
+            header: None,
+            timestamps: vec![],
+        })
+    }
+
+    fn header_bytes(&self, header: &SerHeader) -> [u8; HEADER_SIZE as usize] {
+        let mut bytes = [0; HEADER_SIZE as usize];
+        bytes[..14].copy_from_slice(FILE_ID);
+        let mut int = |offset: usize, value: i32| {
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        int(18, header.color.id());
+        int(22, if header.little_endian { 0 } else { 1 });
+        int(26, header.width as i32);
+        int(30, header.height as i32);
+        int(34, header.pixel_depth as i32);
+        int(38, self.timestamps.len() as i32);
+        for (offset, text) in [
+            (42, &self.metadata.observer),
+            (82, &self.metadata.instrument),
+            (122, &self.metadata.telescope),
+        ] {
+            let text = text.as_bytes();
+            let len = text.len().min(40);
+            bytes[offset..offset + len].copy_from_slice(&text[..len]);
+        }
+        let utc = header.date.map_or(0, ticks);
+        let offset = Local::now().offset().local_minus_utc() as i64 * 10_000_000;
+        bytes[162..170].copy_from_slice(&(utc + offset).to_le_bytes());
+        bytes[170..178].copy_from_slice(&utc.to_le_bytes());
+        bytes
+    }
+
+    /// Append a frame captured at `time`. The first frame sets the size and format.
+    pub fn write(&mut self, frame: &Frame, time: DateTime<Utc>) -> Result<(), SerError> {
+        let (color, pixel_depth) = match (&frame.pixels, frame.bayer_pattern) {
+            (Pixels::Mono8(_), None) => (SerColor::Mono, 8),
+            (Pixels::Mono8(_), Some(pattern)) => (SerColor::Bayer(pattern), 8),
+            (Pixels::Mono16(_), None) => (SerColor::Mono, 16),
+            (Pixels::Mono16(_), Some(pattern)) => (SerColor::Bayer(pattern), 16),
+            (Pixels::Bgr24(_), _) => (SerColor::Bgr, 8),
+        };
+        match &self.header {
+            None => {
+                let header = SerHeader {
+                    color,
+                    little_endian: true,
+                    width: frame.width,
+                    height: frame.height,
+                    pixel_depth,
+                    frame_count: 0,
+                    date: Some(time),
+                };
+                // a readable header from the start, the frame count is updated at the end
+                let bytes = self.header_bytes(&header);
+                self.file.write_all(&bytes)?;
+                self.header = Some(header);
+            }
+            Some(header) => {
+                if header.color.id() != color.id()
+                    || header.pixel_depth != pixel_depth
+                    || header.width != frame.width
+                    || header.height != frame.height
+                {
+                    return Err(SerError::FrameMismatch);
+                }
+            }
+        }
+
+        match &frame.pixels {
+            Pixels::Mono8(pixels) | Pixels::Bgr24(pixels) => self.file.write_all(pixels)?,
+            Pixels::Mono16(pixels) => {
+                let bytes: Vec<u8> = pixels.iter().flat_map(|v| v.to_le_bytes()).collect();
+                self.file.write_all(&bytes)?;
+            }
+        }
+        self.timestamps.push(ticks(time));
+        Ok(())
+    }
+
+    /// Write the trailer and the final frame count. Returns the number of frames.
+    pub fn finish(mut self) -> Result<usize, SerError> {
+        let Some(header) = self.header.take() else {
+            return Ok(0);
+        };
+        for timestamp in &self.timestamps {
+            self.file.write_all(&timestamp.to_le_bytes())?;
+        }
+        let bytes = self.header_bytes(&header);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&bytes)?;
+        self.file.flush()?;
+        Ok(self.timestamps.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(index: usize) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 4, 8, 18, 17, 0).unwrap()
+            + chrono::Duration::microseconds(index as i64 * 40_123)
+    }
+
+    /// Write the frames one `time` apart and read back the header, the frames and the
+    /// time stamps.
+    fn round_trip(
+        name: &str,
+        frames: &[Frame],
+    ) -> (SerHeader, Vec<Frame>, Vec<Option<DateTime<Utc>>>) {
+        let path = std::env::temp_dir().join(format!("{}_{}.ser", name, std::process::id()));
+        let metadata = SerMetadata {
+            observer: "Observer".to_string(),
+            ..Default::default()
+        };
+        let mut writer = SerWriter::create(&path, metadata).unwrap();
+        for (index, frame) in frames.iter().enumerate() {
+            writer.write(frame, time(index)).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), frames.len());
+
+        let reader = SerReader::open(&path).unwrap();
+        let count = reader.header.frame_count;
+        let read = (0..count).map(|i| reader.frame(i).unwrap()).collect();
+        let timestamps = (0..count).map(|i| reader.timestamp(i)).collect();
+        let header = reader.header.clone();
+        drop(reader);
+        fs::remove_file(&path).unwrap();
+        (header, read, timestamps)
+    }
+
+    fn assert_same(written: &Frame, read: &Frame) {
+        assert_eq!((written.width, written.height), (read.width, read.height));
+        let color = |frame: &Frame| frame.bayer_pattern.map(|p| SerColor::Bayer(p).id());
+        assert_eq!(color(written), color(read));
+        match (&written.pixels, &read.pixels) {
+            (Pixels::Mono8(a), Pixels::Mono8(b)) | (Pixels::Bgr24(a), Pixels::Bgr24(b)) => {
+                assert_eq!(a, b)
+            }
+            (Pixels::Mono16(a), Pixels::Mono16(b)) => assert_eq!(a, b),
+            _ => panic!("the pixel format changed"),
+        }
+    }
+
+    fn check(name: &str, frames: &[Frame], color_id: i32) {
+        let (header, read, timestamps) = round_trip(name, frames);
+        assert_eq!(header.frame_count, frames.len());
+        assert_eq!(header.color.id(), color_id);
+        assert_eq!(header.date, Some(time(0)));
+        for (written, read) in frames.iter().zip(&read) {
+            assert_same(written, read);
+        }
+        let expected: Vec<_> = (0..frames.len()).map(|i| Some(time(i))).collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[test]
+    fn empty_or_oversized_frames_are_rejected() {
+        let header = |width: i32, height: i32| {
+            let mut bytes = [0; HEADER_SIZE as usize];
+            bytes[..14].copy_from_slice(FILE_ID);
+            for (offset, value) in [(18, 100), (26, width), (30, height), (34, 16), (38, 1)] {
+                bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            SerHeader::parse(&bytes)
+        };
+        assert!(header(640, 480).is_ok());
+        for (width, height) in [(0, 480), (640, 0), (-1, 480), (i32::MAX, i32::MAX)] {
+            assert!(matches!(header(width, height), Err(SerError::NotSer)));
+        }
+    }
+
+    #[test]
+    fn mono8_round_trip() {
+        let frames: Vec<Frame> = (0..3)
+            .map(|i| Frame {
+                width: 5,
+                height: 3,
+                pixels: Pixels::Mono8((0..15).map(|v| v * 10 + i).collect()),
+                bayer_pattern: None,
+            })
+            .collect();
+        check("ser_mono8", &frames, 0);
+    }
+
+    #[test]
+    fn mono16_round_trip() {
+        let frames: Vec<Frame> = (0..2)
+            .map(|i| Frame {
+                width: 4,
+                height: 2,
+                pixels: Pixels::Mono16((0..8).map(|v| v * 8000 + i).collect()),
+                bayer_pattern: None,
+            })
+            .collect();
+        check("ser_mono16", &frames, 0);
+    }
+
+    #[test]
+    fn bayer_round_trip() {
+        let frames: Vec<Frame> = (0..2)
+            .map(|i| Frame {
+                width: 4,
+                height: 4,
+                pixels: Pixels::Mono16((0..16).map(|v| v * 4000 + i).collect()),
+                bayer_pattern: Some(ASIBayerPattern::GB),
+            })
+            .collect();
+        check("ser_bayer", &frames, 10);
+    }
+}
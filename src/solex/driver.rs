@@ -0,0 +1,154 @@
+use std::{
+    error::Error,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+use eframe::egui;
+
+use super::{
+    motor::{Motor, MotorCalibration},
+    solex_api::SolEX,
+};
+use crate::ui::app::ConnectionStatus;
+
+#[derive(Clone)]
+pub struct SolEXStatus {
+    pub connection_status: ConnectionStatus,
+    pub motor: Motor,
+    pub lamp_on: bool,
+    /// Commands sent and not yet done.
+    pub pending: usize,
+    pub error: Option<String>,
+}
+
+impl SolEXStatus {
+    pub fn is_busy(&self) -> bool {
+        self.pending > 0
+    }
+}
+
+pub enum SolEXCommand {
+    Home,
+    /// Relative moves in steps, as planned by [`Motor::plan_move`].
+    Move(Vec<i32>),
+    Speed(u8),
+    Lamp(bool),
+    Disconnect,
+}
+
+/// Owns the serial connection on a worker thread so that slow motor moves never block the UI.
+pub struct SolEXDriver {
+    status: Arc<Mutex<SolEXStatus>>,
+    sender: Option<Sender<SolEXCommand>>,
+}
+
+impl SolEXDriver {
+    pub fn new(calibration: MotorCalibration) -> Self {
+        Self {
+            status: Arc::new(Mutex::new(SolEXStatus {
+                connection_status: ConnectionStatus::Unconnected,
+                motor: Motor::new(calibration),
+                lamp_on: false,
+                pending: 0,
+                error: None,
+            })),
+            sender: None,
+        }
+    }
+
+    pub fn status(&self) -> MutexGuard<'_, SolEXStatus> {
+        self.status.lock().unwrap()
+    }
+
+    pub fn connect(&mut self, port_name: String, ctx: egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        self.sender = Some(sender);
+        {
+            let mut status = self.status();
+            status.connection_status = ConnectionStatus::Connecting;
+            status.error = None;
+        }
+
+        let status = self.status.clone();
+        thread::spawn(move || {
+            let result = match SolEX::open(&port_name) {
+                Ok(solex) => {
+                    status.lock().unwrap().connection_status = ConnectionStatus::Connected;
+                    ctx.request_repaint();
+                    run(solex, receiver, &status, &ctx)
+                }
+                Err(e) => Err(e),
+            };
+
+            let mut status = status.lock().unwrap();
+            if let Err(e) = result {
+                log::error!("Sol'EX {}: {}", port_name, e);
+                status.error = Some(e.to_string());
+            }
+            status.connection_status = ConnectionStatus::Unconnected;
+            status.motor.forget_position();
+            status.lamp_on = false;
+            status.pending = 0;
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            self.status().connection_status = ConnectionStatus::Disconnecting;
+            let _ = sender.send(SolEXCommand::Disconnect);
+        }
+    }
+
+    pub fn send(&self, command: SolEXCommand) {
+        if let Some(sender) = &self.sender {
+            self.status().pending += 1;
+            if sender.send(command).is_err() {
+                self.status().pending -= 1;
+            }
+        }
+    }
+}
+
+fn run(
+    mut solex: SolEX,
+    receiver: Receiver<SolEXCommand>,
+    status: &Mutex<SolEXStatus>,
+    ctx: &egui::Context,
+) -> Result<(), Box<dyn Error>> {
+    while let Ok(command) = receiver.recv() {
+        let result = match command {
+            SolEXCommand::Home => solex.home().and_then(|_| {
+                if !solex.limit_switch()? {
+                    return Err("Limit switch was not reached.".into());
+                }
+                status.lock().unwrap().motor.home_found();
+                Ok(())
+            }),
+            SolEXCommand::Move(moves) => moves.into_iter().try_for_each(|steps| {
+                solex.move_steps(steps)?;
+                status.lock().unwrap().motor.position += steps;
+                ctx.request_repaint();
+                Ok(())
+            }),
+            SolEXCommand::Speed(speed) => solex.set_speed(speed),
+            SolEXCommand::Lamp(on) => solex
+                .set_lamp(on)
+                .map(|_| status.lock().unwrap().lamp_on = on),
+            SolEXCommand::Disconnect => {
+                let _ = solex.set_lamp(false);
+                return Ok(());
+            }
+        };
+
+        let mut status = status.lock().unwrap();
+        status.pending = status.pending.saturating_sub(1);
+        status.error = result.err().map(|e| e.to_string());
+        ctx.request_repaint();
+    }
+    Ok(())
+}
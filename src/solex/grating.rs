@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// Geometry of the Sol'EX grating mount.
+/// The incident and diffracted beams are separated by a fixed deviation angle,
+/// so the grating equation reduces to `m * λ = 2 * d * cos(D / 2) * sin(θ)`
+/// where `θ` is the rotation of the grating.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grating {
+    pub lines_per_mm: f64,
+    pub order: i32,
+    /// Angle between the collimator and the camera lens axes in degrees.
+    pub deviation_angle: f64,
+    /// Focal length of the camera lens in millimeters.
+    pub camera_focal_length: f64,
+}
+
+impl Default for Grating {
+    fn default() -> Self {
+        Self {
+            lines_per_mm: 2400.,
+            order: 1,
+            deviation_angle: 34.,
+            camera_focal_length: 125.,
+        }
+    }
+}
+
+impl Grating {
+    /// Groove spacing in nanometers.
+    fn groove_spacing(&self) -> f64 {
+        1e6 / self.lines_per_mm
+    }
+
+    /// Wavelength in nanometers at the centre of the sensor for a grating angle in degrees.
+    pub fn wavelength(&self, angle: f64) -> f64 {
+        let half_deviation = (self.deviation_angle / 2.).to_radians();
+        2. * self.groove_spacing() * half_deviation.cos() * angle.to_radians().sin()
+            / self.order as f64
+    }
+
+    /// Grating angle in degrees bringing a wavelength in nanometers to the centre of the sensor.
+    pub fn angle(&self, wavelength: f64) -> Option<f64> {
+        let half_deviation = (self.deviation_angle / 2.).to_radians();
+        let sin =
+            wavelength * self.order as f64 / (2. * self.groove_spacing() * half_deviation.cos());
+        (-1.0..=1.0).contains(&sin).then(|| sin.asin().to_degrees())
+    }
+
+    /// Linear dispersion in nanometers per millimeter on the sensor for a grating angle in degrees.
+    pub fn linear_dispersion(&self, angle: f64) -> f64 {
+        let diffraction_angle = (angle - self.deviation_angle / 2.).to_radians();
+        self.groove_spacing() * diffraction_angle.cos()
+            / (self.order as f64 * self.camera_focal_length)
+    }
+}
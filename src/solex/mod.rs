@@ -0,0 +1,4 @@
+pub mod driver;
+pub mod grating;
+pub mod motor;
+pub mod solex_api;
@@ -0,0 +1,183 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HomeReference {
+    /// The firmware drives the grating onto the limit switch.
+    LimitSwitch,
+    /// The user centres a known spectral line (in nanometers) on the sensor.
+    SpectralLine(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ApproachDirection {
+    Forward,
+    Backward,
+}
+
+impl ApproachDirection {
+    fn sign(self) -> i32 {
+        match self {
+            Self::Forward => 1,
+            Self::Backward => -1,
+        }
+    }
+}
+
+/// Step to angle calibration of the grating motor.
+/// This is persisted between sessions, the motor position is not.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MotorCalibration {
+    pub steps_per_degree: f64,
+    /// Grating angle in degrees at step 0, i.e. at the home position.
+    pub home_angle: f64,
+    pub home_reference: HomeReference,
+    pub backlash_steps: i32,
+    pub approach_direction: ApproachDirection,
+    /// Soft limits of the grating angle in degrees.
+    pub min_angle: f64,
+    pub max_angle: f64,
+}
+
+impl Default for MotorCalibration {
+    fn default() -> Self {
+        Self {
+            steps_per_degree: 200.,
+            home_angle: 0.,
+            home_reference: HomeReference::LimitSwitch,
+            backlash_steps: 0,
+            approach_direction: ApproachDirection::Forward,
+            min_angle: 0.,
+            max_angle: 70.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MotorError {
+    NotHomed,
+    OutOfRange,
+}
+
+impl Display for MotorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MotorError::NotHomed => write!(f, "Motor is not homed."),
+            MotorError::OutOfRange => write!(f, "Target is outside of the soft limits."),
+        }
+    }
+}
+
+impl Error for MotorError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Motor {
+    pub calibration: MotorCalibration,
+    /// Position in steps relative to the home position.
+    pub position: i32,
+    pub homed: bool,
+    /// Grating angle at step 0 of this session when the position was referenced on a
+    /// spectral line, which replaces the calibrated home angle until the next homing.
+    reference_angle: Option<f64>,
+}
+
+impl Motor {
+    pub fn new(calibration: MotorCalibration) -> Self {
+        Self {
+            calibration,
+            position: 0,
+            homed: false,
+            reference_angle: None,
+        }
+    }
+
+    /// Grating angle at step 0.
+    fn zero_angle(&self) -> f64 {
+        self.reference_angle.unwrap_or(self.calibration.home_angle)
+    }
+
+    pub fn angle(&self) -> f64 {
+        self.angle_at(self.position)
+    }
+
+    pub fn angle_at(&self, steps: i32) -> f64 {
+        self.zero_angle() + steps as f64 / self.calibration.steps_per_degree
+    }
+
+    pub fn steps_at(&self, angle: f64) -> i32 {
+        ((angle - self.zero_angle()) * self.calibration.steps_per_degree).round() as i32
+    }
+
+    /// Soft limits once the motor position is known. Until then only jogs are possible,
+    /// to centre the reference line, and they are kept within one travel span of the
+    /// power-on position.
+    pub fn is_within_limits(&self, steps: i32) -> bool {
+        if !self.homed {
+            let span = (self.calibration.max_angle - self.calibration.min_angle)
+                * self.calibration.steps_per_degree;
+            return (steps.unsigned_abs() as f64) <= span.abs();
+        }
+        let angle = self.angle_at(steps);
+        (self.calibration.min_angle..=self.calibration.max_angle).contains(&angle)
+    }
+
+    /// Called after the firmware reported the limit switch.
+    pub fn home_found(&mut self) {
+        self.position = 0;
+        self.homed = true;
+        self.reference_angle = None;
+    }
+
+    /// Called when the position is no longer known, e.g. after a disconnection.
+    pub fn forget_position(&mut self) {
+        self.homed = false;
+        self.reference_angle = None;
+    }
+
+    /// Declare the current position to be at the given grating angle for this session.
+    /// The persisted home angle is left alone, the step count is only relative to the
+    /// power-on position.
+    pub fn set_reference(&mut self, angle: f64) {
+        self.reference_angle =
+            Some(angle - self.position as f64 / self.calibration.steps_per_degree);
+        self.homed = true;
+    }
+
+    /// Moves by `steps` relative to the current position, also without a reference.
+    pub fn plan_jog(&self, steps: i32) -> Result<Vec<i32>, MotorError> {
+        self.plan_move(self.position + steps)
+    }
+
+    /// Relative moves needed to reach `target` in steps.
+    /// The last move always approaches the target from the calibrated direction,
+    /// overshooting by the backlash when coming from the other side.
+    fn plan_move(&self, target: i32) -> Result<Vec<i32>, MotorError> {
+        let delta = target - self.position;
+        if delta == 0 {
+            return Ok(vec![]);
+        }
+
+        let sign = self.calibration.approach_direction.sign();
+        let backlash = self.calibration.backlash_steps.max(0);
+        if delta.signum() == sign || backlash == 0 {
+            if !self.is_within_limits(target) {
+                return Err(MotorError::OutOfRange);
+            }
+            return Ok(vec![delta]);
+        }
+
+        let overshoot = target - sign * backlash;
+        if !self.is_within_limits(target) || !self.is_within_limits(overshoot) {
+            return Err(MotorError::OutOfRange);
+        }
+        Ok(vec![overshoot - self.position, sign * backlash])
+    }
+
+    pub fn plan_move_to(&self, angle: f64) -> Result<Vec<i32>, MotorError> {
+        if !self.homed {
+            return Err(MotorError::NotHomed);
+        }
+        self.plan_move(self.steps_at(angle))
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{ErrorKind, Read, Write},
+    time::{Duration, Instant},
+};
+
+use serialport::SerialPort;
+
+const BAUD_RATE: u32 = 115_200;
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Homing and long moves only reply once the motor has stopped.
+const MOTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+pub enum SolEXError {
+    Timeout,
+    Rejected(String),
+    InvalidReply(String),
+}
+
+impl Display for SolEXError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolEXError::Timeout => write!(f, "Sol'EX did not reply in time."),
+            SolEXError::Rejected(reason) => write!(f, "Sol'EX rejected the command. {}", reason),
+            SolEXError::InvalidReply(reply) => write!(f, "Invalid reply from Sol'EX. {}", reply),
+        }
+    }
+}
+
+impl Error for SolEXError {}
+
+/// Names of the serial ports the Sol'EX controller may be connected to.
+pub fn available_ports() -> Vec<String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|port| port.port_name).collect())
+        .unwrap_or_default()
+}
+
+/// Line based ASCII protocol of the Sol'EX motor and lamp controller.
+/// Every command is answered with `OK [value]` or `ERR <reason>`.
+pub struct SolEX {
+    port: Box<dyn SerialPort>,
+}
+
+impl SolEX {
+    pub fn open(port_name: &str) -> Result<Self, Box<dyn Error>> {
+        let port = serialport::new(port_name, BAUD_RATE)
+            .timeout(READ_TIMEOUT)
+            .open()?;
+        let mut solex = Self { port };
+        solex.command("PING", REPLY_TIMEOUT)?;
+        Ok(solex)
+    }
+
+    fn read_line(&mut self, timeout: Duration) -> Result<String, Box<dyn Error>> {
+        let deadline = Instant::now() + timeout;
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            match self.port.read(&mut byte) {
+                Ok(0) => {}
+                Ok(_) if byte[0] == b'\n' => break,
+                Ok(_) => line.push(byte[0]),
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => return Err(e.into()),
+            }
+            if Instant::now() > deadline {
+                return Err(SolEXError::Timeout.into());
+            }
+        }
+        Ok(String::from_utf8_lossy(&line).trim().to_string())
+    }
+
+    fn command(&mut self, command: &str, timeout: Duration) -> Result<String, Box<dyn Error>> {
+        self.port.write_all(format!("{}\n", command).as_bytes())?;
+        self.port.flush()?;
+
+        let reply = self.read_line(timeout)?;
+        if let Some(value) = reply.strip_prefix("OK") {
+            Ok(value.trim().to_string())
+        } else if let Some(reason) = reply.strip_prefix("ERR") {
+            Err(SolEXError::Rejected(reason.trim().to_string()).into())
+        } else {
+            Err(SolEXError::InvalidReply(reply).into())
+        }
+    }
+
+    /// Move the grating by a relative number of steps, returns when the motor stopped.
+    pub fn move_steps(&mut self, steps: i32) -> Result<(), Box<dyn Error>> {
+        self.command(&format!("MOVE {}", steps), MOTION_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// Drive the grating onto the limit switch, the firmware resets its position to 0.
+    pub fn home(&mut self) -> Result<(), Box<dyn Error>> {
+        self.command("HOME", MOTION_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// Rotation speed from 1 (slowest) to 8 (fastest).
+    pub fn set_speed(&mut self, speed: u8) -> Result<(), Box<dyn Error>> {
+        self.command(&format!("SPEED {}", speed), REPLY_TIMEOUT)?;
+        Ok(())
+    }
+
+    pub fn set_lamp(&mut self, on: bool) -> Result<(), Box<dyn Error>> {
+        self.command(&format!("LAMP {}", on as u8), REPLY_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// Whether the limit switch is currently pressed.
+    pub fn limit_switch(&mut self) -> Result<bool, Box<dyn Error>> {
+        match self.command("LIMIT?", REPLY_TIMEOUT)?.as_str() {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            reply => Err(SolEXError::InvalidReply(reply.to_string()).into()),
+        }
+    }
+}
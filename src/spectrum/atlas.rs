@@ -0,0 +1,312 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{dispersion::Dispersion, lines::FRAUNHOFER, profile::Profile};
+use crate::fits::{self, FitsImage};
+
+/// Sampling of the line list model in Å.
+const MODEL_STEP: f64 = 0.02;
+/// Largest error of the predicted dispersion searched by the cross-correlation, in Å.
+const MAX_SHIFT: f64 = 60.;
+/// Largest relative error of the predicted Å per pixel.
+const MAX_SCALE_ERROR: f64 = 0.1;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Parse(usize),
+    Empty,
+    /// FITS spectra need a wavelength axis.
+    NoWavelengths,
+}
+
+impl Display for AtlasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AtlasError::Parse(line) => write!(f, "Invalid atlas data on line {}.", line),
+            AtlasError::Empty => write!(f, "The atlas has no data."),
+            AtlasError::NoWavelengths => write!(f, "The FITS spectrum has no wavelength axis."),
+        }
+    }
+}
+
+impl Error for AtlasError {}
+
+/// Solar reference spectrum, normalized to the continuum.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub name: String,
+    /// File the atlas was loaded from, `None` for the line list model.
+    pub path: Option<PathBuf>,
+    /// Ascending wavelengths in Å.
+    pub wavelengths: Vec<f64>,
+    pub flux: Vec<f64>,
+}
+
+impl Atlas {
+    /// Spectrum synthesized from the Fraunhofer line list. Only the line positions are
+    /// reliable, it is not a solar atlas and has no continuum or weak lines.
+    pub fn line_list_model() -> Self {
+        let start = 3850.;
+        let end = 6800.;
+        let wavelengths: Vec<f64> = (0..((end - start) / MODEL_STEP) as usize)
+            .map(|i| start + i as f64 * MODEL_STEP)
+            .collect();
+        let flux = wavelengths
+            .iter()
+            .map(|wavelength| {
+                FRAUNHOFER
+                    .iter()
+                    .filter(|line| (line.line.wavelength - wavelength).abs() < 30. * line.width)
+                    .map(|line| {
+                        // pseudo-Voigt, the lorentzian part gives the wings of strong lines
+                        let x = (wavelength - line.line.wavelength) / (line.width / 2.);
+                        let gauss = (-x * x * std::f64::consts::LN_2).exp();
+                        let lorentz = 1. / (1. + x * x);
+                        1. - line.depth * (0.7 * gauss + 0.3 * lorentz)
+                    })
+                    .product()
+            })
+            .collect();
+        Self {
+            name: "Fraunhofer line list model".to_string(),
+            path: None,
+            wavelengths,
+            flux,
+        }
+    }
+
+    /// Two columns of wavelength and flux separated by spaces, tabs or commas.
+    /// Wavelengths in nm are converted to Å, lines starting with `#` are skipped.
+    /// FITS files are read as a spectrum along the wavelength axis of their header.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if fits::is_fits(path) {
+            return Self::load_fits(path);
+        }
+        let text = fs::read_to_string(path)?;
+        let mut samples = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|column| !column.is_empty())
+                .map(str::parse::<f64>);
+            match (columns.next(), columns.next()) {
+                (Some(Ok(wavelength)), Some(Ok(flux))) => samples.push((wavelength, flux)),
+                // header line
+                _ if samples.is_empty() => continue,
+                _ => return Err(AtlasError::Parse(i + 1).into()),
+            }
+        }
+        if samples.is_empty() {
+            return Err(AtlasError::Empty.into());
+        }
+
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let unit = if samples.last().unwrap().0 < 1200. {
+            10.
+        } else {
+            1.
+        };
+        Ok(Self::from_samples(path, samples, unit))
+    }
+
+    fn load_fits(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let image = FitsImage::open(path)?;
+        let dispersion = image.dispersion().ok_or(AtlasError::NoWavelengths)?;
+        // the first row of two dimensional spectra
+        let len = image.shape[0];
+        let mut samples: Vec<(f64, f64)> = image
+            .data
+            .values()
+            .iter()
+            .take(len)
+            .enumerate()
+            .map(|(i, flux)| (dispersion.wavelength(i as f64), *flux))
+            .filter(|(_, flux)| flux.is_finite())
+            .collect();
+        if samples.is_empty() {
+            return Err(AtlasError::Empty.into());
+        }
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self::from_samples(path, samples, 1.))
+    }
+
+    /// Atlas of samples sorted by wavelength, scaled to Å by `unit` and to a peak of 1.
+    fn from_samples(path: &Path, samples: Vec<(f64, f64)>, unit: f64) -> Self {
+        let max = samples.iter().map(|s| s.1).fold(f64::MIN, f64::max);
+        Self {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: Some(path.to_path_buf()),
+            wavelengths: samples.iter().map(|s| s.0 * unit).collect(),
+            flux: samples.iter().map(|s| s.1 / max).collect(),
+        }
+    }
+
+    /// Atlas averaged over bins of `step` Å from `start`, interpolated where sparser.
+    pub fn resample(&self, start: f64, step: f64, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|i| {
+                let low = start + (i as f64 - 0.5) * step;
+                let high = low + step;
+                let first = self.wavelengths.partition_point(|w| *w < low);
+                let last = self.wavelengths.partition_point(|w| *w < high);
+                if last > first {
+                    self.flux[first..last].iter().sum::<f64>() / (last - first) as f64
+                } else {
+                    self.flux_at(start + i as f64 * step)
+                }
+            })
+            .collect()
+    }
+
+    /// Linearly interpolated flux, the continuum outside of the atlas.
+    pub fn flux_at(&self, wavelength: f64) -> f64 {
+        let i = self.wavelengths.partition_point(|w| *w < wavelength);
+        if i == 0 || i == self.wavelengths.len() {
+            return 1.;
+        }
+        let (w0, w1) = (self.wavelengths[i - 1], self.wavelengths[i]);
+        let t = (wavelength - w0) / (w1 - w0);
+        self.flux[i - 1] * (1. - t) + self.flux[i] * t
+    }
+}
+
+/// Convolve with a normalized gaussian of `sigma` samples, repeating the edge values.
+pub fn convolve_gaussian(values: &[f64], sigma: f64) -> Vec<f64> {
+    if sigma <= 0. || values.is_empty() {
+        return values.to_vec();
+    }
+    let radius = (3. * sigma).ceil() as isize;
+    let kernel: Vec<f64> = (-radius..=radius)
+        .map(|i| (-(i * i) as f64 / (2. * sigma * sigma)).exp())
+        .collect();
+    let norm: f64 = kernel.iter().sum();
+    let last = values.len() as isize - 1;
+    (0..values.len() as isize)
+        .map(|i| {
+            kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| {
+                    weight * values[(i + k as isize - radius).clamp(0, last) as usize]
+                })
+                .sum::<f64>()
+                / norm
+        })
+        .collect()
+}
+
+/// Pearson correlation coefficient.
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+    let (mut ab, mut aa, mut bb) = (0., 0., 0.);
+    for (a, b) in a.iter().zip(b) {
+        ab += (a - mean_a) * (b - mean_b);
+        aa += (a - mean_a).powi(2);
+        bb += (b - mean_b).powi(2);
+    }
+    ab / (aa * bb).sqrt().max(1e-12)
+}
+
+/// Dispersion `λ = centre + scale * (predicted(x) - centre) + shift`.
+fn transformed(predicted: &Dispersion, centre: f64, scale: f64, shift: f64) -> Dispersion {
+    let mut coefficients: Vec<f64> = predicted.coefficients.iter().map(|c| c * scale).collect();
+    coefficients[0] += centre * (1. - scale) + shift;
+    Dispersion { coefficients }
+}
+
+/// Linear correction of the predicted dispersion that best lines up the profile with the
+/// atlas convolved to `resolution` Å FWHM. Returns the dispersion and the correlation.
+pub fn cross_correlate(
+    profile: &Profile,
+    atlas: &Atlas,
+    predicted: &Dispersion,
+    resolution: f64,
+) -> Option<(Dispersion, f64)> {
+    let len = profile.values.len();
+    if len < 10 {
+        return None;
+    }
+    let observed = profile.detrended().values;
+    let angstrom_per_pixel = predicted.angstrom_per_pixel(len as f64 / 2.).abs();
+    let centre = predicted.wavelength(len as f64 / 2.);
+
+    // atlas on a regular grid covering every candidate solution
+    let margin = MAX_SHIFT + MAX_SCALE_ERROR * len as f64 * angstrom_per_pixel;
+    let low = predicted
+        .wavelength(0.)
+        .min(predicted.wavelength(len as f64))
+        - margin;
+    let high = predicted
+        .wavelength(0.)
+        .max(predicted.wavelength(len as f64))
+        + margin;
+    let step = angstrom_per_pixel / 4.;
+    let grid_len = ((high - low) / step) as usize + 1;
+    let model = convolve_gaussian(
+        &atlas.resample(low, step, grid_len),
+        resolution / 2.3548 / step,
+    );
+    let model_at = |wavelength: f64| {
+        let position = ((wavelength - low) / step).clamp(0., (grid_len - 1) as f64);
+        let i = (position as usize).min(grid_len - 2);
+        let t = position - i as f64;
+        model[i] * (1. - t) + model[i + 1] * t
+    };
+
+    let score = |scale: f64, shift: f64, stride: usize| {
+        let dispersion = transformed(predicted, centre, scale, shift);
+        let (observed, expected): (Vec<f64>, Vec<f64>) = (0..len)
+            .step_by(stride)
+            .map(|x| (observed[x], model_at(dispersion.wavelength(x as f64))))
+            .unzip();
+        correlation(&observed, &expected)
+    };
+
+    let search = |scales: Vec<f64>, shifts: Vec<f64>, stride: usize| {
+        let mut best = (f64::MIN, 1., 0.);
+        for scale in &scales {
+            for shift in &shifts {
+                let value = score(*scale, *shift, stride);
+                if value > best.0 {
+                    best = (value, *scale, *shift);
+                }
+            }
+        }
+        best
+    };
+    let range = |centre: f64, half: f64, step: f64| -> Vec<f64> {
+        let n = (half / step).round() as i32;
+        (-n..=n).map(|i| centre + i as f64 * step).collect()
+    };
+
+    // coarse search over the whole range on a subset of the pixels,
+    // then refine around the maximum
+    let (_, scale, shift) = search(
+        range(1., MAX_SCALE_ERROR, 0.01),
+        range(0., MAX_SHIFT, angstrom_per_pixel),
+        4,
+    );
+    let (_, scale, shift) = search(
+        range(scale, 0.01, 0.001),
+        range(shift, 2. * angstrom_per_pixel, angstrom_per_pixel / 4.),
+        1,
+    );
+    let (correlation, scale, shift) = search(
+        range(scale, 0.001, 0.0002),
+        range(shift, angstrom_per_pixel / 4., angstrom_per_pixel / 20.),
+        1,
+    );
+    Some((transformed(predicted, centre, scale, shift), correlation))
+}
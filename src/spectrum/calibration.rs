@@ -0,0 +1,396 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::{dispersion::Dispersion, fit, lines::SpectralLine, profile::Profile};
+
+/// Largest error of the predicted dispersion searched for when matching lines, in Å.
+const MAX_SHIFT: f64 = 60.;
+/// Largest relative error of the predicted Å per pixel.
+const MAX_SCALE_ERROR: f64 = 0.1;
+/// Half width of the centroid window in pixels.
+const CENTROID_RADIUS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Peak {
+    /// Sub-pixel centroid along the dispersion axis.
+    pub position: f64,
+    /// Height above the background in ADU.
+    pub amplitude: f64,
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.;
+    }
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+/// Emission peaks higher than `sigma` times the noise above the background.
+pub fn find_peaks(profile: &Profile, sigma: f64) -> Vec<Peak> {
+    let values = &profile.values;
+    let background = median(&mut values.clone());
+    let noise = 1.4826
+        * median(
+            &mut values
+                .iter()
+                .map(|value| (value - background).abs())
+                .collect::<Vec<_>>(),
+        );
+    let threshold = background + sigma * noise.max(1e-6);
+
+    let mut peaks = vec![];
+    for i in 1..values.len().saturating_sub(1) {
+        if values[i] <= threshold || values[i] < values[i - 1] || values[i] <= values[i + 1] {
+            continue;
+        }
+        // centroid of the pixels above half height
+        let half = (values[i] + background) / 2.;
+        let mut start = i;
+        while start > 0 && i - start < CENTROID_RADIUS && values[start - 1] > half {
+            start -= 1;
+        }
+        let mut end = i;
+        while end + 1 < values.len() && end - i < CENTROID_RADIUS && values[end + 1] > half {
+            end += 1;
+        }
+        let (sum, weighted) = (start..=end).fold((0., 0.), |(sum, weighted), x| {
+            let weight = values[x] - background;
+            (sum + weight, weighted + weight * x as f64)
+        });
+        peaks.push(Peak {
+            position: weighted / sum,
+            amplitude: values[i] - background,
+        });
+    }
+    peaks
+}
+
+/// Absorption lines deeper than `sigma` times the noise below the continuum.
+pub fn find_absorption_lines(profile: &Profile, sigma: f64) -> Vec<Peak> {
+    let inverted = Profile {
+        values: profile
+            .detrended()
+            .values
+            .iter()
+            .map(|value| 1. - value)
+            .collect(),
+    };
+    find_peaks(&inverted, sigma)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineMatch {
+    pub pixel: f64,
+    /// Reference wavelength in Å.
+    pub wavelength: f64,
+    pub name: String,
+    /// Reference minus fitted wavelength in Å.
+    pub residual: f64,
+}
+
+/// Pixel to wavelength solution measured from reference lines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WavelengthCalibration {
+    pub dispersion: Dispersion,
+    pub matches: Vec<LineMatch>,
+    /// RMS of the residuals in Å.
+    pub rms: f64,
+    /// Motor position the solution was measured at, it is only valid there.
+    pub motor_position: i32,
+    pub bin: i32,
+    /// Name of the reference spectrum.
+    #[serde(default)]
+    pub reference: String,
+    /// Correlation with the atlas for solar calibrations.
+    #[serde(default)]
+    pub correlation: Option<f64>,
+    /// Degree asked for, the fit falls back to lower degrees when too few lines match.
+    #[serde(default)]
+    pub requested_degree: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum CalibrationError {
+    NoPeaks,
+    TooFewMatches(usize),
+    FitFailed,
+}
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationError::NoPeaks => write!(f, "No lines were detected."),
+            CalibrationError::TooFewMatches(count) => {
+                write!(f, "Only {} lines matched the line list.", count)
+            }
+            CalibrationError::FitFailed => write!(f, "The dispersion fit failed."),
+        }
+    }
+}
+
+impl Error for CalibrationError {}
+
+/// Pair each position with the closest line within `tolerance` Å, each line used once.
+fn match_lines<'a>(
+    positions: &[f64],
+    dispersion: &Dispersion,
+    lines: &'a [SpectralLine],
+    tolerance: f64,
+) -> Vec<(f64, &'a SpectralLine)> {
+    let mut matches: Vec<(f64, &SpectralLine, f64)> = vec![];
+    for position in positions {
+        let wavelength = dispersion.wavelength(*position);
+        let Some(line) = lines.iter().min_by(|a, b| {
+            (a.wavelength - wavelength)
+                .abs()
+                .total_cmp(&(b.wavelength - wavelength).abs())
+        }) else {
+            continue;
+        };
+        let error = (line.wavelength - wavelength).abs();
+        if error > tolerance {
+            continue;
+        }
+        match matches
+            .iter_mut()
+            .find(|(_, other, _)| std::ptr::eq(*other, line))
+        {
+            Some(other) if other.2 > error => *other = (*position, line, error),
+            Some(_) => {}
+            None => matches.push((*position, line, error)),
+        }
+    }
+    matches.sort_by(|a, b| a.0.total_cmp(&b.0));
+    matches
+        .into_iter()
+        .map(|(position, line, _)| (position, line))
+        .collect()
+}
+
+/// Linear dispersion through a pair of positions and a pair of lines that lines up the
+/// most positions with lines, within `MAX_SHIFT` and `MAX_SCALE_ERROR` of the prediction.
+fn best_linear(
+    positions: &[f64],
+    predicted: &Dispersion,
+    lines: &[SpectralLine],
+    tolerance: f64,
+) -> Option<Dispersion> {
+    let (first, last) = positions
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), x| {
+            (min.min(*x), max.max(*x))
+        });
+    let range = predicted.wavelength(first).min(predicted.wavelength(last)) - MAX_SHIFT
+        ..=predicted.wavelength(first).max(predicted.wavelength(last)) + MAX_SHIFT;
+    let candidates: Vec<&SpectralLine> = lines
+        .iter()
+        .filter(|line| range.contains(&line.wavelength))
+        .collect();
+    let scale = predicted.angstrom_per_pixel((first + last) / 2.);
+
+    let mut best: Option<(usize, f64, Dispersion)> = None;
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            for (j, line_a) in candidates.iter().enumerate() {
+                for line_b in &candidates[j + 1..] {
+                    let (a, b) = if a < b { (a, b) } else { (b, a) };
+                    let (line_a, line_b) = if scale > 0. {
+                        (line_a, line_b)
+                    } else {
+                        (line_b, line_a)
+                    };
+                    let slope = (line_b.wavelength - line_a.wavelength) / (b - a);
+                    if (slope / scale - 1.).abs() > MAX_SCALE_ERROR {
+                        continue;
+                    }
+                    let dispersion = Dispersion {
+                        coefficients: vec![line_a.wavelength - slope * a, slope],
+                    };
+                    let centre = (first + last) / 2.;
+                    if (dispersion.wavelength(centre) - predicted.wavelength(centre)).abs()
+                        > MAX_SHIFT
+                    {
+                        continue;
+                    }
+
+                    let matches = match_lines(positions, &dispersion, lines, tolerance);
+                    let error: f64 = matches
+                        .iter()
+                        .map(|(position, line)| {
+                            (dispersion.wavelength(*position) - line.wavelength).abs()
+                        })
+                        .sum();
+                    if best.as_ref().is_none_or(|(count, best_error, _)| {
+                        matches.len() > *count || (matches.len() == *count && error < *best_error)
+                    }) {
+                        best = Some((matches.len(), error, dispersion));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_, _, dispersion)| dispersion)
+}
+
+/// Fit a polynomial of `degree` through the matched lines.
+pub fn fit_dispersion(
+    matches: &[(f64, &SpectralLine)],
+    degree: usize,
+) -> Result<(Dispersion, Vec<LineMatch>, f64), CalibrationError> {
+    let pixels: Vec<f64> = matches.iter().map(|(pixel, _)| *pixel).collect();
+    let wavelengths: Vec<f64> = matches.iter().map(|(_, line)| line.wavelength).collect();
+    let dispersion = Dispersion {
+        coefficients: fit::polyfit(&pixels, &wavelengths, degree)
+            .ok_or(CalibrationError::FitFailed)?,
+    };
+
+    let matches: Vec<LineMatch> = matches
+        .iter()
+        .map(|(pixel, line)| LineMatch {
+            pixel: *pixel,
+            wavelength: line.wavelength,
+            name: line.name.to_string(),
+            residual: line.wavelength - dispersion.wavelength(*pixel),
+        })
+        .collect();
+    let rms =
+        (matches.iter().map(|m| m.residual.powi(2)).sum::<f64>() / matches.len() as f64).sqrt();
+    Ok((dispersion, matches, rms))
+}
+
+/// Identify the `positions` of measured lines in `lines` starting from the predicted
+/// dispersion, then fit the solution.
+pub fn identify(
+    positions: &[f64],
+    predicted: &Dispersion,
+    lines: &[SpectralLine],
+    degree: usize,
+) -> Result<(Dispersion, Vec<LineMatch>, f64), CalibrationError> {
+    if positions.is_empty() {
+        return Err(CalibrationError::NoPeaks);
+    }
+    let angstrom_per_pixel = predicted.angstrom_per_pixel(0.).abs();
+
+    let dispersion = best_linear(positions, predicted, lines, 2. * angstrom_per_pixel)
+        .ok_or(CalibrationError::TooFewMatches(0))?;
+    refine(positions, dispersion, lines, degree)
+}
+
+/// Fit the solution of `degree` through the lines identified with an approximate
+/// `dispersion`, rejecting outliers once.
+pub fn refine(
+    positions: &[f64],
+    mut dispersion: Dispersion,
+    lines: &[SpectralLine],
+    degree: usize,
+) -> Result<(Dispersion, Vec<LineMatch>, f64), CalibrationError> {
+    if positions.is_empty() {
+        return Err(CalibrationError::NoPeaks);
+    }
+    let angstrom_per_pixel = dispersion.angstrom_per_pixel(0.).abs();
+
+    // refine on the lines matched so far before fitting the full degree
+    for tolerance in [3., 2.] {
+        let matches = match_lines(
+            positions,
+            &dispersion,
+            lines,
+            tolerance * angstrom_per_pixel,
+        );
+        if matches.len() < 2 {
+            return Err(CalibrationError::TooFewMatches(matches.len()));
+        }
+        dispersion = fit_dispersion(&matches, 1)?.0;
+    }
+
+    let matches = match_lines(positions, &dispersion, lines, 2. * angstrom_per_pixel);
+    // at least one more line than coefficients to measure the residuals
+    if matches.len() < degree + 2 {
+        return Err(CalibrationError::TooFewMatches(matches.len()));
+    }
+    let (dispersion, fitted, rms) = fit_dispersion(&matches, degree)?;
+
+    let kept: Vec<(f64, &SpectralLine)> = matches
+        .iter()
+        .zip(&fitted)
+        .filter(|(_, fitted)| fitted.residual.abs() <= 3. * rms)
+        .map(|(matched, _)| *matched)
+        .collect();
+    if kept.len() < matches.len() && kept.len() >= degree + 2 {
+        fit_dispersion(&kept, degree)
+    } else {
+        Ok((dispersion, fitted, rms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::lines::NEON_ARGON;
+
+    /// Positions of the lamp lines on a sensor of `width` pixels with a known solution,
+    /// and a noise peak that is not in the line list.
+    fn positions(dispersion: &Dispersion, width: f64) -> Vec<f64> {
+        let mut positions: Vec<f64> = NEON_ARGON
+            .iter()
+            .map(|line| dispersion.pixel(line.wavelength))
+            .filter(|pixel| (0. ..width).contains(pixel))
+            .collect();
+        positions.push(517.3);
+        positions
+    }
+
+    #[test]
+    fn known_dispersions_are_identified() {
+        let truth = Dispersion {
+            coefficients: vec![6100., 0.3, 2e-6],
+        };
+        let positions = positions(&truth, 1200.);
+        assert!(positions.len() >= 8);
+        // shifted by 10 Å and 3 % off in scale, as a grating angle error would be
+        let predicted = Dispersion {
+            coefficients: vec![6105., 0.309],
+        };
+        let (dispersion, matches, rms) = identify(&positions, &predicted, NEON_ARGON, 2).unwrap();
+        assert_eq!(matches.len(), positions.len() - 1);
+        assert!(rms < 1e-3);
+        for pixel in [0., 600., 1200.] {
+            assert!((dispersion.wavelength(pixel) - truth.wavelength(pixel)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn refine_needs_more_lines_than_coefficients() {
+        let truth = Dispersion {
+            coefficients: vec![6300., 0.1],
+        };
+        // three lines in the window, enough for a line but not a cubic
+        let positions = positions(&truth, 1000.);
+        let approximate = Dispersion {
+            coefficients: vec![6300.2, 0.1002],
+        };
+        let (dispersion, _, _) = refine(&positions, approximate.clone(), NEON_ARGON, 1).unwrap();
+        assert!((dispersion.wavelength(500.) - truth.wavelength(500.)).abs() < 1e-6);
+        assert!(matches!(
+            refine(&positions, approximate, NEON_ARGON, 3),
+            Err(CalibrationError::TooFewMatches(_))
+        ));
+    }
+
+    #[test]
+    fn best_linear_lines_up_the_lines() {
+        let truth = Dispersion {
+            coefficients: vec![6100., 0.3],
+        };
+        let positions = positions(&truth, 1200.);
+        let predicted = Dispersion {
+            coefficients: vec![6080., 0.32],
+        };
+        let found = best_linear(&positions, &predicted, NEON_ARGON, 0.6).unwrap();
+        for pixel in [0., 1200.] {
+            assert!((found.wavelength(pixel) - truth.wavelength(pixel)).abs() < 1e-6);
+        }
+    }
+}
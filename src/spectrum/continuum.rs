@@ -0,0 +1,227 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    atlas::{self, Atlas},
+    dispersion::Dispersion,
+    fit,
+    profile::Profile,
+};
+
+/// Largest number of clipping iterations.
+const MAX_ITERATIONS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ContinuumModel {
+    Polynomial {
+        degree: usize,
+    },
+    /// Cubic B-spline with uniformly spaced knots.
+    Spline {
+        segments: usize,
+    },
+}
+
+/// Least squares cubic B-spline with `segments` uniform intervals over `0..len`,
+/// evaluated at every pixel.
+fn spline_fit(x: &[f64], y: &[f64], len: usize, segments: usize) -> Option<Vec<f64>> {
+    let segments = segments.max(1);
+    let h = (len.max(2) - 1) as f64 / segments as f64;
+    // segment and the weights of its four basis functions
+    let basis = |x: f64| {
+        let u = x / h;
+        let i = (u.floor().max(0.) as usize).min(segments - 1);
+        let t = u - i as f64;
+        let weights = [
+            (1. - t).powi(3) / 6.,
+            (3. * t.powi(3) - 6. * t * t + 4.) / 6.,
+            (-3. * t.powi(3) + 3. * t * t + 3. * t + 1.) / 6.,
+            t.powi(3) / 6.,
+        ];
+        (i, weights)
+    };
+
+    let n = segments + 3;
+    let mut matrix = vec![vec![0.; n]; n];
+    let mut rhs = vec![0.; n];
+    for (x, y) in x.iter().zip(y) {
+        let (i, weights) = basis(*x);
+        for (a, wa) in weights.iter().enumerate() {
+            for (b, wb) in weights.iter().enumerate() {
+                matrix[i + a][i + b] += wa * wb;
+            }
+            rhs[i + a] += wa * y;
+        }
+    }
+    // a little damping keeps knots without points from making the system singular
+    let ridge = 1e-9 * (0..n).map(|i| matrix[i][i]).fold(0., f64::max).max(1e-12);
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] += ridge;
+    }
+    let coefficients = fit::solve(matrix, rhs)?;
+
+    Some(
+        (0..len)
+            .map(|x| {
+                let (i, weights) = basis(x as f64);
+                weights
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| w * coefficients[i + k])
+                    .sum()
+            })
+            .collect(),
+    )
+}
+
+/// Model through the points, evaluated at every pixel of `0..len`.
+fn model_fit(x: &[f64], y: &[f64], len: usize, model: ContinuumModel) -> Option<Vec<f64>> {
+    match model {
+        ContinuumModel::Polynomial { degree } => {
+            let coefficients = fit::polyfit(x, y, degree)?;
+            Some(
+                (0..len)
+                    .map(|x| fit::polyval(&coefficients, x as f64))
+                    .collect(),
+            )
+        }
+        ContinuumModel::Spline { segments } => spline_fit(x, y, len, segments),
+    }
+}
+
+/// Continuum of a profile.
+#[derive(Debug, Clone)]
+pub struct Continuum {
+    pub values: Vec<f64>,
+    /// Pixels kept by the clipping.
+    pub used: Vec<bool>,
+}
+
+impl Continuum {
+    /// Fit the model to the pixels in `windows`, or to the whole profile if empty,
+    /// iteratively rejecting pixels more than `low` sigmas below or `high` sigmas
+    /// above it. Absorption lines are removed with a smaller `low`.
+    pub fn fit(
+        values: &[f64],
+        windows: &[RangeInclusive<usize>],
+        model: ContinuumModel,
+        low: f64,
+        high: f64,
+    ) -> Option<Self> {
+        let len = values.len();
+        let mut used: Vec<bool> = (0..len)
+            .map(|i| windows.is_empty() || windows.iter().any(|window| window.contains(&i)))
+            .collect();
+        let initial = used.iter().filter(|used| **used).count();
+
+        let mut continuum = vec![];
+        for _ in 0..MAX_ITERATIONS {
+            let (x, y): (Vec<f64>, Vec<f64>) = (0..len)
+                .filter(|i| used[*i])
+                .map(|i| (i as f64, values[i]))
+                .unzip();
+            continuum = model_fit(&x, &y, len, model)?;
+
+            let sigma = ((0..len)
+                .filter(|i| used[*i])
+                .map(|i| (values[i] - continuum[i]).powi(2))
+                .sum::<f64>()
+                / x.len() as f64)
+                .sqrt();
+            let mut changed = false;
+            for i in 0..len {
+                let residual = values[i] - continuum[i];
+                if used[i] && (residual < -low * sigma || residual > high * sigma) {
+                    used[i] = false;
+                    changed = true;
+                }
+            }
+            if !changed || used.iter().filter(|used| **used).count() < initial / 4 {
+                break;
+            }
+        }
+        Some(Self {
+            values: continuum,
+            used,
+        })
+    }
+}
+
+/// Throughput of the instrument and camera over wavelength, the ratio of an observed
+/// disk centre spectrum to the solar atlas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstrumentResponse {
+    /// Ascending wavelengths in Å.
+    pub wavelengths: Vec<f64>,
+    /// ADU per unit of atlas flux.
+    pub response: Vec<f64>,
+    /// Name of the atlas it was derived from.
+    pub atlas: String,
+}
+
+impl InstrumentResponse {
+    /// Compare the profile to the atlas convolved to `resolution` Å FWHM and smooth the
+    /// ratio with the continuum model.
+    pub fn derive(
+        profile: &Profile,
+        dispersion: &Dispersion,
+        atlas: &Atlas,
+        resolution: f64,
+        model: ContinuumModel,
+    ) -> Option<Self> {
+        let len = profile.values.len();
+        if len < 2 {
+            return None;
+        }
+        let wavelengths: Vec<f64> = (0..len).map(|i| dispersion.wavelength(i as f64)).collect();
+        let low = wavelengths.iter().cloned().fold(f64::MAX, f64::min);
+        let high = wavelengths.iter().cloned().fold(f64::MIN, f64::max);
+        let step = dispersion.angstrom_per_pixel(len as f64 / 2.).abs() / 4.;
+        if step <= 0. {
+            return None;
+        }
+        let grid_len = ((high - low) / step) as usize + 2;
+        let reference = atlas::convolve_gaussian(
+            &atlas.resample(low, step, grid_len),
+            resolution / 2.3548 / step,
+        );
+        let ratio: Vec<f64> = wavelengths
+            .iter()
+            .zip(&profile.values)
+            .map(|(wavelength, value)| {
+                let position = ((wavelength - low) / step).clamp(0., (grid_len - 1) as f64);
+                let i = (position as usize).min(grid_len - 2);
+                let t = position - i as f64;
+                value / (reference[i] * (1. - t) + reference[i + 1] * t).max(1e-3)
+            })
+            .collect();
+        let smooth = Continuum::fit(&ratio, &[], model, 3., 3.)?;
+
+        let mut samples: Vec<(f64, f64)> = wavelengths.into_iter().zip(smooth.values).collect();
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(Self {
+            wavelengths: samples.iter().map(|s| s.0).collect(),
+            response: samples.iter().map(|s| s.1).collect(),
+            atlas: atlas.name.clone(),
+        })
+    }
+
+    /// Linearly interpolated response, `None` outside of the measured range.
+    pub fn at(&self, wavelength: f64) -> Option<f64> {
+        let i = self.wavelengths.partition_point(|w| *w < wavelength);
+        if i == 0 || i == self.wavelengths.len() {
+            return (self.wavelengths.first() == Some(&wavelength)).then(|| self.response[0]);
+        }
+        let (w0, w1) = (self.wavelengths[i - 1], self.wavelengths[i]);
+        let t = (wavelength - w0) / (w1 - w0);
+        Some(self.response[i - 1] * (1. - t) + self.response[i] * t)
+    }
+
+    /// Response at every pixel of a profile, `None` if it does not cover the profile.
+    pub fn divisor(&self, dispersion: &Dispersion, len: usize) -> Option<Vec<f64>> {
+        (0..len)
+            .map(|i| self.at(dispersion.wavelength(i as f64)))
+            .collect()
+    }
+}
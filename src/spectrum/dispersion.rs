@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use super::fit;
+use crate::solex::grating::Grating;
+
+/// Relation between a pixel along the dispersion axis and a wavelength in Ångström,
+/// `λ = c0 + c1 * x + c2 * x² + ...`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dispersion {
+    pub coefficients: Vec<f64>,
+}
+
+impl Dispersion {
+    /// Linear dispersion predicted from the grating geometry, centred on the profile.
+    pub fn predicted(grating: &Grating, angle: f64, pixel_size: f64, len: usize) -> Self {
+        let centre_wavelength = grating.wavelength(angle) * 10.;
+        // pixel size is in µm, linear dispersion in nm/mm
+        let angstrom_per_pixel = grating.linear_dispersion(angle) * pixel_size * 1e-2;
+        let centre_pixel = len as f64 / 2.;
+        Self {
+            coefficients: vec![
+                centre_wavelength - centre_pixel * angstrom_per_pixel,
+                angstrom_per_pixel,
+            ],
+        }
+    }
+
+    pub fn wavelength(&self, pixel: f64) -> f64 {
+        fit::polyval(&self.coefficients, pixel)
+    }
+
+    /// Pixel at a wavelength, by Newton iterations from the linear term.
+    pub fn pixel(&self, wavelength: f64) -> f64 {
+        let slope = self.coefficients.get(1).copied().unwrap_or(0.);
+        if slope == 0. {
+            return 0.;
+        }
+        let mut pixel = (wavelength - self.coefficients[0]) / slope;
+        for _ in 0..10 {
+            let derivative = self.angstrom_per_pixel(pixel);
+            if derivative == 0. {
+                break;
+            }
+            pixel -= (self.wavelength(pixel) - wavelength) / derivative;
+        }
+        pixel
+    }
+
+    /// Derivative of the wavelength in Å per pixel.
+    pub fn angstrom_per_pixel(&self, pixel: f64) -> f64 {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .rev()
+            .fold(0., |acc, (i, coefficient)| {
+                acc * pixel + i as f64 * coefficient
+            })
+    }
+}
@@ -0,0 +1,136 @@
+use super::{
+    dispersion::Dispersion,
+    profile::{Profile, SlitOrientation},
+    smile,
+};
+use crate::frame::Frame;
+
+/// Speed of light in km/s.
+pub const SPEED_OF_LIGHT: f64 = 299_792.458;
+
+/// Centroid of the absorption below the local continuum within `half_window` pixels
+/// of `guess`, over the pixels deeper than half the line depth.
+pub fn line_centroid(profile: &Profile, guess: f64, half_window: usize) -> Option<f64> {
+    let values = &profile.values;
+    let guess = guess.round().max(0.) as usize;
+    let start = guess.saturating_sub(half_window);
+    let end = (guess + half_window).min(values.len().checked_sub(1)?);
+    if end < start + 2 {
+        return None;
+    }
+
+    let (core, min) = (start..=end)
+        .map(|i| (i, values[i]))
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+    let max = |range: std::ops::RangeInclusive<usize>| {
+        values[range].iter().cloned().fold(f64::MIN, f64::max)
+    };
+    let continuum = (max(start..=core) + max(core..=end)) / 2.;
+    if continuum <= min {
+        return None;
+    }
+    let half = (continuum + min) / 2.;
+
+    // contiguous pixels below half depth around the core
+    let mut left = core;
+    while left > start && values[left - 1] < half {
+        left -= 1;
+    }
+    let mut right = core;
+    while right < end && values[right + 1] < half {
+        right += 1;
+    }
+    let (sum, weighted) = (left..=right).fold((0., 0.), |(sum, weighted), i| {
+        let weight = half - values[i];
+        (sum + weight, weighted + weight * i as f64)
+    });
+    (sum > 0.).then(|| weighted / sum)
+}
+
+/// Line position along the dispersion axis at a position along the slit.
+#[derive(Debug, Clone, Copy)]
+pub struct DopplerPoint {
+    pub slit: f64,
+    pub pixel: f64,
+    /// Position of the telluric line in the same band.
+    pub telluric: Option<f64>,
+}
+
+/// Follow the line near `guess` along the slit, and the telluric line near
+/// `telluric_guess` if given.
+pub fn track(
+    frame: &Frame,
+    orientation: SlitOrientation,
+    guess: f64,
+    telluric_guess: Option<f64>,
+    half_window: usize,
+    band: usize,
+) -> Vec<DopplerPoint> {
+    let locate = |profile: &Profile, position: f64| line_centroid(profile, position, half_window);
+    let line = smile::track(frame, orientation, guess, band, locate);
+    let telluric = telluric_guess
+        .map(|guess| smile::track(frame, orientation, guess, band, locate))
+        .unwrap_or_default();
+    line.iter()
+        .map(|(slit, pixel)| DopplerPoint {
+            slit: *slit,
+            pixel: *pixel,
+            telluric: telluric
+                .iter()
+                .find(|(other, _)| other == slit)
+                .map(|(_, pixel)| *pixel),
+        })
+        .collect()
+}
+
+/// Origin of the velocities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DopplerZero {
+    /// Mean wavelength of the line over a range of positions along the slit,
+    /// usually the disk centre.
+    Slit { start: f64, end: f64 },
+    /// Shifts relative to the rest wavelengths in Å, less the shift of the telluric line
+    /// which removes the calibration error. The velocity of the observer relative to the
+    /// Sun is not removed.
+    Telluric { line: f64, telluric: f64 },
+}
+
+/// Line of sight velocity in km/s at each position along the slit, positive away from
+/// the observer.
+pub fn velocities(
+    points: &[DopplerPoint],
+    dispersion: &Dispersion,
+    zero: DopplerZero,
+) -> Vec<(f64, f64)> {
+    match zero {
+        DopplerZero::Slit { start, end } => {
+            let reference: Vec<f64> = points
+                .iter()
+                .filter(|point| (start..=end).contains(&point.slit))
+                .map(|point| dispersion.wavelength(point.pixel))
+                .collect();
+            if reference.is_empty() {
+                return vec![];
+            }
+            let reference = reference.iter().sum::<f64>() / reference.len() as f64;
+            points
+                .iter()
+                .map(|point| {
+                    let wavelength = dispersion.wavelength(point.pixel);
+                    (
+                        point.slit,
+                        SPEED_OF_LIGHT * (wavelength - reference) / reference,
+                    )
+                })
+                .collect()
+        }
+        DopplerZero::Telluric { line, telluric } => points
+            .iter()
+            .filter_map(|point| {
+                let shift = (dispersion.wavelength(point.pixel) - line) / line;
+                let instrument = (dispersion.wavelength(point.telluric?) - telluric) / telluric;
+                Some((point.slit, SPEED_OF_LIGHT * (shift - instrument)))
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,238 @@
+/// Solve `matrix * x = rhs` by Gaussian elimination with partial pivoting.
+/// Returns `None` for a singular system.
+pub fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .total_cmp(&matrix[*b][column].abs())
+        })?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (value, pivot_value) in lower[0][column..].iter_mut().zip(&upper[column][column..])
+            {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+
+    let mut x = vec![0.; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| matrix[row][k] * x[k]).sum();
+        x[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(x)
+}
+
+/// Least squares polynomial `y = c0 + c1 * x + ...` through the points, lowest order first.
+pub fn polyfit(x: &[f64], y: &[f64], degree: usize) -> Option<Vec<f64>> {
+    if x.len() != y.len() || x.len() <= degree {
+        return None;
+    }
+
+    // fit in a normalized variable to keep the normal equations well conditioned
+    let mean = x.iter().sum::<f64>() / x.len() as f64;
+    let scale = x
+        .iter()
+        .map(|x| (x - mean).abs())
+        .fold(0., f64::max)
+        .max(1e-12);
+    let n = degree + 1;
+    let mut matrix = vec![vec![0.; n]; n];
+    let mut rhs = vec![0.; n];
+    for (x, y) in x.iter().zip(y) {
+        let t = (x - mean) / scale;
+        let powers: Vec<f64> = (0..n).map(|k| t.powi(k as i32)).collect();
+        for i in 0..n {
+            for j in 0..n {
+                matrix[i][j] += powers[i] * powers[j];
+            }
+            rhs[i] += powers[i] * y;
+        }
+    }
+    let normalized = solve(matrix, rhs)?;
+
+    // expand a_k * ((x - mean) / scale)^k back into powers of x
+    let mut coefficients = vec![0.; n];
+    for (k, a) in normalized.iter().enumerate() {
+        let mut binomial = 1.;
+        for (j, coefficient) in coefficients.iter_mut().enumerate().take(k + 1) {
+            *coefficient += a * binomial * (-mean).powi((k - j) as i32) / scale.powi(k as i32);
+            binomial = binomial * (k - j) as f64 / (j + 1) as f64;
+        }
+    }
+    Some(coefficients)
+}
+
+/// Evaluate a polynomial with the coefficients lowest order first.
+pub fn polyval(coefficients: &[f64], x: f64) -> f64 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0., |acc, coefficient| acc * x + coefficient)
+}
+
+/// Parameters of a non-linear least squares fit.
+#[derive(Debug, Clone)]
+pub struct FitResult {
+    pub parameters: Vec<f64>,
+    /// One sigma uncertainty of each parameter, scaled by the residuals.
+    pub errors: Vec<f64>,
+    /// RMS of the residuals.
+    pub rms: f64,
+}
+
+/// Fit `model(x, parameters)` to the points with the Levenberg-Marquardt algorithm,
+/// starting from `parameters`. The jacobian is computed numerically.
+pub fn levenberg_marquardt(
+    x: &[f64],
+    y: &[f64],
+    mut parameters: Vec<f64>,
+    model: impl Fn(f64, &[f64]) -> f64,
+    iterations: usize,
+) -> Option<FitResult> {
+    let n = parameters.len();
+    if x.len() != y.len() || x.len() <= n {
+        return None;
+    }
+    let chi2 = |parameters: &[f64]| -> f64 {
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| (y - model(*x, parameters)).powi(2))
+            .sum()
+    };
+    // normal matrix and gradient of the residuals
+    let normal = |parameters: &[f64]| {
+        let mut matrix = vec![vec![0.; n]; n];
+        let mut gradient = vec![0.; n];
+        let steps: Vec<f64> = parameters
+            .iter()
+            .map(|p| 1e-6 * p.abs().max(1e-3))
+            .collect();
+        for (x, y) in x.iter().zip(y) {
+            let value = model(*x, parameters);
+            let derivatives: Vec<f64> = (0..n)
+                .map(|k| {
+                    let mut shifted = parameters.to_vec();
+                    shifted[k] += steps[k];
+                    (model(*x, &shifted) - value) / steps[k]
+                })
+                .collect();
+            for i in 0..n {
+                for j in 0..n {
+                    matrix[i][j] += derivatives[i] * derivatives[j];
+                }
+                gradient[i] += derivatives[i] * (y - value);
+            }
+        }
+        (matrix, gradient)
+    };
+
+    let mut current = chi2(&parameters);
+    let mut lambda = 1e-3;
+    for _ in 0..iterations {
+        let (matrix, gradient) = normal(&parameters);
+        let mut improved = false;
+        while lambda < 1e10 {
+            let mut damped = matrix.clone();
+            for (i, row) in damped.iter_mut().enumerate() {
+                row[i] += lambda * matrix[i][i].max(1e-12);
+            }
+            let Some(step) = solve(damped, gradient.clone()) else {
+                lambda *= 10.;
+                continue;
+            };
+            let candidate: Vec<f64> = parameters.iter().zip(&step).map(|(p, s)| p + s).collect();
+            let value = chi2(&candidate);
+            if value.is_finite() && value < current {
+                let converged = (current - value) <= 1e-10 * current;
+                parameters = candidate;
+                current = value;
+                lambda = (lambda / 10.).max(1e-12);
+                improved = !converged;
+                break;
+            }
+            lambda *= 10.;
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    // covariance from the inverse of the normal matrix, parameters the model does not
+    // depend on at the solution (e.g. clamped) are left out with an unknown error
+    let (mut matrix, _) = normal(&parameters);
+    let free: Vec<bool> = (0..n).map(|k| matrix[k][k] > 0.).collect();
+    for (k, row) in matrix.iter_mut().enumerate() {
+        if !free[k] {
+            row[k] = 1.;
+        }
+    }
+    let variance = current / (x.len() - n) as f64;
+    let errors = (0..n)
+        .map(|k| {
+            let mut unit = vec![0.; n];
+            unit[k] = 1.;
+            match solve(matrix.clone(), unit) {
+                Some(column) if free[k] => (column[k] * variance).sqrt(),
+                _ => f64::NAN,
+            }
+        })
+        .collect();
+    Some(FitResult {
+        parameters,
+        errors,
+        rms: (current / x.len() as f64).sqrt(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polyfit_recovers_a_known_polynomial() {
+        let coefficients = [6500., 0.12, -3e-5, 2e-9];
+        let x: Vec<f64> = (0..50).map(|i| 100. + i as f64 * 25.).collect();
+        let y: Vec<f64> = x.iter().map(|x| polyval(&coefficients, *x)).collect();
+        let fitted = polyfit(&x, &y, 3).unwrap();
+        for x in &x {
+            assert!((polyval(&fitted, *x) - polyval(&coefficients, *x)).abs() < 1e-6);
+        }
+        assert!(polyfit(&x[..3], &y[..3], 3).is_none());
+    }
+
+    #[test]
+    fn levenberg_marquardt_recovers_a_known_gaussian() {
+        let gaussian = |x: f64, p: &[f64]| p[0] + p[1] * (-0.5 * ((x - p[2]) / p[3]).powi(2)).exp();
+        let truth = [100., -60., 41.3, 3.2];
+        let x: Vec<f64> = (0..80).map(|i| i as f64).collect();
+        // a deterministic ripple in place of noise
+        let y: Vec<f64> = x
+            .iter()
+            .map(|x| gaussian(*x, &truth) + 0.2 * (x * 1.7).sin())
+            .collect();
+        let fit = levenberg_marquardt(&x, &y, vec![90., -40., 38., 5.], gaussian, 100).unwrap();
+        for (fitted, truth) in fit.parameters.iter().zip(truth) {
+            assert!(
+                (fitted - truth).abs() < 0.05 * truth.abs().max(1.),
+                "{:?}",
+                fit
+            );
+        }
+        assert!(fit.rms < 0.2);
+        assert!(fit
+            .errors
+            .iter()
+            .all(|error| error.is_finite() && *error < 0.5));
+    }
+}
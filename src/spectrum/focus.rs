@@ -0,0 +1,93 @@
+use super::profile::Profile;
+
+/// Absorption line measured in a profile.
+#[derive(Debug, Clone, Copy)]
+pub struct LineWidth {
+    /// Sub-pixel position of the line core.
+    pub centre: f64,
+    /// Full width at half depth in pixels.
+    pub fwhm: f64,
+}
+
+/// Measure the absorption line with its core within `half_window` pixels of `guess`.
+/// Returns `None` if the line is not fully contained in the window.
+pub fn line_width(profile: &Profile, guess: f64, half_window: usize) -> Option<LineWidth> {
+    let values = &profile.values;
+    let guess = guess.round().max(0.) as usize;
+    let start = guess.saturating_sub(half_window);
+    let end = (guess + half_window).min(values.len().checked_sub(1)?);
+    if end < start + 2 {
+        return None;
+    }
+
+    let window = &values[start..=end];
+    let (core, min) = window
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, value)| (start + i, *value))?;
+    let max = |values: &[f64]| values.iter().cloned().fold(f64::MIN, f64::max);
+    let continuum = (max(&values[start..=core]) + max(&values[core..=end])) / 2.;
+    if continuum <= min {
+        return None;
+    }
+    let half = (continuum + min) / 2.;
+
+    let crossing = |i: usize, j: usize| {
+        // linear interpolation between a pixel below and a pixel above half depth
+        i as f64 + (half - values[i]) / (values[j] - values[i]) * (j as f64 - i as f64)
+    };
+    let left = (start..core).rev().find(|i| values[*i] >= half)?;
+    let right = (core + 1..=end).find(|i| values[*i] >= half)?;
+    let left = crossing(left + 1, left);
+    let right = crossing(right - 1, right);
+
+    // parabola through the core and its neighbours
+    let centre = if core > 0 && core + 1 < values.len() {
+        let (a, b, c) = (values[core - 1], values[core], values[core + 1]);
+        let denominator = a - 2. * b + c;
+        if denominator > 0. {
+            core as f64 + (a - c) / (2. * denominator)
+        } else {
+            core as f64
+        }
+    } else {
+        core as f64
+    };
+
+    Some(LineWidth {
+        centre,
+        fwhm: right - left,
+    })
+}
+
+/// Deepest local minimum of the profile that is not within `exclude` pixels of `taken`.
+pub fn deepest_line(profile: &Profile, taken: &[f64], exclude: usize) -> Option<usize> {
+    let values = &profile.values;
+    (1..values.len().saturating_sub(1))
+        .filter(|i| values[*i] <= values[i - 1] && values[*i] <= values[i + 1])
+        .filter(|i| {
+            taken
+                .iter()
+                .all(|line| (*i as f64 - line).abs() > exclude as f64)
+        })
+        .min_by(|a, b| values[*a].total_cmp(&values[*b]))
+}
+
+/// RMS of the pixel to pixel gradient relative to the mean intensity in percent.
+/// Sharper lines give a higher score, independent of the exposure.
+pub fn gradient_score(profile: &Profile) -> f64 {
+    let values = &profile.values;
+    if values.len() < 2 {
+        return 0.;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    if mean <= 0. {
+        return 0.;
+    }
+    let squares = values
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).powi(2))
+        .sum::<f64>();
+    (squares / (values.len() - 1) as f64).sqrt() / mean * 100.
+}
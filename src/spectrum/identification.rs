@@ -0,0 +1,127 @@
+use super::{
+    calibration::find_absorption_lines, dispersion::Dispersion, lines::SpectralLine,
+    profile::Profile,
+};
+
+/// Absorption line of the profile matched to a reference line.
+#[derive(Debug, Clone, Copy)]
+pub struct Identification {
+    pub pixel: f64,
+    pub line: SpectralLine,
+    /// Reference minus observed wavelength in Å, after removing the common shift.
+    pub residual: f64,
+}
+
+impl Identification {
+    /// Element, ion and wavelength, e.g. `Fe I 6301.50`.
+    pub fn label(&self) -> String {
+        format!("{} {:.2}", self.line.name, self.line.wavelength)
+    }
+}
+
+/// Absorption lines of a profile identified against a line list.
+#[derive(Debug, Clone, Default)]
+pub struct Identified {
+    /// Common error of the dispersion in Å, reference minus observed.
+    pub shift: f64,
+    pub lines: Vec<Identification>,
+}
+
+/// Pairs of an observed wavelength and a reference line closer than `tolerance` Å once
+/// shifted, each line and each observed wavelength used at most once.
+fn pair(
+    observed: &[f64],
+    lines: &[SpectralLine],
+    shift: f64,
+    tolerance: f64,
+) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(usize, usize, f64)> = vec![];
+    for (i, wavelength) in observed.iter().enumerate() {
+        let nearest = lines
+            .iter()
+            .enumerate()
+            .map(|(j, line)| (j, (line.wavelength - wavelength - shift).abs()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((j, distance)) = nearest {
+            match pairs.iter_mut().find(|(_, other, _)| *other == j) {
+                Some(existing) if existing.2 > distance => *existing = (i, j, distance),
+                Some(_) => {}
+                None => pairs.push((i, j, distance)),
+            }
+        }
+    }
+    pairs.into_iter().map(|(i, j, _)| (i, j)).collect()
+}
+
+/// Detect the absorption lines deeper than `sigma` times the noise and match them to
+/// `lines`. The dispersion may be off by up to `max_shift` Å, the common shift giving
+/// the most matches within `tolerance` Å is removed first.
+pub fn identify(
+    profile: &Profile,
+    dispersion: &Dispersion,
+    lines: &[SpectralLine],
+    sigma: f64,
+    tolerance: f64,
+    max_shift: f64,
+) -> Identified {
+    let pixels: Vec<f64> = find_absorption_lines(profile, sigma)
+        .iter()
+        .map(|peak| peak.position)
+        .collect();
+    let observed: Vec<f64> = pixels.iter().map(|p| dispersion.wavelength(*p)).collect();
+    let (low, high) = observed
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(low, high), w| {
+            (low.min(*w), high.max(*w))
+        });
+    let lines: Vec<SpectralLine> = lines
+        .iter()
+        .filter(|line| (low - max_shift..=high + max_shift).contains(&line.wavelength))
+        .copied()
+        .collect();
+    if observed.is_empty() || lines.is_empty() {
+        return Identified::default();
+    }
+
+    // every pair within range is a candidate shift, keep the one matching the most lines
+    let mut candidates = vec![0.];
+    for wavelength in &observed {
+        for line in &lines {
+            let shift = line.wavelength - wavelength;
+            if shift.abs() <= max_shift {
+                candidates.push(shift);
+            }
+        }
+    }
+    let best = candidates
+        .iter()
+        .map(|shift| (*shift, pair(&observed, &lines, *shift, tolerance).len()))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.abs().total_cmp(&a.0.abs())))
+        .map_or(0., |(shift, _)| shift);
+
+    // refine with the mean offset of the matches
+    let pairs = pair(&observed, &lines, best, tolerance);
+    let shift = if pairs.is_empty() {
+        best
+    } else {
+        pairs
+            .iter()
+            .map(|(i, j)| lines[*j].wavelength - observed[*i])
+            .sum::<f64>()
+            / pairs.len() as f64
+    };
+    let mut identified: Vec<Identification> = pairs
+        .iter()
+        .map(|(i, j)| Identification {
+            pixel: pixels[*i],
+            line: lines[*j],
+            residual: lines[*j].wavelength - observed[*i] - shift,
+        })
+        .collect();
+    identified.sort_by(|a, b| a.pixel.total_cmp(&b.pixel));
+    Identified {
+        shift,
+        lines: identified,
+    }
+}
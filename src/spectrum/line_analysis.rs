@@ -0,0 +1,187 @@
+use std::{error::Error, fmt::Display};
+
+use super::{
+    dispersion::Dispersion,
+    fit,
+    line_fit::{LineFit, LineShape},
+    profile::Profile,
+};
+
+/// Number of bisector levels between the core and the continuum.
+const BISECTOR_LEVELS: usize = 10;
+
+#[derive(Debug, Clone)]
+pub enum AnalysisError {
+    OutOfProfile,
+    NoContinuum,
+    NoLine,
+}
+
+impl Display for AnalysisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisError::OutOfProfile => write!(f, "The line window is outside the profile."),
+            AnalysisError::NoContinuum => write!(f, "The continuum could not be fitted."),
+            AnalysisError::NoLine => write!(f, "No absorption line in the window."),
+        }
+    }
+}
+
+impl Error for AnalysisError {}
+
+/// Measurements of an absorption line in a continuum normalized profile.
+/// Positions are in pixels, widths in Å when the dispersion is known and pixels otherwise.
+#[derive(Debug, Clone)]
+pub struct LineAnalysis {
+    /// Normalized profile over the line and continuum windows as pixel and value.
+    pub normalized: Vec<(f64, f64)>,
+    /// First and last pixel of the line window.
+    pub window: (usize, usize),
+    /// Continuum noise relative to the continuum.
+    pub noise: f64,
+    pub fit: LineFit,
+    /// Mid points of the line at levels between the core and the continuum, as
+    /// normalized level and pixel.
+    pub bisector: Vec<(f64, f64)>,
+    /// Mean position of the bisector.
+    pub bisector_centre: f64,
+    /// Depth of the core below the continuum, as a fraction of it.
+    pub depth: f64,
+    pub fwhm: f64,
+    pub fwhm_error: f64,
+    pub equivalent_width: f64,
+    pub equivalent_width_error: f64,
+}
+
+impl LineAnalysis {
+    /// Analyse the absorption line within `half_window` pixels of `guess`, normalized by
+    /// a straight continuum fitted over `continuum_width` pixels on each side.
+    pub fn measure(
+        profile: &Profile,
+        guess: f64,
+        half_window: usize,
+        continuum_width: usize,
+        shape: LineShape,
+        dispersion: Option<&Dispersion>,
+    ) -> Result<Self, AnalysisError> {
+        let values = &profile.values;
+        let guess = guess.round().max(0.) as usize;
+        let start = guess.saturating_sub(half_window);
+        let end = guess + half_window;
+        if start < continuum_width || end + continuum_width >= values.len() || half_window < 3 {
+            return Err(AnalysisError::OutOfProfile);
+        }
+
+        // straight continuum through both sides, clipping outliers once
+        let mut side: Vec<usize> = (start - continuum_width..start)
+            .chain(end + 1..=end + continuum_width)
+            .collect();
+        let mut continuum = vec![];
+        for _ in 0..2 {
+            let x: Vec<f64> = side.iter().map(|i| *i as f64).collect();
+            let y: Vec<f64> = side.iter().map(|i| values[*i]).collect();
+            continuum = fit::polyfit(&x, &y, 1).ok_or(AnalysisError::NoContinuum)?;
+            let residual = |i: &usize| values[*i] / fit::polyval(&continuum, *i as f64) - 1.;
+            let rms =
+                (side.iter().map(|i| residual(i).powi(2)).sum::<f64>() / side.len() as f64).sqrt();
+            side.retain(|i| residual(i).abs() <= 2.5 * rms.max(1e-9));
+            if side.len() < 2 {
+                return Err(AnalysisError::NoContinuum);
+            }
+        }
+        if fit::polyval(&continuum, guess as f64) <= 0. {
+            return Err(AnalysisError::NoContinuum);
+        }
+        let normalize = |i: usize| values[i] / fit::polyval(&continuum, i as f64);
+        let noise = (side
+            .iter()
+            .map(|i| (normalize(*i) - 1.).powi(2))
+            .sum::<f64>()
+            / (side.len() as f64 - 2.).max(1.))
+        .sqrt();
+        let normalized: Vec<(f64, f64)> = (start - continuum_width..=end + continuum_width)
+            .map(|i| (i as f64, normalize(i)))
+            .collect();
+
+        let fit = LineFit::fit(
+            &Profile {
+                values: (0..values.len())
+                    .map(|i| {
+                        if (start - continuum_width..=end + continuum_width).contains(&i) {
+                            normalize(i)
+                        } else {
+                            1.
+                        }
+                    })
+                    .collect(),
+            },
+            guess as f64,
+            half_window,
+            shape,
+        )
+        .filter(|fit| fit.amplitude < 0.)
+        .ok_or(AnalysisError::NoLine)?;
+
+        let line: Vec<f64> = (start..=end).map(normalize).collect();
+        let (core, minimum) = line
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, value)| (i, *value))
+            .ok_or(AnalysisError::NoLine)?;
+        let depth = 1. - minimum;
+        if depth <= 0. {
+            return Err(AnalysisError::NoLine);
+        }
+
+        // mid points between the crossings of each level on both sides of the core
+        let crossing = |level: f64, outward: &mut dyn Iterator<Item = usize>| {
+            let mut previous = core;
+            for i in outward {
+                if line[i] >= level {
+                    let t = (level - line[previous]) / (line[i] - line[previous]);
+                    return Some(previous as f64 + t * (i as f64 - previous as f64));
+                }
+                previous = i;
+            }
+            None
+        };
+        let bisector: Vec<(f64, f64)> = (1..BISECTOR_LEVELS)
+            .filter_map(|k| {
+                let level = minimum + depth * k as f64 / BISECTOR_LEVELS as f64;
+                let left = crossing(level, &mut (0..core).rev())?;
+                let right = crossing(level, &mut (core + 1..line.len()))?;
+                Some((level, start as f64 + (left + right) / 2.))
+            })
+            .collect();
+        if bisector.is_empty() {
+            return Err(AnalysisError::NoLine);
+        }
+        let bisector_centre = bisector.iter().map(|(_, x)| x).sum::<f64>() / bisector.len() as f64;
+
+        let step = |i: usize| match dispersion {
+            Some(dispersion) => dispersion.angstrom_per_pixel(i as f64).abs(),
+            None => 1.,
+        };
+        let equivalent_width: f64 = (start..=end).map(|i| (1. - normalize(i)) * step(i)).sum();
+        // pixel noise, and the uncertainty of the continuum level over the window
+        let width = (start..=end).map(step).sum::<f64>();
+        let pixel_error = noise * (start..=end).map(|i| step(i).powi(2)).sum::<f64>().sqrt();
+        let continuum_error = noise / (side.len() as f64).sqrt() * width;
+        let scale = step(fit.centre.round() as usize);
+
+        Ok(Self {
+            normalized,
+            window: (start, end),
+            noise,
+            fit,
+            bisector,
+            bisector_centre,
+            depth,
+            fwhm: fit.fwhm * scale,
+            fwhm_error: fit.fwhm_error * scale,
+            equivalent_width,
+            equivalent_width_error: pixel_error.hypot(continuum_error),
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use super::{fit, profile::Profile};
+
+/// Number of Levenberg-Marquardt iterations.
+const ITERATIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LineShape {
+    Gaussian,
+    /// Pseudo-Voigt, a mix of a gaussian and a lorentzian of the same FWHM.
+    Voigt,
+}
+
+impl LineShape {
+    /// Profile of unit height at `offset` from the centre.
+    fn value(self, offset: f64, fwhm: f64, eta: f64) -> f64 {
+        let x = offset / (fwhm / 2.);
+        let gauss = (-x * x * std::f64::consts::LN_2).exp();
+        match self {
+            LineShape::Gaussian => gauss,
+            LineShape::Voigt => {
+                let eta = eta.clamp(0., 1.);
+                eta / (1. + x * x) + (1. - eta) * gauss
+            }
+        }
+    }
+}
+
+/// Line profile fitted over a linear background.
+#[derive(Debug, Clone, Copy)]
+pub struct LineFit {
+    pub shape: LineShape,
+    /// Position of the line centre in pixels.
+    pub centre: f64,
+    pub centre_error: f64,
+    /// Full width at half maximum in pixels.
+    pub fwhm: f64,
+    pub fwhm_error: f64,
+    /// Height above the background, negative for absorption lines.
+    pub amplitude: f64,
+    /// Lorentzian fraction of a Voigt profile.
+    pub eta: f64,
+    /// Background at the centre and its slope per pixel.
+    pub background: (f64, f64),
+    /// RMS of the residuals.
+    pub rms: f64,
+}
+
+impl LineFit {
+    /// Fit the line within `half_window` pixels of `guess`. Returns `None` when the fit
+    /// does not converge or the line is not contained in the window.
+    pub fn fit(
+        profile: &Profile,
+        guess: f64,
+        half_window: usize,
+        shape: LineShape,
+    ) -> Option<Self> {
+        let values = &profile.values;
+        let guess = guess.round().max(0.) as usize;
+        let start = guess.saturating_sub(half_window);
+        let end = (guess + half_window).min(values.len().checked_sub(1)?);
+        if end < start + 6 {
+            return None;
+        }
+        let x: Vec<f64> = (start..=end).map(|i| i as f64).collect();
+        let y = &values[start..=end];
+
+        // background through the window edges, the line at the largest deviation from it
+        let slope = (y[y.len() - 1] - y[0]) / (x[x.len() - 1] - x[0]);
+        let background = |i: usize| y[0] + slope * (x[i] - x[0]);
+        let (peak, deviation) = (0..y.len())
+            .map(|i| (i, y[i] - background(i)))
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+        let width = (0..y.len())
+            .filter(|i| ((y[*i] - background(*i)) / deviation) > 0.5)
+            .count()
+            .max(2) as f64;
+
+        let centre_guess = x[peak];
+        let mut parameters = vec![background(peak), slope, deviation, centre_guess, width];
+        if shape == LineShape::Voigt {
+            parameters.push(0.3);
+        }
+        let model = |x: f64, p: &[f64]| {
+            let eta = p.get(5).copied().unwrap_or(0.);
+            p[0] + p[1] * (x - centre_guess)
+                + p[2] * shape.value(x - p[3], p[4].abs().max(1e-3), eta)
+        };
+        let result = fit::levenberg_marquardt(&x, y, parameters, model, ITERATIONS)?;
+        let p = &result.parameters;
+
+        let fwhm = p[4].abs();
+        let centre = p[3];
+        if !(start as f64..=end as f64).contains(&centre)
+            || fwhm < 0.5
+            || fwhm > 2. * half_window as f64
+        {
+            return None;
+        }
+        Some(Self {
+            shape,
+            centre,
+            centre_error: result.errors[3],
+            fwhm,
+            fwhm_error: result.errors[4],
+            amplitude: p[2],
+            eta: p.get(5).copied().unwrap_or(0.).clamp(0., 1.),
+            background: (p[0] + p[1] * (centre - centre_guess), p[1]),
+            rms: result.rms,
+        })
+    }
+
+    /// Value of the fitted model at a pixel.
+    pub fn value(&self, x: f64) -> f64 {
+        self.background.0
+            + self.background.1 * (x - self.centre)
+            + self.amplitude * self.shape.value(x - self.centre, self.fwhm, self.eta)
+    }
+}
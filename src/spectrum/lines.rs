@@ -0,0 +1,161 @@
+/// Reference line with a known wavelength.
+#[derive(Debug, Clone, Copy)]
+pub struct SpectralLine {
+    /// Air wavelength in Å.
+    pub wavelength: f64,
+    pub name: &'static str,
+}
+
+const fn line(wavelength: f64, name: &'static str) -> SpectralLine {
+    SpectralLine { wavelength, name }
+}
+
+/// Bright Ne I and Ar I lines of a neon-argon glow lamp, air wavelengths from the NIST
+/// atomic spectra database.
+pub const NEON_ARGON: &[SpectralLine] = &[
+    line(4158.590, "Ar I"),
+    line(4200.674, "Ar I"),
+    line(4259.362, "Ar I"),
+    line(4300.101, "Ar I"),
+    line(4333.561, "Ar I"),
+    line(4510.733, "Ar I"),
+    line(4702.316, "Ar I"),
+    line(5037.751, "Ne I"),
+    line(5330.778, "Ne I"),
+    line(5341.094, "Ne I"),
+    line(5400.562, "Ne I"),
+    line(5606.733, "Ar I"),
+    line(5650.704, "Ar I"),
+    line(5764.419, "Ne I"),
+    line(5852.488, "Ne I"),
+    line(5881.895, "Ne I"),
+    line(5944.834, "Ne I"),
+    line(5975.534, "Ne I"),
+    line(6029.997, "Ne I"),
+    line(6074.338, "Ne I"),
+    line(6096.163, "Ne I"),
+    line(6143.063, "Ne I"),
+    line(6163.594, "Ne I"),
+    line(6217.281, "Ne I"),
+    line(6266.495, "Ne I"),
+    line(6304.789, "Ne I"),
+    line(6334.428, "Ne I"),
+    line(6382.992, "Ne I"),
+    line(6402.248, "Ne I"),
+    line(6506.528, "Ne I"),
+    line(6532.882, "Ne I"),
+    line(6598.953, "Ne I"),
+    line(6678.276, "Ne I"),
+    line(6717.043, "Ne I"),
+    line(6752.834, "Ar I"),
+    line(6871.289, "Ar I"),
+    line(6929.467, "Ne I"),
+    line(6965.431, "Ar I"),
+    line(7032.413, "Ne I"),
+    line(7067.218, "Ar I"),
+    line(7147.042, "Ar I"),
+    line(7173.938, "Ne I"),
+    line(7245.167, "Ne I"),
+    line(7272.936, "Ar I"),
+    line(7383.980, "Ar I"),
+    line(7503.869, "Ar I"),
+    line(7514.652, "Ar I"),
+    line(7635.106, "Ar I"),
+];
+
+/// Solar absorption line with an approximate profile for the line list model.
+#[derive(Debug, Clone, Copy)]
+pub struct FraunhoferLine {
+    pub line: SpectralLine,
+    /// Central depth relative to the continuum.
+    pub depth: f64,
+    /// FWHM of the core in Å.
+    pub width: f64,
+}
+
+const fn solar(wavelength: f64, name: &'static str, depth: f64, width: f64) -> FraunhoferLine {
+    FraunhoferLine {
+        line: line(wavelength, name),
+        depth,
+        width,
+    }
+}
+
+/// Strong photospheric lines between Ca II K and H-alpha, air wavelengths.
+/// Depths and widths are rough values read from the disk centre atlas.
+pub const FRAUNHOFER: &[FraunhoferLine] = &[
+    solar(3905.527, "Si I", 0.75, 0.25),
+    solar(3920.258, "Fe I", 0.70, 0.25),
+    solar(3922.912, "Fe I", 0.70, 0.25),
+    solar(3927.920, "Fe I", 0.75, 0.30),
+    solar(3930.297, "Fe I", 0.75, 0.30),
+    solar(3933.663, "Ca II K", 0.92, 6.00),
+    solar(3944.006, "Al I", 0.75, 0.30),
+    solar(3961.520, "Al I", 0.75, 0.30),
+    solar(3968.469, "Ca II H", 0.90, 5.00),
+    solar(3970.074, "H ε", 0.50, 1.00),
+    solar(4045.813, "Fe I", 0.80, 0.30),
+    solar(4063.594, "Fe I", 0.75, 0.25),
+    solar(4071.737, "Fe I", 0.75, 0.25),
+    solar(4077.709, "Sr II", 0.70, 0.20),
+    solar(4101.734, "H δ", 0.75, 1.60),
+    solar(4226.728, "Ca I", 0.85, 1.20),
+    solar(4260.474, "Fe I", 0.70, 0.20),
+    solar(4271.760, "Fe I", 0.70, 0.20),
+    solar(4307.902, "Fe I", 0.70, 0.30),
+    solar(4325.762, "Fe I", 0.70, 0.20),
+    solar(4340.462, "H γ", 0.75, 2.00),
+    solar(4383.545, "Fe I", 0.80, 0.35),
+    solar(4404.750, "Fe I", 0.75, 0.30),
+    solar(4415.122, "Fe I", 0.70, 0.25),
+    solar(4554.029, "Ba II", 0.75, 0.15),
+    solar(4571.096, "Mg I", 0.60, 0.15),
+    solar(4861.323, "H β", 0.80, 1.20),
+    solar(4891.492, "Fe I", 0.60, 0.15),
+    solar(4920.502, "Fe I", 0.65, 0.20),
+    solar(4957.596, "Fe I", 0.65, 0.20),
+    solar(5167.321, "Mg I b4", 0.85, 0.90),
+    solar(5172.684, "Mg I b2", 0.85, 1.00),
+    solar(5183.604, "Mg I b1", 0.85, 1.20),
+    solar(5227.189, "Fe I", 0.70, 0.25),
+    solar(5269.537, "Fe I", 0.80, 0.40),
+    solar(5328.039, "Fe I", 0.75, 0.30),
+    solar(5371.489, "Fe I", 0.70, 0.20),
+    solar(5397.128, "Fe I", 0.70, 0.20),
+    solar(5405.775, "Fe I", 0.70, 0.20),
+    solar(5429.697, "Fe I", 0.70, 0.20),
+    solar(5446.917, "Fe I", 0.70, 0.20),
+    solar(5528.405, "Mg I", 0.60, 0.30),
+    solar(5889.951, "Na I D2", 0.95, 0.50),
+    solar(5895.924, "Na I D1", 0.93, 0.45),
+    solar(6102.723, "Ca I", 0.60, 0.20),
+    solar(6122.217, "Ca I", 0.70, 0.25),
+    solar(6162.173, "Ca I", 0.75, 0.30),
+    solar(6191.558, "Fe I", 0.55, 0.15),
+    solar(6230.723, "Fe I", 0.55, 0.15),
+    solar(6252.555, "Fe I", 0.55, 0.15),
+    solar(6301.501, "Fe I", 0.60, 0.12),
+    solar(6302.494, "Fe I", 0.55, 0.12),
+    solar(6335.331, "Fe I", 0.50, 0.12),
+    solar(6393.601, "Fe I", 0.60, 0.15),
+    solar(6400.001, "Fe I", 0.60, 0.15),
+    solar(6430.846, "Fe I", 0.55, 0.12),
+    solar(6439.075, "Ca I", 0.65, 0.15),
+    solar(6494.981, "Fe I", 0.65, 0.15),
+    solar(6546.239, "Fe I", 0.50, 0.12),
+    solar(6562.808, "H α", 0.83, 1.40),
+    solar(6592.914, "Fe I", 0.50, 0.12),
+    solar(6643.630, "Ni I", 0.45, 0.12),
+    solar(6717.681, "Ca I", 0.55, 0.15),
+];
+
+/// Water vapour lines of the Earth's atmosphere around H-alpha, air wavelengths.
+/// They are at rest in the observer's frame.
+pub const TELLURIC: &[SpectralLine] = &[
+    line(6543.907, "H2O"),
+    line(6547.705, "H2O"),
+    line(6552.629, "H2O"),
+    line(6557.171, "H2O"),
+    line(6564.206, "H2O"),
+    line(6572.086, "H2O"),
+];
@@ -0,0 +1,15 @@
+pub mod atlas;
+pub mod calibration;
+pub mod continuum;
+pub mod dispersion;
+pub mod doppler;
+pub mod fit;
+pub mod focus;
+pub mod identification;
+pub mod line_analysis;
+pub mod line_fit;
+pub mod lines;
+pub mod profile;
+pub mod resolution;
+pub mod smile;
+pub mod tilt;
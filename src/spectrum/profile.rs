@@ -0,0 +1,80 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use super::fit;
+use crate::frame::Frame;
+
+/// Direction of the slit image on the sensor.
+/// The spectrum is dispersed perpendicular to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SlitOrientation {
+    /// The slit runs along the columns, the dispersion axis is horizontal.
+    Vertical,
+    /// The slit runs along the rows, the dispersion axis is vertical.
+    Horizontal,
+}
+
+impl SlitOrientation {
+    /// Number of pixels along the dispersion axis and along the slit.
+    pub fn dimensions(self, frame: &Frame) -> (usize, usize) {
+        match self {
+            SlitOrientation::Vertical => (frame.width, frame.height),
+            SlitOrientation::Horizontal => (frame.height, frame.width),
+        }
+    }
+
+    /// Pixel value at a position along the dispersion axis and along the slit.
+    pub fn value(self, frame: &Frame, dispersion: usize, slit: usize) -> f32 {
+        match self {
+            SlitOrientation::Vertical => frame.value(dispersion, slit),
+            SlitOrientation::Horizontal => frame.value(slit, dispersion),
+        }
+    }
+}
+
+/// Intensity in ADU along the dispersion axis.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub values: Vec<f64>,
+}
+
+impl Profile {
+    /// Average a band of positions along the slit.
+    pub fn extract(
+        frame: &Frame,
+        orientation: SlitOrientation,
+        band: RangeInclusive<usize>,
+    ) -> Self {
+        let (len, slit_len) = orientation.dimensions(frame);
+        let start = (*band.start()).min(slit_len.saturating_sub(1));
+        let end = (*band.end()).clamp(start, slit_len.saturating_sub(1));
+
+        let mut values = vec![0.; len];
+        for slit in start..=end {
+            for (i, value) in values.iter_mut().enumerate() {
+                *value += orientation.value(frame, i, slit) as f64;
+            }
+        }
+        let count = (end - start + 1) as f64;
+        values.iter_mut().for_each(|value| *value /= count);
+        Self { values }
+    }
+
+    /// Profile divided by a parabola fitted through it, so that the instrument response
+    /// and the solar continuum slope are flattened around 1.
+    pub fn detrended(&self) -> Self {
+        let x: Vec<f64> = (0..self.values.len()).map(|i| i as f64).collect();
+        let Some(trend) = fit::polyfit(&x, &self.values, 2) else {
+            return self.clone();
+        };
+        Self {
+            values: self
+                .values
+                .iter()
+                .zip(&x)
+                .map(|(value, x)| value / fit::polyval(&trend, *x).max(1e-6))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    calibration,
+    dispersion::Dispersion,
+    line_fit::{LineFit, LineShape},
+    profile::{Profile, SlitOrientation},
+};
+use crate::frame::Frame;
+
+/// Detection threshold of the lines in noise sigmas.
+const DETECTION_SIGMA: f64 = 5.;
+
+/// Width of a single line.
+#[derive(Debug, Clone)]
+pub struct LineResolution {
+    pub fit: LineFit,
+    /// Wavelength of the line centre in Å, when calibrated.
+    pub wavelength: Option<f64>,
+    /// FWHM in Å, when calibrated.
+    pub fwhm_angstrom: Option<f64>,
+}
+
+impl LineResolution {
+    /// Resolving power `λ / Δλ`.
+    pub fn resolving_power(&self) -> Option<f64> {
+        Some(self.wavelength? / self.fwhm_angstrom?)
+    }
+}
+
+/// Fit every line at least `2 * half_window` pixels from its neighbours.
+/// Lamp spectra are measured on emission lines, solar spectra on absorption lines.
+pub fn measure(
+    profile: &Profile,
+    emission: bool,
+    shape: LineShape,
+    half_window: usize,
+    dispersion: Option<&Dispersion>,
+) -> Vec<LineResolution> {
+    let peaks = if emission {
+        calibration::find_peaks(profile, DETECTION_SIGMA)
+    } else {
+        calibration::find_absorption_lines(profile, DETECTION_SIGMA)
+    };
+    let isolated = |position: f64| {
+        peaks.iter().all(|other| {
+            other.position == position
+                || (other.position - position).abs() > 2. * half_window as f64
+        })
+    };
+
+    peaks
+        .iter()
+        .filter(|peak| isolated(peak.position))
+        .filter_map(|peak| LineFit::fit(profile, peak.position, half_window, shape))
+        .filter(|fit| (fit.amplitude > 0.) == emission)
+        .map(|fit| LineResolution {
+            fit,
+            wavelength: dispersion.map(|d| d.wavelength(fit.centre)),
+            fwhm_angstrom: dispersion.map(|d| fit.fwhm * d.angstrom_per_pixel(fit.centre).abs()),
+        })
+        .collect()
+}
+
+/// Lines measured in one band of the slit.
+#[derive(Debug, Clone)]
+pub struct BandResolution {
+    /// Centre of the band along the slit in pixels.
+    pub slit: f64,
+    pub lines: Vec<LineResolution>,
+}
+
+impl BandResolution {
+    /// Median FWHM in pixels.
+    pub fn fwhm(&self) -> Option<f64> {
+        median(self.lines.iter().map(|line| line.fit.fwhm).collect())
+    }
+}
+
+/// Split the slit into `bands` equal bands and measure the lines in the profile of each,
+/// bands without any fitted line are left out.
+pub fn measure_bands(
+    frame: &Frame,
+    orientation: SlitOrientation,
+    bands: usize,
+    emission: bool,
+    shape: LineShape,
+    half_window: usize,
+    dispersion: Option<&Dispersion>,
+) -> Vec<BandResolution> {
+    let (_, slit_len) = orientation.dimensions(frame);
+    let bands = bands.clamp(1, slit_len.max(1));
+    (0..bands)
+        .map(|i| {
+            let start = i * slit_len / bands;
+            let end = ((i + 1) * slit_len / bands).saturating_sub(1).max(start);
+            let profile = Profile::extract(frame, orientation, start..=end);
+            BandResolution {
+                slit: (start + end) as f64 / 2.,
+                lines: measure(&profile, emission, shape, half_window, dispersion),
+            }
+        })
+        .filter(|band| !band.lines.is_empty())
+        .collect()
+}
+
+/// Range of the median FWHM of the bands in pixels, `None` with less than two bands.
+pub fn spread(bands: &[BandResolution]) -> Option<f64> {
+    let fwhm: Vec<f64> = bands.iter().filter_map(BandResolution::fwhm).collect();
+    if fwhm.len() < 2 {
+        return None;
+    }
+    let min = fwhm.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = fwhm.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    Some(max - min)
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+/// Summary of a measurement kept to compare the resolution over time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionRecord {
+    pub time: DateTime<Utc>,
+    /// Instrument setup the measurement applies to.
+    pub configuration: String,
+    pub shape: LineShape,
+    pub lines: usize,
+    /// Median FWHM in pixels.
+    pub fwhm: f64,
+    /// Median FWHM in Å.
+    pub fwhm_angstrom: Option<f64>,
+    /// Median resolving power.
+    pub resolving_power: Option<f64>,
+    /// Range of the median FWHM of the slit bands in pixels.
+    #[serde(default)]
+    pub spread: Option<f64>,
+}
+
+impl ResolutionRecord {
+    pub fn new(bands: &[BandResolution], shape: LineShape, configuration: String) -> Option<Self> {
+        let lines: Vec<&LineResolution> = bands.iter().flat_map(|band| &band.lines).collect();
+        Some(Self {
+            time: Utc::now(),
+            configuration,
+            shape,
+            lines: lines.len(),
+            fwhm: median(lines.iter().map(|line| line.fit.fwhm).collect())?,
+            fwhm_angstrom: median(lines.iter().filter_map(|line| line.fwhm_angstrom).collect()),
+            resolving_power: median(
+                lines
+                    .iter()
+                    .filter_map(|line| line.resolving_power())
+                    .collect(),
+            ),
+            spread: spread(bands),
+        })
+    }
+}
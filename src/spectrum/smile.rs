@@ -0,0 +1,256 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fit, focus,
+    profile::{Profile, SlitOrientation},
+};
+use crate::frame::Frame;
+
+/// Bands darker than this fraction of the brightest band are off the slit and skipped.
+const DARK_FRACTION: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+pub enum SmileError {
+    TooFewPoints(usize),
+    FitFailed,
+}
+
+impl Display for SmileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmileError::TooFewPoints(count) => {
+                write!(f, "The line was found in only {} bands.", count)
+            }
+            SmileError::FitFailed => write!(f, "The curvature fit failed."),
+        }
+    }
+}
+
+impl Error for SmileError {}
+
+/// Curvature of the spectral lines along the slit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Smile {
+    /// Line position along the dispersion axis as a polynomial of the position along the
+    /// slit, lowest order first.
+    pub coefficients: Vec<f64>,
+    pub orientation: SlitOrientation,
+    /// Position along the slit the line is straightened to.
+    pub reference: f64,
+    /// Measured line positions along the slit and along the dispersion axis.
+    #[serde(skip)]
+    pub points: Vec<(f64, f64)>,
+    /// RMS of the residuals in pixels.
+    pub rms: f64,
+}
+
+impl Smile {
+    /// Follow the absorption line near `guess` along the slit in bands of `band` pixels
+    /// and fit a polynomial of `degree` to its centre.
+    pub fn measure(
+        frame: &Frame,
+        orientation: SlitOrientation,
+        guess: f64,
+        half_window: usize,
+        degree: usize,
+        band: usize,
+    ) -> Result<Self, SmileError> {
+        let (_, slit_len) = orientation.dimensions(frame);
+        let points = track(frame, orientation, guess, band, |profile, position| {
+            focus::line_width(profile, position, half_window).map(|line| line.centre)
+        });
+
+        let mut smile = Self::fit(&points, orientation, slit_len, degree)?;
+        let kept: Vec<(f64, f64)> = points
+            .iter()
+            .filter(|(slit, centre)| (smile.centre(*slit) - centre).abs() <= 3. * smile.rms)
+            .cloned()
+            .collect();
+        if kept.len() < points.len() && kept.len() >= degree + 2 {
+            smile = Self::fit(&kept, orientation, slit_len, degree)?;
+        }
+        Ok(smile)
+    }
+
+    fn fit(
+        points: &[(f64, f64)],
+        orientation: SlitOrientation,
+        slit_len: usize,
+        degree: usize,
+    ) -> Result<Self, SmileError> {
+        // at least one more point than coefficients to measure the residuals
+        if points.len() < degree + 2 {
+            return Err(SmileError::TooFewPoints(points.len()));
+        }
+        let (slit, centre): (Vec<f64>, Vec<f64>) = points.iter().cloned().unzip();
+        let coefficients = fit::polyfit(&slit, &centre, degree).ok_or(SmileError::FitFailed)?;
+        let rms = (points
+            .iter()
+            .map(|(slit, centre)| (fit::polyval(&coefficients, *slit) - centre).powi(2))
+            .sum::<f64>()
+            / points.len() as f64)
+            .sqrt();
+        Ok(Self {
+            coefficients,
+            orientation,
+            reference: slit_len as f64 / 2.,
+            points: points.to_vec(),
+            rms,
+        })
+    }
+
+    /// Line position along the dispersion axis at a position along the slit.
+    pub fn centre(&self, slit: f64) -> f64 {
+        fit::polyval(&self.coefficients, slit)
+    }
+
+    /// Derivative of the line position along the dispersion axis by the position along the slit.
+    pub fn slope(&self, slit: f64) -> f64 {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, c)| k as f64 * c * slit.powi(k as i32 - 1))
+            .sum()
+    }
+
+    /// Offset of the line from its position at the reference, in pixels along the
+    /// dispersion axis.
+    pub fn shift(&self, slit: f64) -> f64 {
+        self.centre(slit) - self.centre(self.reference)
+    }
+
+    /// Peak to valley curvature over the slit in pixels.
+    pub fn sagitta(&self, slit_len: usize) -> f64 {
+        let (min, max) = (0..slit_len).fold((f64::MAX, f64::MIN), |(min, max), slit| {
+            let shift = self.shift(slit as f64);
+            (min.min(shift), max.max(shift))
+        });
+        max - min
+    }
+
+    /// Frame resampled along the dispersion axis so that the lines are straight.
+    pub fn correct(&self, frame: &Frame) -> Frame {
+        let (_, slit_len) = self.orientation.dimensions(frame);
+        let shifts: Vec<f32> = (0..slit_len)
+            .map(|slit| self.shift(slit as f64) as f32)
+            .collect();
+        match self.orientation {
+            SlitOrientation::Vertical => frame.remap(frame.width, frame.height, |x, y| {
+                (x as f32 + shifts[y], y as f32)
+            }),
+            SlitOrientation::Horizontal => frame.remap(frame.width, frame.height, |x, y| {
+                (x as f32, y as f32 + shifts[x])
+            }),
+        }
+    }
+}
+
+/// Follow a line along the slit in bands of `band` pixels, starting near `guess` at the
+/// middle of the slit so it can drift out of the first window towards the ends.
+/// `locate` finds the line in a band profile near a position along the dispersion axis.
+/// Returns the positions along the slit and along the dispersion axis, bands off the
+/// slit are skipped.
+pub fn track(
+    frame: &Frame,
+    orientation: SlitOrientation,
+    guess: f64,
+    band: usize,
+    locate: impl Fn(&Profile, f64) -> Option<f64>,
+) -> Vec<(f64, f64)> {
+    let (_, slit_len) = orientation.dimensions(frame);
+    let band = band.max(1);
+    let bands: Vec<(f64, Profile)> = (0..slit_len / band)
+        .map(|i| {
+            let start = i * band;
+            (
+                start as f64 + (band - 1) as f64 / 2.,
+                Profile::extract(frame, orientation, start..=start + band - 1),
+            )
+        })
+        .collect();
+    let mean =
+        |profile: &Profile| profile.values.iter().sum::<f64>() / profile.values.len().max(1) as f64;
+    let brightest = bands
+        .iter()
+        .map(|(_, profile)| mean(profile))
+        .fold(0., f64::max);
+
+    let middle = bands.len() / 2;
+    let mut points = vec![];
+    let upper = (middle..bands.len()).collect::<Vec<_>>();
+    let lower = (0..middle).rev().collect::<Vec<_>>();
+    for half in [upper, lower] {
+        let mut position = guess;
+        for i in half {
+            let (slit, profile) = &bands[i];
+            if mean(profile) < DARK_FRACTION * brightest {
+                continue;
+            }
+            if let Some(centre) = locate(profile, position) {
+                position = centre;
+                points.push((*slit, centre));
+            }
+        }
+    }
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    points
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::frame::Pixels;
+
+    /// Continuum with an absorption line at `centre(slit)` along the dispersion axis.
+    pub(crate) fn frame(orientation: SlitOrientation, centres: impl Fn(f64) -> Vec<f64>) -> Frame {
+        let (dispersion_len, slit_len) = (200, 300);
+        let (width, height) = match orientation {
+            SlitOrientation::Vertical => (dispersion_len, slit_len),
+            SlitOrientation::Horizontal => (slit_len, dispersion_len),
+        };
+        let mut pixels = vec![0; width * height];
+        for slit in 0..slit_len {
+            let centres = centres(slit as f64);
+            for x in 0..dispersion_len {
+                let absorption: f64 = centres
+                    .iter()
+                    .map(|centre| 1. - 0.6 * (-0.5 * ((x as f64 - centre) / 2.).powi(2)).exp())
+                    .product();
+                let (column, row) = match orientation {
+                    SlitOrientation::Vertical => (x, slit),
+                    SlitOrientation::Horizontal => (slit, x),
+                };
+                pixels[row * width + column] = (2000. * absorption).round() as u16;
+            }
+        }
+        Frame {
+            width,
+            height,
+            pixels: Pixels::Mono16(pixels),
+            bayer_pattern: None,
+        }
+    }
+
+    #[test]
+    fn curved_lines_are_measured() {
+        // 8 px from the middle to the ends of the slit
+        let curvature = 8. / 150f64.powi(2);
+        let truth = |slit: f64| 100. + curvature * (slit - 150.).powi(2);
+        for orientation in [SlitOrientation::Vertical, SlitOrientation::Horizontal] {
+            let frame = frame(orientation, |slit| vec![truth(slit)]);
+            let smile = Smile::measure(&frame, orientation, 101., 10, 2, 10).unwrap();
+            for slit in [10., 80., 150., 220., 290.] {
+                assert!(
+                    (smile.centre(slit) - truth(slit)).abs() < 0.2,
+                    "{:?}",
+                    smile
+                );
+            }
+            assert!((smile.sagitta(300) - 8.).abs() < 0.3);
+            assert!(smile.rms < 0.1);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    profile::SlitOrientation,
+    smile::{Smile, SmileError},
+};
+use crate::frame::Frame;
+
+/// Rotation of the spectral lines relative to the slit axis of the sensor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tilt {
+    /// Counter-clockwise rotation of the lines on screen in degrees.
+    pub angle: f64,
+    /// Spread of the angles of the measured lines in degrees.
+    pub spread: f64,
+    pub orientation: SlitOrientation,
+    /// Curvature of each measured line.
+    #[serde(skip)]
+    pub lines: Vec<Smile>,
+}
+
+impl Tilt {
+    /// Average the slope at the middle of the slit of the absorption lines near `guesses`.
+    pub fn measure(
+        frame: &Frame,
+        orientation: SlitOrientation,
+        guesses: &[f64],
+        half_window: usize,
+        band: usize,
+    ) -> Result<Self, SmileError> {
+        let lines: Vec<Smile> = guesses
+            .iter()
+            .filter_map(|guess| {
+                Smile::measure(frame, orientation, *guess, half_window, 2, band).ok()
+            })
+            .collect();
+        if lines.is_empty() {
+            return Err(SmileError::TooFewPoints(0));
+        }
+
+        let angles: Vec<f64> = lines
+            .iter()
+            .map(|line| {
+                let slope = line.slope(line.reference).atan().to_degrees();
+                match orientation {
+                    SlitOrientation::Vertical => slope,
+                    SlitOrientation::Horizontal => -slope,
+                }
+            })
+            .collect();
+        let angle = angles.iter().sum::<f64>() / angles.len() as f64;
+        let spread =
+            (angles.iter().map(|a| (a - angle).powi(2)).sum::<f64>() / angles.len() as f64).sqrt();
+        Ok(Self {
+            angle,
+            spread,
+            orientation,
+            lines,
+        })
+    }
+
+    /// Frame rotated about its centre so that the lines run along the slit axis.
+    pub fn correct(&self, frame: &Frame) -> Frame {
+        let (sin, cos) = (self.angle.to_radians() as f32).sin_cos();
+        let (cx, cy) = (
+            (frame.width as f32 - 1.) / 2.,
+            (frame.height as f32 - 1.) / 2.,
+        );
+        frame.remap(frame.width, frame.height, |x, y| {
+            let (u, v) = (x as f32 - cx, y as f32 - cy);
+            (cx + u * cos + v * sin, cy - u * sin + v * cos)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::smile::tests::frame;
+
+    #[test]
+    fn tilted_lines_are_measured() {
+        let slope = 1.5f64.to_radians().tan();
+        for orientation in [SlitOrientation::Vertical, SlitOrientation::Horizontal] {
+            let frame = frame(orientation, |slit| {
+                [50., 100., 150.]
+                    .iter()
+                    .map(|centre| centre + slope * (slit - 150.))
+                    .collect()
+            });
+            let tilt = Tilt::measure(&frame, orientation, &[50., 100., 150.], 10, 10).unwrap();
+            assert_eq!(tilt.lines.len(), 3);
+            let angle = match orientation {
+                SlitOrientation::Vertical => 1.5,
+                SlitOrientation::Horizontal => -1.5,
+            };
+            assert!((tilt.angle - angle).abs() < 0.05, "{:?}", tilt);
+            assert!(tilt.spread < 0.05);
+        }
+    }
+}
@@ -1,9 +1,49 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, path::PathBuf};
 
+use chrono::{DateTime, Utc};
 use eframe::egui;
-use egui_plot::{Line, PlotPoints};
 
-use crate::asi::asi_api::ASICameraInfo;
+use crate::{
+    asi::{
+        asi_api::ASIControlType,
+        auto_exposure::{AutoExposure, AutoExposureState},
+        camera::{CameraCommand, CameraDriver},
+    },
+    fits::Acquisition,
+    frame::FrameSource,
+    imaging::{histogram::Histogram, presentation::Presentation},
+    ser::SerMetadata,
+    solex::{
+        driver::{SolEXCommand, SolEXDriver},
+        grating::Grating,
+        motor::{ApproachDirection, HomeReference, MotorCalibration},
+        solex_api,
+    },
+    spectrum::{
+        calibration::WavelengthCalibration, continuum::InstrumentResponse, dispersion::Dispersion,
+        smile::Smile, tilt::Tilt,
+    },
+};
+
+use super::{
+    calibration::CalibrationPanel,
+    continuum::ContinuumPanel,
+    correction::{Corrections, Corrector},
+    dark_flat::DarkFlatPanel,
+    doppler::DopplerPanel,
+    focus::FocusAssistant,
+    histogram::histogram_ui,
+    identification::IdentificationPanel,
+    image_view::ImageView,
+    line_analysis::LineAnalysisPanel,
+    recorder::RecorderPanel,
+    replay::ReplayPanel,
+    resolution::ResolutionPanel,
+    smile::SmilePanel,
+    spectroheliogram::SpectroheliogramPanel,
+    spectrum_plot::SpectrumPlot,
+    tilt::TiltPanel,
+};
 
 #[derive(Clone, Copy)]
 pub enum ConnectionStatus {
@@ -13,44 +53,353 @@ pub enum ConnectionStatus {
     Unconnected,
 }
 
-#[derive(Clone)]
-pub struct ASIStatus {
-    pub connected_cams: Vec<ASICameraInfo>,
-    pub connection_status: ConnectionStatus,
+#[derive(Clone, Copy, PartialEq)]
+enum ExposureUnit {
+    Microseconds,
+    Milliseconds,
+    Seconds,
 }
 
-#[derive(Clone)]
-pub struct SolEXStatus {
-    pub connection_status: ConnectionStatus,
+impl ExposureUnit {
+    fn microseconds(self) -> f64 {
+        match self {
+            ExposureUnit::Microseconds => 1.,
+            ExposureUnit::Milliseconds => 1e3,
+            ExposureUnit::Seconds => 1e6,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            ExposureUnit::Microseconds => "µs",
+            ExposureUnit::Milliseconds => "ms",
+            ExposureUnit::Seconds => "s",
+        }
+    }
 }
 
 pub struct App {
-    asi_status: Arc<ASIStatus>,
-    solex_status: Arc<SolEXStatus>,
-    fake_bool: bool,
+    camera: CameraDriver,
+    camera_id: Option<i32>,
+    exposure_unit: ExposureUnit,
+    last_frame: u64,
+    /// Resamples the frames for the tilt and smile before the analysis.
+    corrector: Corrector,
+    last_corrected: u64,
+    /// UTC time the analysed frame was captured, when known.
+    frame_time: Option<DateTime<Utc>>,
+    auto_exposure: AutoExposure,
+    solex: SolEXDriver,
+    solex_ports: Vec<String>,
+    solex_port: Option<String>,
+    grating: Grating,
+    rotation_speed: u8,
+    jog_steps: i32,
+    target_wavelength: f64,
+    image_view: ImageView,
+    spectrum_plot: SpectrumPlot,
+    focus: FocusAssistant,
+    calibration: CalibrationPanel,
+    smile: SmilePanel,
+    tilt: TiltPanel,
+    resolution: ResolutionPanel,
+    line_analysis: LineAnalysisPanel,
+    doppler: DopplerPanel,
+    continuum: ContinuumPanel,
+    identification: IdentificationPanel,
+    spectroheliogram: SpectroheliogramPanel,
+    recorder: RecorderPanel,
+    replay: ReplayPanel,
+    dark_flat: DarkFlatPanel,
 }
 
 impl App {
-    pub fn new() -> Self {
-        let asi_status = Arc::new(ASIStatus {
-            connected_cams: vec![],
-            connection_status: ConnectionStatus::Unconnected,
-        });
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let camera = CameraDriver::new();
+        let camera_id = camera
+            .status()
+            .connected_cams
+            .first()
+            .map(|cam| cam.camera_id);
 
-        let solex_status = Arc::new(SolEXStatus {
-            connection_status: ConnectionStatus::Unconnected,
-        });
+        let (
+            calibration,
+            grating,
+            wavelength_calibration,
+            atlas_files,
+            smile,
+            tilt,
+            resolution_history,
+            instrument_response,
+            enhancements,
+            presentation,
+            ser_metadata,
+        ) = match cc.storage {
+            Some(storage) => (
+                eframe::get_value(storage, "motor_calibration").unwrap_or_default(),
+                eframe::get_value(storage, "grating").unwrap_or_default(),
+                eframe::get_value::<Option<WavelengthCalibration>>(
+                    storage,
+                    "wavelength_calibration",
+                )
+                .flatten(),
+                eframe::get_value::<Vec<PathBuf>>(storage, "atlas_files").unwrap_or_default(),
+                eframe::get_value::<Option<Smile>>(storage, "smile").flatten(),
+                eframe::get_value::<Option<Tilt>>(storage, "tilt").flatten(),
+                eframe::get_value(storage, "resolution_history").unwrap_or_default(),
+                eframe::get_value::<Option<InstrumentResponse>>(storage, "instrument_response")
+                    .flatten(),
+                eframe::get_value(storage, "enhancements").unwrap_or_default(),
+                eframe::get_value(storage, "presentation").unwrap_or_default(),
+                eframe::get_value(storage, "ser_metadata").unwrap_or_default(),
+            ),
+            None => (
+                MotorCalibration::default(),
+                Grating::default(),
+                None,
+                vec![],
+                None,
+                None,
+                vec![],
+                None,
+                BTreeMap::new(),
+                Presentation::default(),
+                SerMetadata::default(),
+            ),
+        };
+
+        let solex_ports = solex_api::available_ports();
 
         Self {
-            asi_status,
-            solex_status,
-            fake_bool: false,
+            camera,
+            camera_id,
+            exposure_unit: ExposureUnit::Milliseconds,
+            last_frame: 0,
+            corrector: Corrector::new(cc.egui_ctx.clone()),
+            last_corrected: 0,
+            frame_time: None,
+            auto_exposure: AutoExposure::new(),
+            solex: SolEXDriver::new(calibration),
+            solex_port: solex_ports.first().cloned(),
+            solex_ports,
+            grating,
+            rotation_speed: 4,
+            jog_steps: 10,
+            target_wavelength: 656.28,
+            image_view: ImageView::new(cc.egui_ctx.clone()),
+            spectrum_plot: SpectrumPlot::new(),
+            focus: FocusAssistant::new(),
+            calibration: CalibrationPanel::new(
+                cc.egui_ctx.clone(),
+                wavelength_calibration,
+                &atlas_files,
+            ),
+            smile: SmilePanel::new(smile),
+            tilt: TiltPanel::new(tilt),
+            resolution: ResolutionPanel::new(resolution_history),
+            line_analysis: LineAnalysisPanel::new(),
+            doppler: DopplerPanel::new(),
+            continuum: ContinuumPanel::new(instrument_response),
+            identification: IdentificationPanel::new(),
+            spectroheliogram: SpectroheliogramPanel::new(
+                cc.egui_ctx.clone(),
+                enhancements,
+                presentation,
+            ),
+            recorder: RecorderPanel::new(ser_metadata),
+            replay: ReplayPanel::new(),
+            dark_flat: DarkFlatPanel::new(),
+        }
+    }
+
+    /// Wavelength solution of the spectrum profile, predicted from the grating angle.
+    fn predicted_dispersion(&self) -> Option<Dispersion> {
+        let motor = self.solex.status().motor.clone();
+        let status = self.camera.status();
+        let camera = status.camera.as_ref()?;
+        let profile = self.spectrum_plot.live.as_ref()?;
+        motor.homed.then(|| {
+            Dispersion::predicted(
+                &self.grating,
+                motor.angle(),
+                camera.pixel_size * status.bin as f64,
+                profile.values.len(),
+            )
+        })
+    }
+
+    /// Whether the measured wavelength solution applies to the current motor position and binning.
+    fn is_calibration_valid(&self) -> bool {
+        self.calibration.solution.as_ref().is_some_and(|solution| {
+            let motor = &self.solex.status().motor;
+            motor.homed
+                && solution.motor_position == motor.position
+                && solution.bin == self.camera.status().bin
+        })
+    }
+
+    /// Instrument setup the resolution history is kept for.
+    fn configuration(&self) -> String {
+        let motor = &self.solex.status().motor;
+        let wavelength = if motor.homed {
+            format!("{:.1} nm", self.grating.wavelength(motor.angle()))
+        } else {
+            "not homed".to_string()
+        };
+        format!(
+            "{} l/mm, order {}, f {} mm, bin {}, {}",
+            self.grating.lines_per_mm,
+            self.grating.order,
+            self.grating.camera_focal_length,
+            self.camera.status().bin,
+            wavelength
+        )
+    }
+
+    /// Measured wavelength solution if valid, otherwise the predicted one.
+    fn dispersion(&self) -> Option<Dispersion> {
+        match &self.calibration.solution {
+            Some(solution) if self.is_calibration_valid() => Some(solution.dispersion.clone()),
+            _ => self.predicted_dispersion(),
+        }
+    }
+
+    /// Settings of the displayed frames for the headers of exported files, without the
+    /// camera ones when replaying.
+    fn acquisition(&self, dispersion: Option<&Dispersion>) -> Acquisition {
+        let wavelength = dispersion
+            .zip(self.spectrum_plot.live.as_ref())
+            .map(|(dispersion, profile)| dispersion.wavelength(profile.values.len() as f64 / 2.));
+        let metadata = self.recorder.metadata.clone();
+        if self.replay.is_active() {
+            return Acquisition {
+                date: self.frame_time,
+                wavelength,
+                metadata,
+                ..Default::default()
+            };
+        }
+        let status = self.camera.status();
+        Acquisition {
+            date: self.frame_time,
+            exposure: Some(status.exposure as f64 * 1e-6),
+            gain: Some(status.gain),
+            temperature: status.temperature,
+            bin: Some(status.bin),
+            wavelength,
+            camera: status.camera.as_ref().map(|camera| camera.name.clone()),
+            metadata,
         }
     }
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(
+            storage,
+            "motor_calibration",
+            &self.solex.status().motor.calibration,
+        );
+        eframe::set_value(storage, "grating", &self.grating);
+        eframe::set_value(
+            storage,
+            "wavelength_calibration",
+            &self.calibration.solution,
+        );
+        eframe::set_value(storage, "atlas_files", &self.calibration.atlas_paths());
+        eframe::set_value(storage, "smile", &self.smile.smile);
+        eframe::set_value(storage, "tilt", &self.tilt.tilt);
+        eframe::set_value(storage, "resolution_history", &self.resolution.history);
+        eframe::set_value(storage, "instrument_response", &self.continuum.response);
+        eframe::set_value(storage, "enhancements", &self.spectroheliogram.enhancements);
+        eframe::set_value(storage, "presentation", &self.spectroheliogram.presentation);
+        eframe::set_value(storage, "ser_metadata", &self.recorder.metadata);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // a replayed recording or a saved frame takes the place of the camera
+        let (frame, time, frame_count) = if self.replay.is_active() {
+            self.replay.latest()
+        } else {
+            self.camera.latest()
+        };
+        if let Some(frame) = frame.filter(|_| frame_count != self.last_frame) {
+            self.last_frame = frame_count;
+            let orientation = self.spectrum_plot.orientation;
+            self.recorder.frame = Some(frame.clone());
+            self.recorder.frame_time = time;
+            // measured on the raw camera frame being evaluated, the histogram of the view
+            // may be older than this frame
+            if self.auto_exposure.state == AutoExposureState::Running && !self.replay.is_active() {
+                let histogram = Histogram::compute(&frame, 256);
+                let status = self.camera.status().clone();
+                for (control_type, value) in
+                    self.auto_exposure
+                        .step(histogram.peak(), histogram.saturated(), &status)
+                {
+                    self.camera
+                        .send(CameraCommand::Control(control_type, value));
+                }
+            }
+            let corrections = Corrections {
+                masters: self.dark_flat.masters(),
+                tilt: self.tilt.correction(orientation),
+                smile: self.smile.correction(orientation),
+            };
+            self.corrector.send(frame, time, corrections);
+        }
+
+        let (frame, time, frame_count) = self.corrector.latest();
+        if let Some(frame) = frame.filter(|_| frame_count != self.last_corrected) {
+            self.last_corrected = frame_count;
+            self.frame_time = time;
+            let orientation = self.spectrum_plot.orientation;
+            self.dark_flat.mismatch = self.corrector.dark_flat_error();
+            if let Some(calibrated) = self.corrector.calibrated() {
+                self.tilt.update(&calibrated, orientation);
+            }
+            if let Some(derotated) = self.corrector.derotated() {
+                self.smile.update(&derotated, orientation);
+            }
+            self.resolution.update(&frame, orientation);
+            self.doppler.update(&frame, orientation);
+            self.spectroheliogram.update(&frame);
+            self.spectrum_plot.update(&frame);
+            if let Some(profile) = &self.spectrum_plot.live {
+                self.continuum.update(profile);
+                self.line_analysis.update(
+                    profile,
+                    self.spectrum_plot.selected,
+                    self.dispersion().as_ref(),
+                );
+                self.identification.update(
+                    &frame,
+                    orientation,
+                    profile,
+                    self.dispersion().as_ref(),
+                    self.is_calibration_valid(),
+                );
+            }
+            let predicted = self.predicted_dispersion();
+            let bin = self.camera.status().bin;
+            self.calibration.update(
+                &frame,
+                self.spectrum_plot.orientation,
+                &self.solex,
+                predicted.as_ref(),
+                bin,
+            );
+            self.image_view.update(frame);
+            if let Some(profile) = self
+                .spectrum_plot
+                .live
+                .as_ref()
+                .filter(|_| !self.spectrum_plot.frozen)
+            {
+                self.focus.update(profile);
+            }
+        }
+
         egui::TopBottomPanel::top("Top").show(ctx, |ui| {
             ui.horizontal_wrapped(|ui| {
                 egui::widgets::global_dark_light_mode_switch(ui);
@@ -75,6 +424,10 @@ impl eframe::App for App {
                 )
                 .default_open(true)
                 .show(ui, |ui| {
+                    let status = self.camera.status().clone();
+                    let is_connected =
+                        matches!(status.connection_status, ConnectionStatus::Connected);
+
                     ui.heading("Camera 📷");
                     ui.add_space(5.);
 
@@ -82,58 +435,106 @@ impl eframe::App for App {
                         ui.label("Camera");
 
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                            let is_selectable = matches!(
-                                self.asi_status.connection_status,
-                                ConnectionStatus::Unconnected
-                            );
+                            let is_selectable =
+                                matches!(status.connection_status, ConnectionStatus::Unconnected);
 
-                            let mut a = 0;
                             ui.add_enabled_ui(is_selectable, |ui| {
-                                egui::ComboBox::from_id_source("combo_cam").show_ui(ui, |ui| {
-                                    for cam in self.asi_status.connected_cams.iter() {
-                                        ui.selectable_value(&mut a, cam.camera_id, &cam.name);
-                                    }
-                                    ui.style_mut().wrap = Some(false);
-                                    ui.set_min_width(60.0);
-                                });
+                                if ui.button("🔄").clicked() {
+                                    self.camera.refresh();
+                                }
+
+                                let selected = status
+                                    .connected_cams
+                                    .iter()
+                                    .find(|cam| Some(cam.camera_id) == self.camera_id)
+                                    .map(|cam| cam.name.clone())
+                                    .unwrap_or_default();
+                                egui::ComboBox::from_id_source("combo_cam")
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        for cam in status.connected_cams.iter() {
+                                            ui.selectable_value(
+                                                &mut self.camera_id,
+                                                Some(cam.camera_id),
+                                                &cam.name,
+                                            );
+                                        }
+                                        ui.style_mut().wrap = Some(false);
+                                        ui.set_min_width(60.0);
+                                    });
                             });
 
-                            let connected = match self.asi_status.connection_status {
+                            let connected = match status.connection_status {
                                 ConnectionStatus::Connected => "Disconnect ⏹",
                                 ConnectionStatus::Connecting => "Connecting",
                                 ConnectionStatus::Disconnecting => "Disconnecting",
                                 ConnectionStatus::Unconnected => "Connect ▶",
                             };
 
-                            let is_enabled = matches!(
-                                self.asi_status.connection_status,
-                                ConnectionStatus::Connected | ConnectionStatus::Unconnected
-                            );
+                            let is_enabled = match status.connection_status {
+                                ConnectionStatus::Connected => true,
+                                ConnectionStatus::Unconnected => self.camera_id.is_some(),
+                                _ => false,
+                            };
 
                             ui.add_enabled_ui(is_enabled, |ui| {
                                 if ui.button(connected).clicked() {
-                                    self.fake_bool = !self.fake_bool;
+                                    match status.connection_status {
+                                        ConnectionStatus::Connected => self.camera.disconnect(),
+                                        _ => {
+                                            if let Some(id) = self.camera_id {
+                                                self.camera.connect(id, ui.ctx().clone());
+                                            }
+                                        }
+                                    }
                                 }
                             });
 
-                            if !is_enabled {
+                            if matches!(
+                                status.connection_status,
+                                ConnectionStatus::Connecting | ConnectionStatus::Disconnecting
+                            ) {
                                 ui.add(egui::Spinner::new());
                             }
                         })
                     });
 
+                    if let Some(error) = &status.error {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+
                     ui.separator();
                     ui.heading("Image");
                     ui.add_space(5.);
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Binning");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_connected, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                egui::ComboBox::from_id_source("combo_bin").show_ui(ui, |ui| {
-                                    ui.style_mut().wrap = Some(false);
-                                    ui.set_min_width(60.0);
-                                });
+                                let mut bin = status.bin;
+                                egui::ComboBox::from_id_source("combo_bin")
+                                    .selected_text(format!("Bin{}", bin))
+                                    .show_ui(ui, |ui| {
+                                        for supported in status
+                                            .camera
+                                            .iter()
+                                            .flat_map(|camera| camera.supported_bins.iter())
+                                        {
+                                            ui.selectable_value(
+                                                &mut bin,
+                                                *supported,
+                                                format!("Bin{}", supported),
+                                            );
+                                        }
+                                        ui.style_mut().wrap = Some(false);
+                                        ui.set_min_width(60.0);
+                                    });
+                                if bin != status.bin {
+                                    self.camera.send(CameraCommand::Format {
+                                        bin,
+                                        image_type: status.image_type,
+                                    });
+                                }
                             })
                         });
                     });
@@ -143,18 +544,28 @@ impl eframe::App for App {
                     ui.add_space(5.);
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Exposure");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_connected, |ui| {
                             ui.vertical(|ui| {
                                 ui.with_layout(
                                     egui::Layout::right_to_left(egui::Align::TOP),
                                     |ui| {
-                                        egui::ComboBox::from_id_source("combo_exp").show_ui(
-                                            ui,
-                                            |ui| {
+                                        egui::ComboBox::from_id_source("combo_exp")
+                                            .selected_text(self.exposure_unit.suffix())
+                                            .show_ui(ui, |ui| {
+                                                for unit in [
+                                                    ExposureUnit::Microseconds,
+                                                    ExposureUnit::Milliseconds,
+                                                    ExposureUnit::Seconds,
+                                                ] {
+                                                    ui.selectable_value(
+                                                        &mut self.exposure_unit,
+                                                        unit,
+                                                        unit.suffix(),
+                                                    );
+                                                }
                                                 ui.style_mut().wrap = Some(false);
                                                 ui.set_min_width(60.0);
-                                            },
-                                        );
+                                            });
                                     },
                                 );
 
@@ -163,8 +574,29 @@ impl eframe::App for App {
                                 ui.with_layout(
                                     egui::Layout::right_to_left(egui::Align::TOP),
                                     |ui| {
-                                        let mut scalar = 0;
-                                        ui.add(egui::Slider::new(&mut scalar, 0..=360));
+                                        let Some(caps) = status.control(ASIControlType::Exposure)
+                                        else {
+                                            return;
+                                        };
+                                        let scale = self.exposure_unit.microseconds();
+                                        let mut exposure = status.exposure as f64 / scale;
+                                        let range = caps.min_value as f64 / scale
+                                            ..=caps.max_value as f64 / scale;
+                                        if ui
+                                            .add(
+                                                egui::Slider::new(&mut exposure, range)
+                                                    .logarithmic(true)
+                                                    .suffix(self.exposure_unit.suffix()),
+                                            )
+                                            .changed()
+                                        {
+                                            let exposure = (exposure * scale).round() as i32;
+                                            self.camera.status().exposure = exposure;
+                                            self.camera.send(CameraCommand::Control(
+                                                ASIControlType::Exposure,
+                                                exposure,
+                                            ));
+                                        }
                                     },
                                 );
                             });
@@ -175,13 +607,65 @@ impl eframe::App for App {
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Gain");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_connected, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                let mut scalar = 0;
-                                ui.add(egui::Slider::new(&mut scalar, 0..=500));
+                                let Some(caps) = status.control(ASIControlType::Gain) else {
+                                    return;
+                                };
+                                let mut gain = status.gain;
+                                if ui
+                                    .add(egui::Slider::new(
+                                        &mut gain,
+                                        caps.min_value..=caps.max_value,
+                                    ))
+                                    .changed()
+                                {
+                                    self.camera.status().gain = gain;
+                                    self.camera
+                                        .send(CameraCommand::Control(ASIControlType::Gain, gain));
+                                }
                             });
                         });
                     });
+
+                    ui.add_space(5.);
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.add_enabled_ui(is_connected, |ui| {
+                            let running = self.auto_exposure.state == AutoExposureState::Running;
+                            if ui
+                                .add_enabled(!running, egui::Button::new("Auto-expose for spectrum"))
+                                .on_hover_text("Adjust exposure and gain until the spectrum peak reaches the target")
+                                .clicked()
+                            {
+                                self.auto_exposure.start();
+                            }
+                            ui.add(
+                                egui::DragValue::new(&mut self.auto_exposure.target)
+                                    .clamp_range(0.1..=0.95)
+                                    .speed(0.01)
+                                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.))
+                                    .prefix("target "),
+                            );
+                        });
+                    });
+                    match &self.auto_exposure.state {
+                        AutoExposureState::Idle => {}
+                        AutoExposureState::Running => {
+                            ui.label("Adjusting exposure...");
+                        }
+                        AutoExposureState::Done => {
+                            ui.label("Exposure set.");
+                        }
+                        AutoExposureState::Failed(error) => {
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        }
+                    }
+
+                    ui.separator();
+                    ui.heading("Histogram");
+                    ui.add_space(5.);
+                    histogram_ui(ui, self.image_view.histogram.as_ref());
                 });
 
                 ui.add_space(5.);
@@ -193,53 +677,93 @@ impl eframe::App for App {
                 )
                 .default_open(true)
                 .show(ui, |ui| {
+                    let status = self.solex.status().clone();
+                    let is_connected =
+                        matches!(status.connection_status, ConnectionStatus::Connected);
+                    let is_idle = is_connected && !status.is_busy();
+
                     ui.heading("Sol'EX");
                     ui.add_space(5.);
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Sol'EX");
 
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                            egui::ComboBox::from_id_source("combo_com").show_ui(ui, |ui| {
-                                ui.style_mut().wrap = Some(false);
-                                ui.set_min_width(60.0);
+                            let is_selectable =
+                                matches!(status.connection_status, ConnectionStatus::Unconnected);
+
+                            ui.add_enabled_ui(is_selectable, |ui| {
+                                if ui.button("🔄").clicked() {
+                                    self.solex_ports = solex_api::available_ports();
+                                }
+
+                                egui::ComboBox::from_id_source("combo_com")
+                                    .selected_text(self.solex_port.clone().unwrap_or_default())
+                                    .show_ui(ui, |ui| {
+                                        for port in self.solex_ports.iter() {
+                                            ui.selectable_value(
+                                                &mut self.solex_port,
+                                                Some(port.clone()),
+                                                port,
+                                            );
+                                        }
+                                        ui.style_mut().wrap = Some(false);
+                                        ui.set_min_width(60.0);
+                                    });
                             });
 
-                            let connected = match self.solex_status.connection_status {
+                            let connected = match status.connection_status {
                                 ConnectionStatus::Connected => "Disconnect ⏹",
                                 ConnectionStatus::Connecting => "Connecting",
                                 ConnectionStatus::Disconnecting => "Disconnecting",
                                 ConnectionStatus::Unconnected => "Connect ▶",
                             };
 
-                            let is_enabled = matches!(
-                                self.solex_status.connection_status,
-                                ConnectionStatus::Connected | ConnectionStatus::Unconnected
-                            );
+                            let is_enabled = match status.connection_status {
+                                ConnectionStatus::Connected => true,
+                                ConnectionStatus::Unconnected => self.solex_port.is_some(),
+                                _ => false,
+                            };
 
                             ui.add_enabled_ui(is_enabled, |ui| {
                                 if ui.button(connected).clicked() {
-                                    self.fake_bool = !self.fake_bool;
+                                    match status.connection_status {
+                                        ConnectionStatus::Connected => self.solex.disconnect(),
+                                        _ => {
+                                            if let Some(port) = self.solex_port.clone() {
+                                                self.solex.connect(port, ui.ctx().clone());
+                                                self.solex
+                                                    .send(SolEXCommand::Speed(self.rotation_speed));
+                                            }
+                                        }
+                                    }
                                 }
                             });
 
-                            if !is_enabled {
+                            if matches!(
+                                status.connection_status,
+                                ConnectionStatus::Connecting | ConnectionStatus::Disconnecting
+                            ) {
                                 ui.add(egui::Spinner::new());
                             }
                         })
                     });
 
+                    if let Some(error) = &status.error {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+
                     ui.separator();
                     ui.heading("Ne-Ar Lamp 💡");
                     ui.add_space(5.);
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("ON/OFF");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_idle, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                let connected = if !self.fake_bool { "ON" } else { "OFF" };
+                                let connected = if !status.lamp_on { "ON" } else { "OFF" };
 
                                 if ui.button(connected).clicked() {
-                                    self.fake_bool = !self.fake_bool;
+                                    self.solex.send(SolEXCommand::Lamp(!status.lamp_on));
                                 }
                             })
                         })
@@ -249,11 +773,47 @@ impl eframe::App for App {
                     ui.heading("Wavelength Selector ⚙");
                     ui.add_space(5.);
 
+                    let motor = &status.motor;
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Homing");
+                        ui.add_enabled_ui(is_idle, |ui| {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                                match motor.calibration.home_reference {
+                                    HomeReference::LimitSwitch => {
+                                        if ui.button("Home").clicked() {
+                                            self.solex.send(SolEXCommand::Home);
+                                        }
+                                    }
+                                    HomeReference::SpectralLine(wavelength) => {
+                                        let angle = self.grating.angle(wavelength);
+                                        ui.add_enabled_ui(angle.is_some(), |ui| {
+                                            if ui
+                                                .button(format!("Set {:.2}nm", wavelength))
+                                                .on_hover_text(
+                                                    "Centre the line on the sensor first",
+                                                )
+                                                .clicked()
+                                            {
+                                                if let Some(angle) = angle {
+                                                    self.solex.status().motor.set_reference(angle);
+                                                }
+                                            }
+                                        });
+                                    }
+                                }
+                                ui.label(if motor.homed { "✔" } else { "Not homed" });
+                            })
+                        })
+                    });
+
+                    ui.add_space(5.);
+
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Sensor angle");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(motor.homed, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                ui.label("30°");
+                                ui.label(format!("{:.2}°", motor.angle()));
                             })
                         })
                     });
@@ -262,9 +822,12 @@ impl eframe::App for App {
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Calculated wavelength");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(motor.homed, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                ui.label("589nm");
+                                ui.label(format!(
+                                    "{:.2}nm",
+                                    self.grating.wavelength(motor.angle())
+                                ));
                             })
                         })
                     });
@@ -273,10 +836,14 @@ impl eframe::App for App {
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Rotation speed");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_idle, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                let mut scalar = 0;
-                                ui.add(egui::Slider::new(&mut scalar, 1..=8));
+                                if ui
+                                    .add(egui::Slider::new(&mut self.rotation_speed, 1..=8))
+                                    .drag_released()
+                                {
+                                    self.solex.send(SolEXCommand::Speed(self.rotation_speed));
+                                }
                             })
                         })
                     });
@@ -285,10 +852,30 @@ impl eframe::App for App {
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Rotate");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_idle, |ui| {
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                if ui.button("⏵").clicked() {}
-                                if ui.button("⏴").clicked() {}
+                                let forward = motor.plan_jog(self.jog_steps);
+                                let backward = motor.plan_jog(-self.jog_steps);
+
+                                ui.add_enabled_ui(forward.is_ok(), |ui| {
+                                    if ui.button("⏵").clicked() {
+                                        if let Ok(moves) = forward {
+                                            self.solex.send(SolEXCommand::Move(moves));
+                                        }
+                                    }
+                                });
+                                ui.add_enabled_ui(backward.is_ok(), |ui| {
+                                    if ui.button("⏴").clicked() {
+                                        if let Ok(moves) = backward {
+                                            self.solex.send(SolEXCommand::Move(moves));
+                                        }
+                                    }
+                                });
+                                ui.add(
+                                    egui::DragValue::new(&mut self.jog_steps)
+                                        .clamp_range(1..=10_000)
+                                        .suffix(" steps"),
+                                );
                             })
                         })
                     });
@@ -297,14 +884,25 @@ impl eframe::App for App {
 
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Rotate to");
-                        ui.add_enabled_ui(self.fake_bool, |ui| {
+                        ui.add_enabled_ui(is_idle && motor.homed, |ui| {
                             ui.vertical(|ui| {
+                                let calibration = &motor.calibration;
+                                let range = self.grating.wavelength(calibration.min_angle)
+                                    ..=self.grating.wavelength(calibration.max_angle);
+                                let moves = self
+                                    .grating
+                                    .angle(self.target_wavelength)
+                                    .ok_or_else(|| "Wavelength is out of reach.".to_string())
+                                    .and_then(|angle| {
+                                        motor.plan_move_to(angle).map_err(|e| e.to_string())
+                                    });
+
                                 ui.with_layout(
                                     egui::Layout::right_to_left(egui::Align::TOP),
                                     |ui| {
-                                        let mut scalar = 0;
                                         ui.add(
-                                            egui::Slider::new(&mut scalar, 0..=360).suffix("nm"),
+                                            egui::Slider::new(&mut self.target_wavelength, range)
+                                                .suffix("nm"),
                                         );
                                     },
                                 );
@@ -312,51 +910,402 @@ impl eframe::App for App {
                                 ui.with_layout(
                                     egui::Layout::right_to_left(egui::Align::TOP),
                                     |ui| {
-                                        if ui.button("Rotate").clicked() {}
+                                        let button = ui.add_enabled(
+                                            moves.is_ok(),
+                                            egui::Button::new("Rotate"),
+                                        );
+                                        match moves {
+                                            Ok(moves) => {
+                                                if button.clicked() {
+                                                    self.solex.send(SolEXCommand::Move(moves));
+                                                }
+                                            }
+                                            Err(e) => {
+                                                button.on_disabled_hover_text(e);
+                                            }
+                                        }
                                     },
                                 );
                             });
                         });
                     });
+
+                    ui.add_space(5.);
+
+                    egui::CollapsingHeader::new("Motor calibration")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let mut status = self.solex.status();
+                            let calibration = &mut status.motor.calibration;
+
+                            egui::Grid::new("grid_motor").num_columns(2).show(ui, |ui| {
+                                ui.label("Home reference");
+                                ui.horizontal(|ui| {
+                                    let is_switch = matches!(
+                                        calibration.home_reference,
+                                        HomeReference::LimitSwitch
+                                    );
+                                    if ui.radio(is_switch, "Limit switch").clicked() {
+                                        calibration.home_reference = HomeReference::LimitSwitch;
+                                    }
+                                    if ui.radio(!is_switch, "Spectral line").clicked() && is_switch
+                                    {
+                                        calibration.home_reference =
+                                            HomeReference::SpectralLine(589.);
+                                    }
+                                    if let HomeReference::SpectralLine(wavelength) =
+                                        &mut calibration.home_reference
+                                    {
+                                        ui.add(
+                                            egui::DragValue::new(wavelength)
+                                                .speed(0.01)
+                                                .suffix("nm"),
+                                        );
+                                    }
+                                });
+                                ui.end_row();
+
+                                ui.label("Home angle");
+                                ui.add(
+                                    egui::DragValue::new(&mut calibration.home_angle)
+                                        .speed(0.01)
+                                        .suffix("°"),
+                                );
+                                ui.end_row();
+
+                                ui.label("Steps per degree");
+                                ui.add(
+                                    egui::DragValue::new(&mut calibration.steps_per_degree)
+                                        .clamp_range(1.0..=100_000.0),
+                                );
+                                ui.end_row();
+
+                                ui.label("Backlash");
+                                ui.add(
+                                    egui::DragValue::new(&mut calibration.backlash_steps)
+                                        .clamp_range(0..=10_000)
+                                        .suffix(" steps"),
+                                );
+                                ui.end_row();
+
+                                ui.label("Final approach");
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(
+                                        &mut calibration.approach_direction,
+                                        ApproachDirection::Forward,
+                                        "Forward ⏵",
+                                    );
+                                    ui.radio_value(
+                                        &mut calibration.approach_direction,
+                                        ApproachDirection::Backward,
+                                        "Backward ⏴",
+                                    );
+                                });
+                                ui.end_row();
+
+                                ui.label("Soft limits");
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut calibration.min_angle)
+                                            .clamp_range(-90.0..=calibration.max_angle)
+                                            .speed(0.1)
+                                            .suffix("°"),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut calibration.max_angle)
+                                            .clamp_range(calibration.min_angle..=90.0)
+                                            .speed(0.1)
+                                            .suffix("°"),
+                                    );
+                                });
+                                ui.end_row();
+
+                                ui.label("Grating");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.grating.lines_per_mm)
+                                        .clamp_range(1.0..=10_000.0)
+                                        .suffix(" l/mm"),
+                                );
+                                ui.end_row();
+
+                                ui.label("Order");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.grating.order)
+                                        .clamp_range(1..=10),
+                                );
+                                ui.end_row();
+
+                                ui.label("Deviation angle");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.grating.deviation_angle)
+                                        .clamp_range(0.0..=180.0)
+                                        .speed(0.01)
+                                        .suffix("°"),
+                                );
+                                ui.end_row();
+                            });
+                        });
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                let is_capturing =
+                    self.replay.is_active() || self.camera.status().frame.is_some();
+                let dispersion = self.dispersion();
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("Replay").font(egui::FontId::proportional(20.0)),
+                )
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.add_space(5.);
+                    self.replay.ui(ui);
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("Dark and Flat").font(egui::FontId::proportional(20.0)),
+                )
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.add_space(5.);
+                    self.dark_flat.ui(ui);
                 });
 
                 ui.add_space(5.);
                 ui.separator();
                 ui.add_space(5.);
 
-                ui.add_enabled_ui(self.fake_bool, |ui| {
+                ui.add_enabled_ui(is_capturing, |ui| {
                     egui::CollapsingHeader::new(
-                        egui::RichText::new("Calibration").font(egui::FontId::proportional(20.0)),
+                        egui::RichText::new("Focus Assistant")
+                            .font(egui::FontId::proportional(20.0)),
                     )
                     .default_open(false)
                     .show(ui, |ui| {
                         ui.add_space(5.);
-                        ui.horizontal_wrapped(|ui| {
-                            ui.label("Reference spectrum");
+                        self.focus
+                            .ui(ui, self.spectrum_plot.live.as_ref(), dispersion.as_ref());
+                    })
+                });
 
-                            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                egui::ComboBox::from_id_source("combo_spe").show_ui(ui, |ui| {
-                                    ui.style_mut().wrap = Some(false);
-                                    ui.set_min_width(60.0);
-                                });
-                            })
-                        });
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Tilt").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
                         ui.add_space(5.);
+                        self.tilt.ui(ui, self.spectrum_plot.live.as_ref());
+                    })
+                });
 
-                        ui.horizontal_wrapped(|ui| {
-                            ui.label("Start");
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
 
-                            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                                if ui.button("Start").clicked() {}
-                            })
-                        });
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Smile").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        self.smile.ui(ui, self.spectrum_plot.live.as_ref());
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Resolution").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        let configuration = self.configuration();
+                        self.resolution.ui(
+                            ui,
+                            dispersion.as_ref(),
+                            &configuration,
+                        );
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Line Analysis")
+                            .font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        self.line_analysis.ui(
+                            ui,
+                            self.spectrum_plot.selected,
+                            dispersion.as_ref(),
+                        );
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Doppler").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        self.doppler
+                            .ui(ui, self.spectrum_plot.selected, dispersion.as_ref());
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Continuum").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        let atlas = self.calibration.atlas();
+                        // instrument FWHM in Å
+                        let resolution = self.calibration.resolution
+                            * dispersion.as_ref().map_or(0., |dispersion| {
+                                dispersion.angstrom_per_pixel(0.).abs()
+                            });
+                        self.continuum.ui(
+                            ui,
+                            self.spectrum_plot.live.as_ref(),
+                            self.spectrum_plot.selected,
+                            dispersion.as_ref(),
+                            atlas.as_deref(),
+                            resolution,
+                        );
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Line Identification")
+                            .font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.add_space(5.);
+                        self.identification.ui(ui, dispersion.as_ref());
+                    })
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                // the metadata also labels the exported spectroheliograms, editable without
+                // a camera
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("Recording").font(egui::FontId::proportional(20.0)),
+                )
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.add_space(5.);
+                    self.recorder.ui(ui, &self.camera);
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                // recorded scans can be processed without a camera
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("Spectroheliogram").font(egui::FontId::proportional(20.0)),
+                )
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.add_space(5.);
+                    let orientation = self.spectrum_plot.orientation;
+                    let tilt = self
+                        .tilt
+                        .tilt
+                        .as_ref()
+                        .filter(|tilt| self.tilt.derotate && tilt.orientation == orientation);
+                    let straightened = self.smile.straighten
+                        && self
+                            .smile
+                            .smile
+                            .as_ref()
+                            .is_some_and(|smile| smile.orientation == orientation);
+                    self.spectroheliogram.ui(
+                        ui,
+                        orientation,
+                        self.spectrum_plot.selected,
+                        self.smile.smile.as_ref(),
+                        straightened,
+                        tilt,
+                    );
+                });
+
+                ui.add_space(5.);
+                ui.separator();
+                ui.add_space(5.);
+
+                ui.add_enabled_ui(is_capturing, |ui| {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new("Calibration").font(egui::FontId::proportional(20.0)),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        let predicted = self.predicted_dispersion();
+                        let is_valid = self.is_calibration_valid();
+                        self.calibration
+                            .ui(ui, &self.solex, predicted.as_ref(), is_valid);
                     })
                 });
 
                 ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
             });
 
-        egui::CentralPanel::default().show(ctx, |_ui| {});
+        self.spectroheliogram.window(ctx);
+        self.recorder.poll();
+
+        let dispersion = self.dispersion();
+        self.spectrum_plot.divisor = self.spectrum_plot.live.as_ref().and_then(|profile| {
+            self.continuum
+                .divisor(dispersion.as_ref(), profile.values.len())
+        });
+        self.spectrum_plot.identifications = self.identification.labels();
+        let acquisition = self.acquisition(dispersion.as_ref());
+        self.recorder.acquisition = acquisition.clone();
+        self.spectrum_plot.acquisition = acquisition.clone();
+        self.spectroheliogram.acquisition = acquisition;
+        self.spectroheliogram.dispersion = dispersion;
+        self.spectroheliogram.masters = self.dark_flat.masters();
 
         egui::TopBottomPanel::bottom("bottom")
             .resizable(true)
@@ -367,21 +1316,18 @@ impl eframe::App for App {
                 ui.add_space(5.);
                 ui.heading("Plot");
 
-                let line_points: PlotPoints = (0..=100)
-                    .map(|i| {
-                        use std::f64::consts::TAU;
-                        let x = egui::remap(i as f64, 0.0..=100_f64, -TAU..=TAU);
-                        [x, (x.sin().cos() * 10. * x.powi(2).tan()).sqrt()]
-                    })
-                    .collect();
-                let line = Line::new(line_points);
-                egui_plot::Plot::new("example_plot")
-                    .height(290.)
-                    .show_axes(false)
-                    .data_aspect(1.0)
-                    .show(ui, |plot_ui| plot_ui.line(line));
+                let dispersion = self.dispersion();
+                self.spectrum_plot.ui(ui, dispersion.as_ref());
 
                 ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
             });
+
+        // the central panel takes what the other panels leave, so it comes last
+        self.image_view.overlays = self.tilt.overlays();
+        self.image_view.overlays.extend(self.smile.overlays());
+        self.image_view
+            .overlays
+            .extend(self.identification.overlays());
+        egui::CentralPanel::default().show(ctx, |ui| self.image_view.ui(ui));
     }
 }
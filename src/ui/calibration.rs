@@ -0,0 +1,536 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use eframe::egui;
+
+use crate::{
+    frame::Frame,
+    solex::driver::{SolEXCommand, SolEXDriver},
+    spectrum::{
+        atlas::{self, Atlas},
+        calibration::{self, WavelengthCalibration},
+        dispersion::Dispersion,
+        lines::{self, SpectralLine},
+        profile::{Profile, SlitOrientation},
+    },
+};
+
+use super::app::ConnectionStatus;
+
+/// Frames skipped after the lamp is switched on, for the exposure in progress to end.
+const WARMUP_FRAMES: u32 = 3;
+/// Detection threshold of the lamp lines in noise sigmas.
+const PEAK_SIGMA: f64 = 8.;
+/// Detection threshold of the Fraunhofer lines in noise sigmas.
+const ABSORPTION_SIGMA: f64 = 3.;
+/// Brightest peaks used for the line identification.
+const MAX_PEAKS: usize = 30;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReferenceSpectrum {
+    NeonArgon,
+    /// Solar spectrum matched against the Fraunhofer line positions.
+    LineList,
+    /// Index in the loaded atlases.
+    Atlas(usize),
+}
+
+enum CalibrationState {
+    Idle,
+    Warmup { frames: u32 },
+    Measuring { sum: Vec<f64>, frames: u32 },
+    Solving(Receiver<Result<WavelengthCalibration, String>>),
+    Done,
+    Failed(String),
+}
+
+/// Everything the solver thread needs.
+struct SolveRequest {
+    profile: Profile,
+    predicted: Dispersion,
+    atlas: Option<Arc<Atlas>>,
+    degree: usize,
+    /// Instrument FWHM in pixels.
+    resolution: f64,
+    motor_position: i32,
+    bin: i32,
+}
+
+/// Measures the wavelength solution from a reference spectrum.
+pub struct CalibrationPanel {
+    pub source: ReferenceSpectrum,
+    pub degree: usize,
+    /// Number of frames averaged.
+    pub frames: u32,
+    /// Instrument FWHM in pixels the atlas is convolved to.
+    pub resolution: f64,
+    line_list: Arc<Atlas>,
+    atlases: Vec<Arc<Atlas>>,
+    load_error: Option<String>,
+    state: CalibrationState,
+    lamp_was_on: bool,
+    ctx: egui::Context,
+    pub solution: Option<WavelengthCalibration>,
+}
+
+impl CalibrationPanel {
+    pub fn new(
+        ctx: egui::Context,
+        solution: Option<WavelengthCalibration>,
+        atlas_paths: &[PathBuf],
+    ) -> Self {
+        let mut atlases = vec![];
+        for path in atlas_paths {
+            match Atlas::load(path) {
+                Ok(atlas) => atlases.push(Arc::new(atlas)),
+                Err(e) => log::warn!("{}: {}", path.display(), e),
+            }
+        }
+
+        Self {
+            source: ReferenceSpectrum::NeonArgon,
+            degree: 2,
+            frames: 5,
+            resolution: 2.5,
+            line_list: Arc::new(Atlas::line_list_model()),
+            atlases,
+            load_error: None,
+            state: CalibrationState::Idle,
+            lamp_was_on: false,
+            ctx,
+            solution,
+        }
+    }
+
+    /// Atlas selected as reference, else the first one loaded.
+    pub fn atlas(&self) -> Option<Arc<Atlas>> {
+        match self.source {
+            ReferenceSpectrum::Atlas(i) => Some(self.atlases[i].clone()),
+            _ => self.atlases.first().cloned(),
+        }
+    }
+
+    /// Files of the atlases loaded by the user.
+    pub fn atlas_paths(&self) -> Vec<PathBuf> {
+        self.atlases
+            .iter()
+            .filter_map(|atlas| atlas.path.clone())
+            .collect()
+    }
+
+    fn source_name(&self, source: ReferenceSpectrum) -> String {
+        match source {
+            ReferenceSpectrum::NeonArgon => "Ne-Ar lamp".to_string(),
+            ReferenceSpectrum::LineList => format!("Fallback: {}", self.line_list.name),
+            ReferenceSpectrum::Atlas(i) => format!("Solar atlas: {}", self.atlases[i].name),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(
+            self.state,
+            CalibrationState::Warmup { .. }
+                | CalibrationState::Measuring { .. }
+                | CalibrationState::Solving(_)
+        )
+    }
+
+    fn start(&mut self, solex: &SolEXDriver) {
+        self.state = match self.source {
+            ReferenceSpectrum::NeonArgon => {
+                self.lamp_was_on = solex.status().lamp_on;
+                if !self.lamp_was_on {
+                    solex.send(SolEXCommand::Lamp(true));
+                }
+                CalibrationState::Warmup {
+                    frames: WARMUP_FRAMES,
+                }
+            }
+            ReferenceSpectrum::LineList | ReferenceSpectrum::Atlas(_) => {
+                CalibrationState::Measuring {
+                    sum: vec![],
+                    frames: 0,
+                }
+            }
+        };
+    }
+
+    fn stop_lamp(&self, solex: &SolEXDriver) {
+        if self.source == ReferenceSpectrum::NeonArgon && !self.lamp_was_on {
+            solex.send(SolEXCommand::Lamp(false));
+        }
+    }
+
+    /// Feed a new frame, `predicted` is the dispersion the line identification starts from.
+    pub fn update(
+        &mut self,
+        frame: &Frame,
+        orientation: SlitOrientation,
+        solex: &SolEXDriver,
+        predicted: Option<&Dispersion>,
+        bin: i32,
+    ) {
+        match &mut self.state {
+            CalibrationState::Warmup { frames } => {
+                if !solex.status().lamp_on {
+                    return;
+                }
+                *frames = frames.saturating_sub(1);
+                if *frames == 0 {
+                    self.state = CalibrationState::Measuring {
+                        sum: vec![],
+                        frames: 0,
+                    };
+                }
+            }
+            CalibrationState::Measuring { sum, frames } => {
+                // the centre half of the slit has the least smile
+                let (_, slit_len) = orientation.dimensions(frame);
+                let profile = Profile::extract(frame, orientation, slit_len / 4..=slit_len * 3 / 4);
+                if sum.len() != profile.values.len() {
+                    *sum = vec![0.; profile.values.len()];
+                    *frames = 0;
+                }
+                sum.iter_mut()
+                    .zip(&profile.values)
+                    .for_each(|(sum, value)| *sum += value);
+                *frames += 1;
+                if *frames < self.frames {
+                    return;
+                }
+
+                let profile = Profile {
+                    values: sum.iter().map(|value| value / *frames as f64).collect(),
+                };
+                self.stop_lamp(solex);
+                let Some(predicted) = predicted else {
+                    self.state = CalibrationState::Failed(
+                        "The grating angle is unknown, home the motor first.".to_string(),
+                    );
+                    return;
+                };
+
+                let request = SolveRequest {
+                    profile,
+                    predicted: predicted.clone(),
+                    atlas: match self.source {
+                        ReferenceSpectrum::NeonArgon => None,
+                        ReferenceSpectrum::LineList => Some(self.line_list.clone()),
+                        ReferenceSpectrum::Atlas(i) => Some(self.atlases[i].clone()),
+                    },
+                    degree: self.degree,
+                    resolution: self.resolution,
+                    motor_position: solex.status().motor.position,
+                    bin,
+                };
+                let reference = self.source_name(self.source);
+                let (sender, receiver) = mpsc::channel();
+                let ctx = self.ctx.clone();
+                thread::spawn(move || {
+                    let result = solve(request).map(|solution| WavelengthCalibration {
+                        reference,
+                        ..solution
+                    });
+                    let _ = sender.send(result);
+                    ctx.request_repaint();
+                });
+                self.state = CalibrationState::Solving(receiver);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        solex: &SolEXDriver,
+        predicted: Option<&Dispersion>,
+        is_valid: bool,
+    ) {
+        if let CalibrationState::Solving(receiver) = &self.state {
+            match receiver.try_recv() {
+                Ok(Ok(solution)) => {
+                    self.solution = Some(solution);
+                    self.state = CalibrationState::Done;
+                }
+                Ok(Err(e)) => self.state = CalibrationState::Failed(e),
+                Err(TryRecvError::Disconnected) => {
+                    self.state = CalibrationState::Failed("The solver stopped.".to_string())
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        ui.add_space(5.);
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Reference spectrum");
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                let mut source = self.source;
+                egui::ComboBox::from_id_source("combo_spe")
+                    .selected_text(self.source_name(self.source))
+                    .show_ui(ui, |ui| {
+                        for option in [ReferenceSpectrum::NeonArgon, ReferenceSpectrum::LineList]
+                            .into_iter()
+                            .chain((0..self.atlases.len()).map(ReferenceSpectrum::Atlas))
+                        {
+                            ui.selectable_value(&mut source, option, self.source_name(option));
+                        }
+                        ui.style_mut().wrap = Some(false);
+                        ui.set_min_width(60.0);
+                    });
+                if !self.is_running() {
+                    self.source = source;
+                }
+            })
+        });
+        ui.horizontal_wrapped(|ui| {
+            let running = self.is_running();
+            if ui
+                .add_enabled(!running, egui::Button::new("Load atlas..."))
+                .on_hover_text("Two columns of wavelength in Å or nm and flux, or a FITS spectrum")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Atlas", &["txt", "dat", "csv", "fits", "fit", "fts"])
+                    .pick_file()
+                {
+                    match Atlas::load(&path) {
+                        Ok(atlas) => {
+                            self.atlases
+                                .retain(|other| other.path.as_ref() != Some(&path));
+                            self.atlases.push(Arc::new(atlas));
+                            self.source = ReferenceSpectrum::Atlas(self.atlases.len() - 1);
+                            self.load_error = None;
+                        }
+                        Err(e) => self.load_error = Some(e.to_string()),
+                    }
+                }
+            }
+            if let ReferenceSpectrum::Atlas(i) = self.source {
+                if ui
+                    .add_enabled(!running, egui::Button::new("Remove"))
+                    .clicked()
+                {
+                    self.atlases.remove(i);
+                    self.source = ReferenceSpectrum::LineList;
+                }
+            }
+        });
+        if self.source == ReferenceSpectrum::LineList {
+            ui.weak(
+                "No solar atlas is bundled, the line list model only has the positions of the \
+                 strong lines. Load an atlas for a real solar spectrum.",
+            );
+        }
+        if let Some(error) = &self.load_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        ui.add_space(5.);
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Polynomial degree");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                ui.add(egui::DragValue::new(&mut self.degree).clamp_range(1..=4));
+            })
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Averaged frames");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                ui.add(egui::DragValue::new(&mut self.frames).clamp_range(1..=100));
+            })
+        });
+        if let ReferenceSpectrum::Atlas(_) = self.source {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Instrument FWHM");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.resolution)
+                            .clamp_range(0.5..=20.0)
+                            .speed(0.1)
+                            .suffix(" px"),
+                    );
+                })
+            });
+        }
+        ui.add_space(5.);
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Start");
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                let is_connected = matches!(
+                    solex.status().connection_status,
+                    ConnectionStatus::Connected
+                );
+                let error = if !is_connected && self.source == ReferenceSpectrum::NeonArgon {
+                    Some("Connect the Sol'EX to switch the lamp on.")
+                } else if predicted.is_none() {
+                    Some("Home the motor to predict the wavelength range.")
+                } else {
+                    None
+                };
+                if self.is_running() {
+                    if ui.button("Cancel").clicked() {
+                        if !matches!(self.state, CalibrationState::Solving(_)) {
+                            self.stop_lamp(solex);
+                        }
+                        self.state = CalibrationState::Idle;
+                    }
+                } else if ui
+                    .add_enabled(error.is_none(), egui::Button::new("Start"))
+                    .on_disabled_hover_text(error.unwrap_or_default())
+                    .clicked()
+                {
+                    self.start(solex);
+                }
+            })
+        });
+
+        match &self.state {
+            CalibrationState::Idle => {}
+            CalibrationState::Warmup { .. } => {
+                ui.label("Switching the lamp on...");
+            }
+            CalibrationState::Measuring { frames, .. } => {
+                ui.label(format!("Measuring frame {}/{}...", frames + 1, self.frames));
+            }
+            CalibrationState::Solving(_) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Identifying lines...");
+                });
+            }
+            CalibrationState::Done => {
+                ui.label("Calibrated.");
+            }
+            CalibrationState::Failed(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        }
+
+        let Some(solution) = &self.solution else {
+            return;
+        };
+        ui.separator();
+        if !is_valid {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "⚠ The grating or binning changed since the calibration, using the predicted dispersion.",
+            );
+        }
+        if !solution.reference.is_empty() {
+            ui.label(&solution.reference);
+        }
+        let degree = solution.dispersion.coefficients.len().saturating_sub(1);
+        ui.label(format!(
+            "{} lines, degree {}, RMS {:.3} Å, {:.4} Å/px",
+            solution.matches.len(),
+            degree,
+            solution.rms,
+            solution
+                .dispersion
+                .angstrom_per_pixel(solution.matches.first().map_or(0., |m| m.pixel))
+        ));
+        if degree < solution.requested_degree {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "Too few lines were identified for degree {}, fitted with degree {}.",
+                    solution.requested_degree, degree
+                ),
+            );
+        }
+        if let Some(correlation) = solution.correlation {
+            ui.label(format!("Atlas correlation {:.3}", correlation));
+        }
+        egui::Grid::new("grid_calibration")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Line");
+                ui.strong("Pixel");
+                ui.strong("λ [Å]");
+                ui.strong("Residual [Å]");
+                ui.end_row();
+                for line in &solution.matches {
+                    ui.label(&line.name);
+                    ui.label(format!("{:.2}", line.pixel));
+                    ui.label(format!("{:.3}", line.wavelength));
+                    ui.label(format!("{:+.3}", line.residual));
+                    ui.end_row();
+                }
+            });
+        if ui.button("Clear calibration").clicked() {
+            self.solution = None;
+            self.state = CalibrationState::Idle;
+        }
+    }
+}
+
+fn solve(request: SolveRequest) -> Result<WavelengthCalibration, String> {
+    let SolveRequest {
+        profile,
+        predicted,
+        atlas,
+        degree,
+        resolution,
+        motor_position,
+        bin,
+    } = request;
+
+    let (dispersion, matches, rms, correlation) = match atlas {
+        None => {
+            let mut peaks = calibration::find_peaks(&profile, PEAK_SIGMA);
+            // faint peaks are mostly noise or blends that are not in the line list
+            peaks.sort_by(|a, b| b.amplitude.total_cmp(&a.amplitude));
+            peaks.truncate(MAX_PEAKS);
+            let positions: Vec<f64> = peaks.iter().map(|peak| peak.position).collect();
+            let (dispersion, matches, rms) =
+                calibration::identify(&positions, &predicted, lines::NEON_ARGON, degree)
+                    .map_err(|e| e.to_string())?;
+            (dispersion, matches, rms, None)
+        }
+        Some(atlas) => {
+            let resolution = resolution * predicted.angstrom_per_pixel(0.).abs();
+            let (linear, correlation) =
+                atlas::cross_correlate(&profile, &atlas, &predicted, resolution)
+                    .ok_or("The profile is too short.")?;
+
+            let mut lines = calibration::find_absorption_lines(&profile, ABSORPTION_SIGMA);
+            lines.sort_by(|a, b| b.amplitude.total_cmp(&a.amplitude));
+            lines.truncate(MAX_PEAKS);
+            let positions: Vec<f64> = lines.iter().map(|line| line.position).collect();
+            let fraunhofer: Vec<SpectralLine> =
+                lines::FRAUNHOFER.iter().map(|line| line.line).collect();
+
+            // fall back to a lower degree when few lines are in the window
+            let mut result = Err(String::new());
+            for degree in (1..=degree).rev() {
+                result = calibration::refine(&positions, linear.clone(), &fraunhofer, degree)
+                    .map_err(|e| e.to_string());
+                if result.is_ok() {
+                    break;
+                }
+            }
+            let (dispersion, matches, rms) = result?;
+            (dispersion, matches, rms, Some(correlation))
+        }
+    };
+
+    Ok(WavelengthCalibration {
+        dispersion,
+        matches,
+        rms,
+        motor_position,
+        bin,
+        reference: String::new(),
+        correlation,
+        requested_degree: degree,
+    })
+}
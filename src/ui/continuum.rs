@@ -0,0 +1,304 @@
+use std::ops::RangeInclusive;
+
+use eframe::egui;
+use egui_plot::{Legend, Line, VLine};
+
+use crate::spectrum::{
+    atlas::Atlas,
+    continuum::{Continuum, ContinuumModel, InstrumentResponse},
+    dispersion::Dispersion,
+    profile::Profile,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Normalization {
+    None,
+    Continuum,
+    Response,
+}
+
+/// Fits the continuum of the live profile and derives the instrument response.
+pub struct ContinuumPanel {
+    pub response: Option<InstrumentResponse>,
+    model: ContinuumModel,
+    degree: usize,
+    segments: usize,
+    /// Clipping thresholds below and above the continuum in sigmas.
+    low: f64,
+    high: f64,
+    /// Continuum windows marked by the user, the whole profile when empty.
+    windows: Vec<RangeInclusive<usize>>,
+    window_half_width: usize,
+    normalization: Normalization,
+    continuum: Option<Continuum>,
+    error: Option<String>,
+}
+
+impl ContinuumPanel {
+    pub fn new(response: Option<InstrumentResponse>) -> Self {
+        Self {
+            response,
+            model: ContinuumModel::Polynomial { degree: 3 },
+            degree: 3,
+            segments: 8,
+            low: 1.5,
+            high: 3.,
+            windows: vec![],
+            window_half_width: 5,
+            normalization: Normalization::None,
+            continuum: None,
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, profile: &Profile) {
+        self.continuum = Continuum::fit(
+            &profile.values,
+            &self.windows,
+            self.model,
+            self.low,
+            self.high,
+        );
+    }
+
+    /// Values the spectrum plot divides the profiles by.
+    pub fn divisor(&self, dispersion: Option<&Dispersion>, len: usize) -> Option<Vec<f64>> {
+        match self.normalization {
+            Normalization::None => None,
+            Normalization::Continuum => self.continuum.as_ref().map(|c| c.values.clone()),
+            Normalization::Response => self.response.as_ref()?.divisor(dispersion?, len),
+        }
+    }
+
+    /// `selected` is the pixel clicked in the spectrum plot, `atlas` and `resolution` in Å
+    /// are used to derive the instrument response, which needs a loaded solar atlas.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        profile: Option<&Profile>,
+        selected: Option<f64>,
+        dispersion: Option<&Dispersion>,
+        atlas: Option<&Atlas>,
+        resolution: f64,
+    ) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Model");
+            let mut polynomial = matches!(self.model, ContinuumModel::Polynomial { .. });
+            ui.radio_value(&mut polynomial, true, "Polynomial");
+            ui.radio_value(&mut polynomial, false, "Spline");
+            if polynomial {
+                ui.add(
+                    egui::DragValue::new(&mut self.degree)
+                        .clamp_range(0..=10)
+                        .prefix("degree "),
+                );
+                self.model = ContinuumModel::Polynomial {
+                    degree: self.degree,
+                };
+            } else {
+                ui.add(
+                    egui::DragValue::new(&mut self.segments)
+                        .clamp_range(1..=50)
+                        .prefix("segments "),
+                );
+                self.model = ContinuumModel::Spline {
+                    segments: self.segments,
+                };
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Clip");
+            ui.add(
+                egui::DragValue::new(&mut self.low)
+                    .clamp_range(0.5..=10.)
+                    .speed(0.05)
+                    .prefix("below ")
+                    .suffix(" σ"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.high)
+                    .clamp_range(0.5..=10.)
+                    .speed(0.05)
+                    .prefix("above ")
+                    .suffix(" σ"),
+            );
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(selected.is_some(), |ui| {
+                if ui
+                    .button("Add window")
+                    .on_hover_text("Continuum window around the pixel clicked in the spectrum plot")
+                    .clicked()
+                {
+                    if let Some(selected) = selected {
+                        let centre = selected.round().max(0.) as usize;
+                        self.windows.push(
+                            centre.saturating_sub(self.window_half_width)
+                                ..=centre + self.window_half_width,
+                        );
+                    }
+                }
+            });
+            ui.add(
+                egui::DragValue::new(&mut self.window_half_width)
+                    .clamp_range(1..=500)
+                    .prefix("±")
+                    .suffix(" px"),
+            );
+            ui.add_enabled_ui(!self.windows.is_empty(), |ui| {
+                if ui.button("Clear windows").clicked() {
+                    self.windows.clear();
+                }
+            });
+        });
+        ui.label(if self.windows.is_empty() {
+            "Automatic continuum over the whole profile".to_string()
+        } else {
+            format!("{} continuum windows", self.windows.len())
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Spectrum plot");
+            ui.radio_value(&mut self.normalization, Normalization::None, "Raw");
+            ui.radio_value(
+                &mut self.normalization,
+                Normalization::Continuum,
+                "Normalized",
+            );
+            ui.add_enabled_ui(self.response.is_some() && dispersion.is_some(), |ui| {
+                ui.radio_value(
+                    &mut self.normalization,
+                    Normalization::Response,
+                    "Response corrected",
+                );
+            });
+        });
+
+        if let (Some(profile), Some(continuum)) = (profile, &self.continuum) {
+            let kept: Vec<[f64; 2]> = profile
+                .values
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| continuum.used[*i])
+                .map(|(i, value)| [i as f64, *value])
+                .collect();
+            egui_plot::Plot::new("continuum_plot")
+                .height(150.)
+                .legend(Legend::default())
+                .x_axis_label("Pixel")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        Line::new(
+                            profile
+                                .values
+                                .iter()
+                                .enumerate()
+                                .map(|(i, v)| [i as f64, *v])
+                                .collect::<Vec<_>>(),
+                        )
+                        .name("Profile"),
+                    );
+                    plot_ui.points(
+                        egui_plot::Points::new(kept)
+                            .radius(1.)
+                            .name("Continuum pixels"),
+                    );
+                    plot_ui.line(
+                        Line::new(
+                            continuum
+                                .values
+                                .iter()
+                                .enumerate()
+                                .map(|(i, v)| [i as f64, *v])
+                                .collect::<Vec<_>>(),
+                        )
+                        .name("Continuum"),
+                    );
+                    for window in &self.windows {
+                        plot_ui.vline(VLine::new(*window.start() as f64).name("Windows"));
+                        plot_ui.vline(VLine::new(*window.end() as f64).name("Windows"));
+                    }
+                });
+        }
+
+        ui.add_space(5.);
+        ui.strong("Instrument response");
+        let missing = if atlas.is_none() {
+            "No solar atlas is bundled yet, load one in the Calibration section"
+        } else if dispersion.is_none() {
+            "Needs a wavelength calibration"
+        } else {
+            "Needs a live profile"
+        };
+        let enabled = profile.is_some() && dispersion.is_some() && atlas.is_some();
+        ui.add_enabled_ui(enabled, |ui| {
+            if ui
+                .button("Derive from disk centre")
+                .on_hover_text(format!(
+                    "Divide the live profile, taken at the disk centre, by the {} atlas",
+                    atlas.map_or("solar", |atlas| &atlas.name)
+                ))
+                .on_disabled_hover_text(missing)
+                .clicked()
+            {
+                if let (Some(profile), Some(dispersion), Some(atlas)) = (profile, dispersion, atlas)
+                {
+                    match InstrumentResponse::derive(
+                        profile, dispersion, atlas, resolution, self.model,
+                    ) {
+                        Some(response) => {
+                            self.response = Some(response);
+                            self.error = None;
+                        }
+                        None => self.error = Some("The response could not be fitted.".to_string()),
+                    }
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        if let Some(response) = &self.response {
+            let covered = dispersion.is_some_and(|dispersion| {
+                profile.is_some_and(|profile| {
+                    response.divisor(dispersion, profile.values.len()).is_some()
+                })
+            });
+            ui.label(format!(
+                "{:.1} to {:.1} Å from {}",
+                response.wavelengths.first().copied().unwrap_or_default(),
+                response.wavelengths.last().copied().unwrap_or_default(),
+                response.atlas
+            ));
+            if !covered {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "The response does not cover the current spectrum.",
+                );
+            }
+            egui_plot::Plot::new("response_plot")
+                .height(120.)
+                .x_axis_label("Wavelength [Å]")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        Line::new(
+                            response
+                                .wavelengths
+                                .iter()
+                                .zip(&response.response)
+                                .map(|(w, r)| [*w, *r])
+                                .collect::<Vec<_>>(),
+                        )
+                        .name("Response"),
+                    );
+                });
+            if ui.button("Clear response").clicked() {
+                self.response = None;
+                if self.normalization == Normalization::Response {
+                    self.normalization = Normalization::None;
+                }
+            }
+        }
+    }
+}
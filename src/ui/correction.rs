@@ -0,0 +1,134 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+
+use crate::{
+    frame::{Frame, FrameSource},
+    imaging::dark_flat::{DarkFlatError, Masters},
+    spectrum::{smile::Smile, tilt::Tilt},
+};
+
+/// Calibration and resampling applied to a live frame, `None` when disabled.
+#[derive(Clone, Default)]
+pub struct Corrections {
+    pub masters: Masters,
+    pub tilt: Option<Tilt>,
+    pub smile: Option<Smile>,
+}
+
+struct CorrectionRequest {
+    frame: Arc<Frame>,
+    time: Option<DateTime<Utc>>,
+    corrections: Corrections,
+}
+
+#[derive(Default)]
+struct Corrected {
+    frame: Option<Arc<Frame>>,
+    time: Option<DateTime<Utc>>,
+    /// After the dark and flat, before derotating.
+    calibrated: Option<Arc<Frame>>,
+    /// Why the masters were not applied to the frame.
+    dark_flat_error: Option<DarkFlatError>,
+    /// Before straightening.
+    derotated: Option<Arc<Frame>>,
+    frame_count: u64,
+}
+
+/// Resamples the live frames on a worker thread, dropping frames it can not keep up with.
+pub struct Corrector {
+    sender: Sender<CorrectionRequest>,
+    corrected: Arc<Mutex<Corrected>>,
+}
+
+impl Corrector {
+    pub fn new(ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let corrected = Arc::new(Mutex::new(Corrected::default()));
+        let output = corrected.clone();
+        thread::spawn(move || correction_loop(receiver, &output, &ctx));
+        Self { sender, corrected }
+    }
+
+    /// Queue a frame captured at `time`, the result is delivered through
+    /// [`FrameSource::latest`].
+    pub fn send(&self, frame: Arc<Frame>, time: Option<DateTime<Utc>>, corrections: Corrections) {
+        let _ = self.sender.send(CorrectionRequest {
+            frame,
+            time,
+            corrections,
+        });
+    }
+
+    /// Latest frame with the dark and flat applied, the tilt is measured on it.
+    pub fn calibrated(&self) -> Option<Arc<Frame>> {
+        self.corrected.lock().unwrap().calibrated.clone()
+    }
+
+    /// Why the dark and flat could not be applied to the latest frame.
+    pub fn dark_flat_error(&self) -> Option<DarkFlatError> {
+        self.corrected.lock().unwrap().dark_flat_error
+    }
+
+    /// Latest frame derotated but not straightened, the smile is measured on it.
+    pub fn derotated(&self) -> Option<Arc<Frame>> {
+        self.corrected.lock().unwrap().derotated.clone()
+    }
+}
+
+impl FrameSource for Corrector {
+    fn latest(&self) -> (Option<Arc<Frame>>, Option<DateTime<Utc>>, u64) {
+        let corrected = self.corrected.lock().unwrap();
+        (
+            corrected.frame.clone(),
+            corrected.time,
+            corrected.frame_count,
+        )
+    }
+}
+
+fn correction_loop(
+    receiver: Receiver<CorrectionRequest>,
+    output: &Mutex<Corrected>,
+    ctx: &egui::Context,
+) {
+    while let Ok(mut request) = receiver.recv() {
+        while let Ok(newer) = receiver.try_recv() {
+            request = newer;
+        }
+        let corrections = &request.corrections;
+        let (calibrated, dark_flat_error) = if corrections.masters.is_empty() {
+            (request.frame, None)
+        } else {
+            match corrections.masters.correct(&request.frame) {
+                Ok(frame) => (Arc::new(frame), None),
+                Err(e) => (request.frame, Some(e)),
+            }
+        };
+        // derotate before straightening, the smile is measured on derotated frames
+        let derotated = match &corrections.tilt {
+            Some(tilt) => Arc::new(tilt.correct(&calibrated)),
+            None => calibrated.clone(),
+        };
+        let frame = match &corrections.smile {
+            Some(smile) => Arc::new(smile.correct(&derotated)),
+            None => derotated.clone(),
+        };
+        let mut output = output.lock().unwrap();
+        output.frame = Some(frame);
+        output.time = request.time;
+        output.calibrated = Some(calibrated);
+        output.dark_flat_error = dark_flat_error;
+        output.derotated = Some(derotated);
+        output.frame_count += 1;
+        drop(output);
+        ctx.request_repaint();
+    }
+}
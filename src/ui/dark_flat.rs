@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::{
+    fits,
+    imaging::dark_flat::{DarkFlatError, MasterFrame, Masters},
+};
+
+/// Applies master darks and flats from other software to the frames before any
+/// correction.
+pub struct DarkFlatPanel {
+    masters: Masters,
+    apply: bool,
+    /// Why the last frame could not be corrected by the masters, set every frame.
+    pub mismatch: Option<DarkFlatError>,
+    error: Option<String>,
+}
+
+impl DarkFlatPanel {
+    pub fn new() -> Self {
+        Self {
+            masters: Masters::default(),
+            apply: true,
+            mismatch: None,
+            error: None,
+        }
+    }
+
+    /// Masters applied to the frames, none when disabled.
+    pub fn masters(&self) -> Masters {
+        if self.apply {
+            self.masters.clone()
+        } else {
+            Masters::default()
+        }
+    }
+
+    fn load() -> Option<Result<MasterFrame, String>> {
+        let path = rfd::FileDialog::new()
+            .add_filter("FITS", &fits::EXTENSIONS)
+            .pick_file()?;
+        Some(MasterFrame::open(&path).map_err(|e| e.to_string()))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("dark_flat").num_columns(3).show(ui, |ui| {
+            for (label, is_flat) in [("Dark", false), ("Flat", true)] {
+                ui.label(label);
+                let master = if is_flat {
+                    &mut self.masters.flat
+                } else {
+                    &mut self.masters.dark
+                };
+                match master {
+                    Some(frame) => ui.label(format!(
+                        "{} ({} × {})",
+                        frame.name, frame.width, frame.height
+                    )),
+                    None => ui.label("None"),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("Load…").clicked() {
+                        match Self::load() {
+                            Some(Ok(frame)) => {
+                                *master = Some(Arc::new(if is_flat {
+                                    frame.normalized()
+                                } else {
+                                    frame
+                                }));
+                                self.error = None;
+                            }
+                            Some(Err(e)) => self.error = Some(e),
+                            None => {}
+                        }
+                    }
+                    if ui
+                        .add_enabled(master.is_some(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        *master = None;
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        ui.checkbox(&mut self.apply, "Apply to the frames");
+        if let Some(mismatch) = self.mismatch.filter(|_| self.apply) {
+            ui.colored_label(ui.visuals().warn_fg_color, mismatch.to_string());
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
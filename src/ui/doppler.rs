@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+use eframe::egui;
+use egui_plot::{Legend, Line, Points, VLine};
+
+use crate::{
+    frame::Frame,
+    spectrum::{
+        dispersion::Dispersion,
+        doppler::{self, DopplerPoint, DopplerZero},
+        lines::{FRAUNHOFER, TELLURIC},
+        profile::SlitOrientation,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum ZeroMode {
+    SlitCentre,
+    Telluric,
+}
+
+/// Line of sight velocity along the slit from the shift of a line.
+pub struct DopplerPanel {
+    /// Measure on every frame.
+    live: bool,
+    /// Guessed core positions of the line and of the telluric line in pixels.
+    line: Option<f64>,
+    telluric: Option<f64>,
+    /// Rest wavelengths in Å.
+    line_rest: f64,
+    telluric_rest: f64,
+    zero: ZeroMode,
+    /// Half of the slit centre range used as zero, in pixels.
+    centre_half_width: usize,
+    half_window: usize,
+    band: usize,
+    frame: Option<Arc<Frame>>,
+    orientation: SlitOrientation,
+    points: Vec<DopplerPoint>,
+}
+
+/// Nearest rest wavelength within 2 Å.
+fn nearest(wavelength: f64, lines: impl Iterator<Item = f64>) -> Option<f64> {
+    lines
+        .filter(|line| (line - wavelength).abs() < 2.)
+        .min_by(|a, b| (a - wavelength).abs().total_cmp(&(b - wavelength).abs()))
+}
+
+impl DopplerPanel {
+    pub fn new() -> Self {
+        Self {
+            live: false,
+            line: None,
+            telluric: None,
+            line_rest: 6562.808,
+            telluric_rest: 6552.629,
+            zero: ZeroMode::SlitCentre,
+            centre_half_width: 20,
+            half_window: 10,
+            band: 4,
+            frame: None,
+            orientation: SlitOrientation::Vertical,
+            points: vec![],
+        }
+    }
+
+    /// Feed a frame after the geometric corrections.
+    pub fn update(&mut self, frame: &Arc<Frame>, orientation: SlitOrientation) {
+        self.frame = Some(frame.clone());
+        self.orientation = orientation;
+        if self.live {
+            self.measure();
+        }
+    }
+
+    fn measure(&mut self) {
+        let (Some(frame), Some(line)) = (&self.frame, self.line) else {
+            return;
+        };
+        let telluric = self.telluric.filter(|_| self.zero == ZeroMode::Telluric);
+        self.points = doppler::track(
+            frame,
+            self.orientation,
+            line,
+            telluric,
+            self.half_window,
+            self.band,
+        );
+
+        // follow the lines when the spectrum drifts
+        let centre = self.orientation.dimensions(frame).1 as f64 / 2.;
+        if let Some(point) = self
+            .points
+            .iter()
+            .min_by(|a, b| (a.slit - centre).abs().total_cmp(&(b.slit - centre).abs()))
+        {
+            self.line = Some(point.pixel);
+            if point.telluric.is_some() {
+                self.telluric = point.telluric;
+            }
+        }
+    }
+
+    fn zero(&self) -> DopplerZero {
+        match self.zero {
+            ZeroMode::SlitCentre => {
+                let centre = self
+                    .frame
+                    .as_ref()
+                    .map_or(0., |frame| self.orientation.dimensions(frame).1 as f64 / 2.);
+                let half_width = self.centre_half_width as f64;
+                DopplerZero::Slit {
+                    start: centre - half_width,
+                    end: centre + half_width,
+                }
+            }
+            ZeroMode::Telluric => DopplerZero::Telluric {
+                line: self.line_rest,
+                telluric: self.telluric_rest,
+            },
+        }
+    }
+
+    /// `selected` is the pixel of the line clicked in the spectrum plot.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        selected: Option<f64>,
+        dispersion: Option<&Dispersion>,
+    ) {
+        let Some(dispersion) = dispersion else {
+            ui.label("A wavelength solution is needed.");
+            return;
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(selected.is_some(), |ui| {
+                if ui
+                    .button("Use selected line")
+                    .on_hover_text("Line clicked in the spectrum plot")
+                    .clicked()
+                {
+                    if let Some(selected) = selected {
+                        self.line = Some(selected);
+                        let wavelength = dispersion.wavelength(selected);
+                        if let Some(rest) =
+                            nearest(wavelength, FRAUNHOFER.iter().map(|l| l.line.wavelength))
+                        {
+                            self.line_rest = rest;
+                        }
+                        self.measure();
+                    }
+                }
+            });
+            ui.add_enabled_ui(self.line.is_some() && self.frame.is_some(), |ui| {
+                if ui.button("Measure").clicked() {
+                    self.measure();
+                }
+                ui.toggle_value(&mut self.live, "Live");
+            });
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.half_window)
+                    .clamp_range(3..=200)
+                    .prefix("window ±")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.band)
+                    .clamp_range(1..=100)
+                    .prefix("band ")
+                    .suffix(" px"),
+            );
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Zero");
+            ui.radio_value(&mut self.zero, ZeroMode::SlitCentre, "Slit centre");
+            ui.radio_value(&mut self.zero, ZeroMode::Telluric, "Telluric line");
+        });
+        match self.zero {
+            ZeroMode::SlitCentre => {
+                ui.add(
+                    egui::DragValue::new(&mut self.centre_half_width)
+                        .clamp_range(1..=2000)
+                        .prefix("centre ±")
+                        .suffix(" px"),
+                );
+            }
+            ZeroMode::Telluric => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_enabled_ui(selected.is_some(), |ui| {
+                        if ui.button("Use selected as telluric").clicked() {
+                            if let Some(selected) = selected {
+                                self.telluric = Some(selected);
+                                let wavelength = dispersion.wavelength(selected);
+                                if let Some(rest) =
+                                    nearest(wavelength, TELLURIC.iter().map(|l| l.wavelength))
+                                {
+                                    self.telluric_rest = rest;
+                                }
+                                self.measure();
+                            }
+                        }
+                    });
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.line_rest)
+                            .speed(0.001)
+                            .max_decimals(3)
+                            .prefix("line ")
+                            .suffix(" Å"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.telluric_rest)
+                            .speed(0.001)
+                            .max_decimals(3)
+                            .prefix("telluric ")
+                            .suffix(" Å"),
+                    );
+                });
+                if self.telluric.is_none() {
+                    ui.label("Select a telluric line in the spectrum plot.");
+                }
+            }
+        }
+
+        let velocities = doppler::velocities(&self.points, dispersion, self.zero());
+        if velocities.is_empty() {
+            if self.line.is_some() {
+                ui.label("No measurement");
+            } else {
+                ui.label("Click a line in the spectrum plot.");
+            }
+            return;
+        }
+
+        let (min, max) = velocities
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), (_, v)| {
+                (min.min(*v), max.max(*v))
+            });
+        ui.label(format!(
+            "{:.2} to {:.2} km/s over {} bands",
+            min,
+            max,
+            velocities.len()
+        ));
+        let zero = self.zero();
+        let points: Vec<[f64; 2]> = velocities.iter().map(|(s, v)| [*s, *v]).collect();
+        egui_plot::Plot::new("doppler_plot")
+            .height(180.)
+            .legend(Legend::default())
+            .x_axis_label("Slit position [px]")
+            .y_axis_label("Velocity [km/s]")
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(points.clone()).name("Velocity"));
+                plot_ui.points(Points::new(points).radius(1.5).name("Velocity"));
+                if let DopplerZero::Slit { start, end } = zero {
+                    plot_ui.vline(VLine::new(start).name("Zero range"));
+                    plot_ui.vline(VLine::new(end).name("Zero range"));
+                }
+            });
+    }
+}
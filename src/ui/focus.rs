@@ -0,0 +1,232 @@
+use std::{collections::VecDeque, time::Instant};
+
+use eframe::egui;
+use egui_plot::{HLine, Legend, Line, PlotPoints};
+
+use crate::spectrum::{
+    dispersion::Dispersion,
+    focus::{self, LineWidth},
+    profile::Profile,
+};
+
+/// Number of frames kept in the history.
+const HISTORY_LEN: usize = 600;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FocusMetric {
+    /// Mean FWHM of the selected lines, lower is better.
+    Fwhm,
+    /// Gradient score of the whole profile, higher is better.
+    Gradient,
+}
+
+impl FocusMetric {
+    fn is_better(self, value: f64, best: f64) -> bool {
+        match self {
+            FocusMetric::Fwhm => value < best,
+            FocusMetric::Gradient => value > best,
+        }
+    }
+}
+
+struct FocusSample {
+    /// Seconds since the history was reset.
+    time: f64,
+    fwhm: Option<f64>,
+    gradient: f64,
+}
+
+impl FocusSample {
+    fn value(&self, metric: FocusMetric) -> Option<f64> {
+        match metric {
+            FocusMetric::Fwhm => self.fwhm,
+            FocusMetric::Gradient => Some(self.gradient),
+        }
+    }
+}
+
+/// Tracks the sharpness of the spectrum while the focus knob is turned.
+pub struct FocusAssistant {
+    pub metric: FocusMetric,
+    /// Guessed core positions of the measured lines in pixels.
+    lines: Vec<f64>,
+    half_window: usize,
+    widths: Vec<Option<LineWidth>>,
+    history: VecDeque<FocusSample>,
+    start: Instant,
+    best: Option<f64>,
+}
+
+impl FocusAssistant {
+    pub fn new() -> Self {
+        Self {
+            metric: FocusMetric::Fwhm,
+            lines: vec![],
+            half_window: 15,
+            widths: vec![],
+            history: VecDeque::new(),
+            start: Instant::now(),
+            best: None,
+        }
+    }
+
+    pub fn update(&mut self, profile: &Profile) {
+        self.widths = self
+            .lines
+            .iter()
+            .map(|line| focus::line_width(profile, *line, self.half_window))
+            .collect();
+        // follow the lines when the spectrum drifts
+        for (line, width) in self.lines.iter_mut().zip(&self.widths) {
+            if let Some(width) = width {
+                *line = width.centre;
+            }
+        }
+
+        let measured: Vec<f64> = self.widths.iter().flatten().map(|w| w.fwhm).collect();
+        let sample = FocusSample {
+            time: self.start.elapsed().as_secs_f64(),
+            fwhm: (!measured.is_empty())
+                .then(|| measured.iter().sum::<f64>() / measured.len() as f64),
+            gradient: focus::gradient_score(profile),
+        };
+
+        if let Some(value) = sample.value(self.metric) {
+            if self
+                .best
+                .is_none_or(|best| self.metric.is_better(value, best))
+            {
+                self.best = Some(value);
+            }
+        }
+        self.history.push_back(sample);
+        if self.history.len() > HISTORY_LEN {
+            self.history.pop_front();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+        self.start = Instant::now();
+        self.best = None;
+    }
+
+    fn current(&self) -> Option<f64> {
+        self.history.back()?.value(self.metric)
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        profile: Option<&Profile>,
+        dispersion: Option<&Dispersion>,
+    ) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Metric");
+            let mut metric = self.metric;
+            ui.radio_value(&mut metric, FocusMetric::Fwhm, "Line FWHM");
+            ui.radio_value(&mut metric, FocusMetric::Gradient, "Gradient");
+            if metric != self.metric {
+                self.metric = metric;
+                self.reset();
+            }
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(profile.is_some(), |ui| {
+                if ui
+                    .button("Add deepest line")
+                    .on_hover_text("Measure the deepest absorption line not selected yet")
+                    .clicked()
+                {
+                    if let Some(line) =
+                        profile.and_then(|p| focus::deepest_line(p, &self.lines, self.half_window))
+                    {
+                        self.lines.push(line as f64);
+                        self.reset();
+                    }
+                }
+            });
+            ui.add(
+                egui::DragValue::new(&mut self.half_window)
+                    .clamp_range(3..=200)
+                    .prefix("window ±")
+                    .suffix(" px"),
+            );
+        });
+
+        let mut remove = None;
+        for (i, line) in self.lines.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let width = self.widths.get(i).copied().flatten();
+                let text = match (width, dispersion) {
+                    (Some(width), Some(dispersion)) => format!(
+                        "{:.2} px  {:.3} Å",
+                        width.fwhm,
+                        width.fwhm * dispersion.angstrom_per_pixel(width.centre)
+                    ),
+                    (Some(width), None) => format!("{:.2} px", width.fwhm),
+                    (None, _) => "not found".to_string(),
+                };
+                ui.label(format!("Line at {:.1}: {}", line, text));
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.lines.remove(i);
+            self.reset();
+        }
+
+        ui.add_space(5.);
+        match (self.current(), self.best) {
+            (Some(current), Some(best)) => {
+                // 1 at the best focus reached so far
+                let quality = match self.metric {
+                    FocusMetric::Fwhm => best / current,
+                    FocusMetric::Gradient => current / best,
+                } as f32;
+                let color = if quality >= 0.95 {
+                    egui::Color32::from_rgb(0, 160, 0)
+                } else if quality >= 0.8 {
+                    egui::Color32::from_rgb(200, 160, 0)
+                } else {
+                    egui::Color32::from_rgb(200, 40, 40)
+                };
+                ui.add(
+                    egui::ProgressBar::new(quality.clamp(0., 1.))
+                        .fill(color)
+                        .text(format!("{:.3}  (best {:.3})", current, best)),
+                );
+            }
+            _ => {
+                ui.label("No measurement");
+            }
+        }
+        if ui.button("Reset best").clicked() {
+            self.reset();
+        }
+
+        let metric = self.metric;
+        let points: PlotPoints = self
+            .history
+            .iter()
+            .filter_map(|sample| Some([sample.time, sample.value(metric)?]))
+            .collect();
+        let name = match metric {
+            FocusMetric::Fwhm => "FWHM [px]",
+            FocusMetric::Gradient => "Gradient [%]",
+        };
+        egui_plot::Plot::new("focus_plot")
+            .height(150.)
+            .legend(Legend::default())
+            .x_axis_label("Time [s]")
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(points).name(name));
+                if let Some(best) = self.best {
+                    plot_ui.hline(HLine::new(best).name("Best"));
+                }
+            });
+    }
+}
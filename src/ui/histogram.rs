@@ -0,0 +1,76 @@
+use eframe::egui;
+use egui_plot::{Line, PlotPoints};
+
+use crate::imaging::histogram::{Channel, Histogram};
+
+/// Clipped fractions above this are shown as a warning.
+const CLIP_WARNING: f32 = 1e-4;
+
+pub fn histogram_ui(ui: &mut egui::Ui, histogram: Option<&Histogram>) {
+    let Some(histogram) = histogram else {
+        ui.label("No image");
+        return;
+    };
+
+    let mut log_scale = ui
+        .memory(|mem| mem.data.get_temp(egui::Id::new("histogram_log")))
+        .unwrap_or(true);
+    if ui.checkbox(&mut log_scale, "Log scale").changed() {
+        ui.memory_mut(|mem| {
+            mem.data
+                .insert_temp(egui::Id::new("histogram_log"), log_scale)
+        });
+    }
+
+    egui_plot::Plot::new("histogram_plot")
+        .height(120.)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .show_y(false)
+        .y_axis_width(2)
+        .show(ui, |plot_ui| {
+            for channel in &histogram.channels {
+                let bin_width = histogram.max_value as f64 / channel.counts.len() as f64;
+                let points: PlotPoints = channel
+                    .counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, count)| {
+                        let count = *count as f64;
+                        let y = if log_scale {
+                            (count + 1.).log10()
+                        } else {
+                            count
+                        };
+                        [i as f64 * bin_width, y]
+                    })
+                    .collect();
+                let color = match channel.channel {
+                    Channel::Luminance => egui::Color32::GRAY,
+                    Channel::Red => egui::Color32::RED,
+                    Channel::Green => egui::Color32::GREEN,
+                    Channel::Blue => egui::Color32::LIGHT_BLUE,
+                };
+                plot_ui.line(Line::new(points).color(color).fill(0.));
+            }
+        });
+
+    let clip_label = |ui: &mut egui::Ui, name: &str, fraction: f32| {
+        let text = format!("{}: {:.3}%", name, fraction * 100.);
+        if fraction > CLIP_WARNING {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", text));
+        } else {
+            ui.label(text);
+        }
+    };
+    ui.horizontal(|ui| {
+        clip_label(ui, "Saturated", histogram.saturated());
+        ui.separator();
+        clip_label(ui, "Black", histogram.black_clipped());
+    });
+    ui.label(format!(
+        "Peak: {:.1}% of full scale",
+        histogram.peak() * 100.
+    ));
+}
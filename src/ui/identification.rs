@@ -0,0 +1,190 @@
+use eframe::egui;
+
+use super::image_view::ImageOverlay;
+use crate::{
+    frame::Frame,
+    spectrum::{
+        dispersion::Dispersion,
+        identification::{self, Identification, Identified},
+        lines::{SpectralLine, FRAUNHOFER, TELLURIC},
+        profile::{Profile, SlitOrientation},
+    },
+};
+
+/// Tick length on the image view as a fraction of the slit length.
+const TICK_LENGTH: f32 = 0.04;
+
+/// Identifies the absorption lines of the live profile against the solar line list.
+pub struct IdentificationPanel {
+    pub enabled: bool,
+    /// Draw labelled markers on the spectrum plot.
+    pub show_labels: bool,
+    /// Draw ticks at the edges of the image view.
+    pub show_ticks: bool,
+    telluric: bool,
+    sigma: f64,
+    /// Largest distance to a reference line in Å.
+    tolerance: f64,
+    /// Largest error of the predicted dispersion in Å.
+    max_shift: f64,
+    calibrated: bool,
+    orientation: SlitOrientation,
+    slit_len: usize,
+    identified: Identified,
+}
+
+impl IdentificationPanel {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            show_labels: true,
+            show_ticks: false,
+            telluric: false,
+            sigma: 5.,
+            tolerance: 0.5,
+            max_shift: 20.,
+            calibrated: false,
+            orientation: SlitOrientation::Vertical,
+            slit_len: 0,
+            identified: Identified::default(),
+        }
+    }
+
+    /// `calibrated` tells whether the dispersion is measured or predicted from the geometry.
+    pub fn update(
+        &mut self,
+        frame: &Frame,
+        orientation: SlitOrientation,
+        profile: &Profile,
+        dispersion: Option<&Dispersion>,
+        calibrated: bool,
+    ) {
+        self.orientation = orientation;
+        self.slit_len = orientation.dimensions(frame).1;
+        self.calibrated = calibrated;
+        let Some(dispersion) = dispersion.filter(|_| self.enabled) else {
+            self.identified = Identified::default();
+            return;
+        };
+
+        let mut lines: Vec<SpectralLine> = FRAUNHOFER.iter().map(|line| line.line).collect();
+        if self.telluric {
+            lines.extend_from_slice(TELLURIC);
+        }
+        // a measured solution only needs the lines to be within the tolerance
+        let max_shift = if calibrated {
+            self.tolerance
+        } else {
+            self.max_shift
+        };
+        self.identified = identification::identify(
+            profile,
+            dispersion,
+            &lines,
+            self.sigma,
+            self.tolerance,
+            max_shift,
+        );
+    }
+
+    /// Lines to label on the spectrum plot.
+    pub fn labels(&self) -> Vec<Identification> {
+        if self.show_labels {
+            self.identified.lines.clone()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Ticks at both ends of the slit in image coordinates.
+    pub fn overlays(&self) -> Vec<ImageOverlay> {
+        if !self.show_ticks {
+            return vec![];
+        }
+        let slit_len = self.slit_len as f32;
+        let tick = slit_len * TICK_LENGTH;
+        let to_image = |slit: f32, dispersion: f32| match self.orientation {
+            SlitOrientation::Vertical => egui::pos2(dispersion, slit),
+            SlitOrientation::Horizontal => egui::pos2(slit, dispersion),
+        };
+        self.identified
+            .lines
+            .iter()
+            .flat_map(|line| {
+                let pixel = line.pixel as f32;
+                [(0., tick), (slit_len - tick, slit_len)].map(|(start, end)| ImageOverlay {
+                    points: vec![to_image(start, pixel), to_image(end, pixel)],
+                    color: egui::Color32::from_rgb(255, 120, 0),
+                    connected: true,
+                })
+            })
+            .collect()
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, dispersion: Option<&Dispersion>) {
+        if dispersion.is_none() {
+            ui.label("A wavelength solution or the grating geometry is needed.");
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.enabled, "Identify lines");
+            ui.add_enabled_ui(self.enabled, |ui| {
+                ui.checkbox(&mut self.show_labels, "Labels");
+                ui.checkbox(&mut self.show_ticks, "Image ticks");
+                ui.checkbox(&mut self.telluric, "Telluric");
+            });
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.sigma)
+                    .clamp_range(1. ..=50.)
+                    .speed(0.1)
+                    .prefix("threshold ")
+                    .suffix(" σ"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.tolerance)
+                    .clamp_range(0.05..=5.)
+                    .speed(0.01)
+                    .prefix("tolerance ")
+                    .suffix(" Å"),
+            );
+            ui.add_enabled_ui(!self.calibrated, |ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.max_shift)
+                        .clamp_range(0. ..=100.)
+                        .speed(0.1)
+                        .prefix("search ±")
+                        .suffix(" Å"),
+                )
+                .on_hover_text("Largest error of the wavelength predicted from the grating angle");
+            });
+        });
+        if !self.enabled {
+            return;
+        }
+
+        ui.label(format!(
+            "{} lines identified with the {} dispersion, offset {:+.2} Å",
+            self.identified.lines.len(),
+            if self.calibrated {
+                "measured"
+            } else {
+                "predicted"
+            },
+            self.identified.shift
+        ));
+        egui::Grid::new("identification_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for line in &self.identified.lines {
+                    ui.label(line.line.name);
+                    ui.label(format!("{:.3} Å", line.line.wavelength));
+                    ui.label(format!("{:+.3} Å", line.residual));
+                    ui.end_row();
+                }
+            });
+    }
+}
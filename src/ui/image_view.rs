@@ -0,0 +1,374 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::{
+    frame::{Frame, Pixels},
+    imaging::{
+        histogram::{Histogram, SATURATION},
+        stretch::{self, Levels, Stretch},
+    },
+};
+
+/// Fraction of the pixels clipped on each side by the automatic levels.
+const AUTO_CLIP: f32 = 0.001;
+
+struct RenderRequest {
+    frame: Arc<Frame>,
+    stretch: Stretch,
+    /// `None` for automatic levels.
+    levels: Option<Levels>,
+    /// Paint saturated pixels red and black clipped pixels blue.
+    highlight_clipped: bool,
+}
+
+struct Rendered {
+    image: egui::ColorImage,
+    levels: Levels,
+    histogram: Histogram,
+}
+
+/// Converts frames to textures on a worker thread, dropping frames it can not keep up with.
+struct Renderer {
+    sender: Sender<RenderRequest>,
+    rendered: Arc<Mutex<Option<Rendered>>>,
+}
+
+impl Renderer {
+    fn new(ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let rendered = Arc::new(Mutex::new(None));
+        let output = rendered.clone();
+        thread::spawn(move || render_loop(receiver, &output, &ctx));
+        Self { sender, rendered }
+    }
+}
+
+fn render_loop(
+    receiver: Receiver<RenderRequest>,
+    output: &Mutex<Option<Rendered>>,
+    ctx: &egui::Context,
+) {
+    while let Ok(mut request) = receiver.recv() {
+        while let Ok(newer) = receiver.try_recv() {
+            request = newer;
+        }
+        let rendered = render(&request);
+        *output.lock().unwrap() = Some(rendered);
+        ctx.request_repaint();
+    }
+}
+
+fn render(request: &RenderRequest) -> Rendered {
+    let frame = &request.frame;
+    let levels = request
+        .levels
+        .unwrap_or_else(|| Levels::auto(frame, AUTO_CLIP));
+    let lut = stretch::lut(frame, request.stretch, levels);
+    let size = [frame.width, frame.height];
+
+    let mut image = match &frame.pixels {
+        Pixels::Mono8(p) => {
+            let gray: Vec<u8> = p.iter().map(|v| lut[*v as usize]).collect();
+            egui::ColorImage::from_gray(size, &gray)
+        }
+        Pixels::Mono16(p) => {
+            let gray: Vec<u8> = p.iter().map(|v| lut[*v as usize]).collect();
+            egui::ColorImage::from_gray(size, &gray)
+        }
+        Pixels::Bgr24(p) => egui::ColorImage {
+            size,
+            pixels: p
+                .chunks_exact(3)
+                .map(|bgr| {
+                    egui::Color32::from_rgb(
+                        lut[bgr[2] as usize],
+                        lut[bgr[1] as usize],
+                        lut[bgr[0] as usize],
+                    )
+                })
+                .collect(),
+        },
+    };
+
+    if request.highlight_clipped {
+        let saturation = frame.max_value() * SATURATION;
+        for (i, pixel) in image.pixels.iter_mut().enumerate() {
+            let value = frame.value(i % frame.width, i / frame.width);
+            if value >= saturation {
+                *pixel = egui::Color32::RED;
+            } else if value <= 0. {
+                *pixel = egui::Color32::BLUE;
+            }
+        }
+    }
+
+    Rendered {
+        image,
+        levels,
+        histogram: Histogram::compute(frame, 256),
+    }
+}
+
+/// Shape drawn over the image, in image pixel coordinates.
+pub struct ImageOverlay {
+    pub points: Vec<egui::Pos2>,
+    pub color: egui::Color32,
+    /// Join the points with a line instead of marking each one.
+    pub connected: bool,
+}
+
+/// Live camera frame in the central panel.
+pub struct ImageView {
+    pub stretch: Stretch,
+    pub auto_levels: bool,
+    pub levels: Levels,
+    pub highlight_clipped: bool,
+    /// Histogram of the last rendered frame.
+    pub histogram: Option<Histogram>,
+    /// Drawn over the image, replaced by the owner every frame.
+    pub overlays: Vec<ImageOverlay>,
+    renderer: Renderer,
+    texture: Option<egui::TextureHandle>,
+    frame: Option<Arc<Frame>>,
+    frame_times: VecDeque<Instant>,
+    zoom: f32,
+    /// Offset of the image centre from the view centre in screen points.
+    pan: egui::Vec2,
+    fit: bool,
+}
+
+impl ImageView {
+    pub fn new(ctx: egui::Context) -> Self {
+        Self {
+            stretch: Stretch::Linear,
+            auto_levels: true,
+            levels: Levels {
+                black: 0.,
+                white: u16::MAX as f32,
+            },
+            highlight_clipped: false,
+            histogram: None,
+            overlays: vec![],
+            renderer: Renderer::new(ctx),
+            texture: None,
+            frame: None,
+            frame_times: VecDeque::new(),
+            zoom: 1.,
+            pan: egui::Vec2::ZERO,
+            fit: true,
+        }
+    }
+
+    pub fn update(&mut self, frame: Arc<Frame>) {
+        let now = Instant::now();
+        self.frame_times.push_back(now);
+        while let Some(time) = self.frame_times.front() {
+            if now.duration_since(*time) <= Duration::from_secs(1) {
+                break;
+            }
+            self.frame_times.pop_front();
+        }
+
+        self.frame = Some(frame);
+        self.render();
+    }
+
+    fn render(&self) {
+        if let Some(frame) = &self.frame {
+            let _ = self.renderer.sender.send(RenderRequest {
+                frame: frame.clone(),
+                stretch: self.stretch,
+                levels: (!self.auto_levels).then_some(self.levels),
+                highlight_clipped: self.highlight_clipped,
+            });
+        }
+    }
+
+    pub fn frame_rate(&self) -> f32 {
+        match (self.frame_times.front(), self.frame_times.back()) {
+            (Some(first), Some(last)) if self.frame_times.len() > 1 => {
+                (self.frame_times.len() - 1) as f32 / last.duration_since(*first).as_secs_f32()
+            }
+            _ => 0.,
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        let full_scale = match self.frame.as_ref().map(|frame| &frame.pixels) {
+            Some(Pixels::Mono16(_)) => u16::MAX as f32,
+            _ => u8::MAX as f32,
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            let mut changed = false;
+
+            ui.label("Stretch");
+            for (stretch, name) in [
+                (Stretch::Linear, "Linear"),
+                (Stretch::Log, "Log"),
+                (Stretch::Asinh, "Asinh"),
+            ] {
+                changed |= ui.radio_value(&mut self.stretch, stretch, name).changed();
+            }
+
+            ui.separator();
+            changed |= ui.checkbox(&mut self.auto_levels, "Auto levels").changed();
+            ui.add_enabled_ui(!self.auto_levels, |ui| {
+                let white = self.levels.white;
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.levels.black)
+                            .clamp_range(0.0..=white - 1.)
+                            .prefix("black "),
+                    )
+                    .changed();
+                let black = self.levels.black;
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.levels.white)
+                            .clamp_range(black + 1.0..=full_scale)
+                            .prefix("white "),
+                    )
+                    .changed();
+            });
+
+            ui.separator();
+            changed |= ui
+                .toggle_value(&mut self.highlight_clipped, "Highlight clipped")
+                .on_hover_text("Saturated pixels in red, pixels at zero in blue")
+                .changed();
+
+            ui.separator();
+            if ui.button("Fit").clicked() {
+                self.fit = true;
+            }
+            if ui.button("1:1").clicked() {
+                self.fit = false;
+                self.zoom = 1.;
+                self.pan = egui::Vec2::ZERO;
+            }
+            ui.label(format!("{:.0}%", self.zoom * 100.));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(format!("{:.1} fps", self.frame_rate()));
+            });
+
+            if changed {
+                self.render();
+            }
+        });
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(rendered) = self.renderer.rendered.lock().unwrap().take() {
+            if self.auto_levels {
+                self.levels = rendered.levels;
+            }
+            self.histogram = Some(rendered.histogram);
+            match &mut self.texture {
+                Some(texture) => texture.set(rendered.image, egui::TextureOptions::NEAREST),
+                None => {
+                    self.texture = Some(ui.ctx().load_texture(
+                        "live_frame",
+                        rendered.image,
+                        egui::TextureOptions::NEAREST,
+                    ))
+                }
+            }
+        }
+
+        self.toolbar(ui);
+
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0., egui::Color32::BLACK);
+
+        let Some(texture) = &self.texture else {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No image",
+                egui::FontId::proportional(20.),
+                ui.visuals().weak_text_color(),
+            );
+            return;
+        };
+        let image_size = texture.size_vec2();
+
+        if self.fit || response.double_clicked() {
+            self.fit = true;
+            self.zoom = (rect.width() / image_size.x).min(rect.height() / image_size.y);
+            self.pan = egui::Vec2::ZERO;
+        }
+
+        if response.dragged() {
+            self.fit = false;
+            self.pan += response.drag_delta();
+        }
+
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = ui.input(|i| i.scroll_delta.y);
+            if scroll != 0. {
+                self.fit = false;
+                let factor = (scroll / 200.).exp();
+                // keep the pixel under the cursor in place
+                let anchor = pointer - rect.center() - self.pan;
+                self.pan -= anchor * (factor - 1.);
+                self.zoom = (self.zoom * factor).clamp(0.01, 50.);
+            }
+        }
+
+        let image_rect =
+            egui::Rect::from_center_size(rect.center() + self.pan, image_size * self.zoom);
+        painter.with_clip_rect(rect).image(
+            texture.id(),
+            image_rect,
+            egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+            egui::Color32::WHITE,
+        );
+
+        // pixel centres on screen
+        let to_screen = |point: &egui::Pos2| {
+            image_rect.min + (point.to_vec2() + egui::vec2(0.5, 0.5)) * self.zoom
+        };
+        let clipped = painter.with_clip_rect(rect);
+        for overlay in &self.overlays {
+            let points: Vec<egui::Pos2> = overlay.points.iter().map(to_screen).collect();
+            if overlay.connected {
+                clipped.add(egui::Shape::line(
+                    points,
+                    egui::Stroke::new(1.5, overlay.color),
+                ));
+            } else {
+                for point in points {
+                    clipped.circle_stroke(point, 3., egui::Stroke::new(1.5, overlay.color));
+                }
+            }
+        }
+
+        if let (Some(pointer), Some(frame)) = (response.hover_pos(), &self.frame) {
+            let position = (pointer - image_rect.min) / self.zoom;
+            let (x, y) = (position.x.floor(), position.y.floor());
+            if x >= 0. && y >= 0. && (x as usize) < frame.width && (y as usize) < frame.height {
+                let value = frame.value(x as usize, y as usize);
+                painter.text(
+                    rect.left_bottom() + egui::vec2(5., -5.),
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("x {} y {}  {:.0} ADU", x, y, value),
+                    egui::FontId::monospace(14.),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+    }
+}
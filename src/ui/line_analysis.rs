@@ -0,0 +1,302 @@
+use std::{fs::File, io::Write, path::Path};
+
+use eframe::egui;
+use egui_plot::{Legend, Line, Points};
+
+use crate::spectrum::{
+    dispersion::Dispersion,
+    line_analysis::{AnalysisError, LineAnalysis},
+    line_fit::LineShape,
+    profile::Profile,
+};
+
+/// Measurements of a line kept in the table, in Å when calibrated and pixels otherwise.
+struct TableRow {
+    centre: f64,
+    centre_error: f64,
+    bisector_centre: f64,
+    depth: f64,
+    depth_error: f64,
+    fwhm: f64,
+    fwhm_error: f64,
+    equivalent_width: f64,
+    equivalent_width_error: f64,
+    unit: &'static str,
+}
+
+impl TableRow {
+    fn new(analysis: &LineAnalysis, dispersion: Option<&Dispersion>) -> Self {
+        let fit = &analysis.fit;
+        let (centre, centre_error, bisector_centre, unit) = match dispersion {
+            Some(dispersion) => (
+                dispersion.wavelength(fit.centre),
+                fit.centre_error * dispersion.angstrom_per_pixel(fit.centre).abs(),
+                dispersion.wavelength(analysis.bisector_centre),
+                "Å",
+            ),
+            None => (fit.centre, fit.centre_error, analysis.bisector_centre, "px"),
+        };
+        Self {
+            centre,
+            centre_error,
+            bisector_centre,
+            depth: analysis.depth,
+            depth_error: analysis.noise,
+            fwhm: analysis.fwhm,
+            fwhm_error: analysis.fwhm_error,
+            equivalent_width: analysis.equivalent_width,
+            equivalent_width_error: analysis.equivalent_width_error,
+            unit,
+        }
+    }
+}
+
+/// Continuum normalized measurements of the line selected in the spectrum plot.
+pub struct LineAnalysisPanel {
+    shape: LineShape,
+    half_window: usize,
+    continuum_width: usize,
+    /// Profile of the latest frame, measured again when the settings change.
+    profile: Option<Profile>,
+    /// Line position the analysis was measured at.
+    measured: Option<f64>,
+    analysis: Option<Result<LineAnalysis, AnalysisError>>,
+    table: Vec<TableRow>,
+    export_error: Option<String>,
+}
+
+impl LineAnalysisPanel {
+    pub fn new() -> Self {
+        Self {
+            shape: LineShape::Voigt,
+            half_window: 10,
+            continuum_width: 10,
+            profile: None,
+            measured: None,
+            analysis: None,
+            table: vec![],
+            export_error: None,
+        }
+    }
+
+    /// Feed the profile of a new frame, `selected` is the pixel of the line clicked in the
+    /// spectrum plot.
+    pub fn update(
+        &mut self,
+        profile: &Profile,
+        selected: Option<f64>,
+        dispersion: Option<&Dispersion>,
+    ) {
+        self.profile = Some(profile.clone());
+        self.measure(selected, dispersion);
+    }
+
+    fn measure(&mut self, selected: Option<f64>, dispersion: Option<&Dispersion>) {
+        self.measured = selected;
+        self.analysis = self
+            .profile
+            .as_ref()
+            .zip(selected)
+            .map(|(profile, selected)| {
+                LineAnalysis::measure(
+                    profile,
+                    selected,
+                    self.half_window,
+                    self.continuum_width,
+                    self.shape,
+                    dispersion,
+                )
+            });
+    }
+
+    fn export(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "centre,centre_error,bisector_centre,depth,depth_error,fwhm,fwhm_error,\
+             equivalent_width,equivalent_width_error,unit"
+        )?;
+        for row in &self.table {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{}",
+                row.centre,
+                row.centre_error,
+                row.bisector_centre,
+                row.depth,
+                row.depth_error,
+                row.fwhm,
+                row.fwhm_error,
+                row.equivalent_width,
+                row.equivalent_width_error,
+                if row.unit == "Å" {
+                    "angstrom"
+                } else {
+                    "pixel"
+                }
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `selected` is the pixel of the line clicked in the spectrum plot.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        selected: Option<f64>,
+        dispersion: Option<&Dispersion>,
+    ) {
+        let mut changed = ui
+            .horizontal_wrapped(|ui| {
+                ui.label("Profile");
+                ui.radio_value(&mut self.shape, LineShape::Gaussian, "Gaussian")
+                    .changed()
+                    | ui.radio_value(&mut self.shape, LineShape::Voigt, "Voigt")
+                        .changed()
+            })
+            .inner;
+        changed |= ui
+            .horizontal_wrapped(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.half_window)
+                        .clamp_range(3..=200)
+                        .prefix("line ±")
+                        .suffix(" px"),
+                )
+                .changed()
+                    | ui.add(
+                        egui::DragValue::new(&mut self.continuum_width)
+                            .clamp_range(2..=200)
+                            .prefix("continuum ")
+                            .suffix(" px"),
+                    )
+                    .changed()
+            })
+            .inner;
+        // the selection also changes between frames, when the view is frozen or paused
+        if changed || selected != self.measured {
+            self.measure(selected, dispersion);
+        }
+
+        let analysis = match &self.analysis {
+            Some(Ok(analysis)) => analysis,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                return;
+            }
+            None => {
+                ui.label("Click a line in the spectrum plot.");
+                return;
+            }
+        };
+        let row = TableRow::new(analysis, dispersion);
+
+        ui.add_space(5.);
+        egui::Grid::new("line_analysis_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let unit = row.unit;
+                ui.label("Centre (fit)");
+                ui.label(format!(
+                    "{:.3} ± {:.3} {}",
+                    row.centre, row.centre_error, unit
+                ));
+                ui.end_row();
+                ui.label("Centre (bisector)");
+                ui.label(format!("{:.3} {}", row.bisector_centre, unit));
+                ui.end_row();
+                ui.label("Depth");
+                ui.label(format!("{:.3} ± {:.3}", row.depth, row.depth_error));
+                ui.end_row();
+                ui.label("FWHM");
+                ui.label(format!("{:.3} ± {:.3} {}", row.fwhm, row.fwhm_error, unit));
+                ui.end_row();
+                ui.label("Equivalent width");
+                ui.label(format!(
+                    "{:.3} ± {:.3} {}",
+                    row.equivalent_width, row.equivalent_width_error, unit
+                ));
+                ui.end_row();
+            });
+
+        let x = |pixel: f64| dispersion.map_or(pixel, |d| d.wavelength(pixel));
+        let observed: Vec<[f64; 2]> = analysis
+            .normalized
+            .iter()
+            .map(|(pixel, value)| [x(*pixel), *value])
+            .collect();
+        let (start, end) = analysis.window;
+        let model: Vec<[f64; 2]> = (start * 4..=end * 4)
+            .map(|i| {
+                let pixel = i as f64 / 4.;
+                [x(pixel), analysis.fit.value(pixel)]
+            })
+            .collect();
+        let bisector: Vec<[f64; 2]> = analysis
+            .bisector
+            .iter()
+            .map(|(level, pixel)| [x(*pixel), *level])
+            .collect();
+        egui_plot::Plot::new("line_analysis_plot")
+            .height(180.)
+            .legend(Legend::default())
+            .x_axis_label(if dispersion.is_some() {
+                "Wavelength [Å]"
+            } else {
+                "Pixel"
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(observed).name("Normalized"));
+                plot_ui.line(Line::new(model).name("Fit"));
+                plot_ui.line(Line::new(bisector.clone()).name("Bisector"));
+                plot_ui.points(Points::new(bisector).radius(2.).name("Bisector"));
+            });
+
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Add to table").clicked() {
+                self.table.push(row);
+            }
+            ui.add_enabled_ui(!self.table.is_empty(), |ui| {
+                if ui.button("Export CSV").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .set_file_name("lines.csv")
+                        .save_file()
+                    {
+                        self.export_error = self.export(&path).err().map(|e| e.to_string());
+                    }
+                }
+                if ui.button("Clear").clicked() {
+                    self.table.clear();
+                }
+            });
+        });
+        if let Some(error) = &self.export_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let mut remove = None;
+        egui::Grid::new("line_analysis_table")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, row) in self.table.iter().enumerate() {
+                    ui.label(format!("{:.3} {}", row.centre, row.unit));
+                    ui.label(format!("depth {:.3}", row.depth));
+                    ui.label(format!("FWHM {:.3}", row.fwhm));
+                    ui.label(format!(
+                        "EW {:.3} ± {:.3}",
+                        row.equivalent_width, row.equivalent_width_error
+                    ));
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = remove {
+            self.table.remove(i);
+        }
+    }
+}
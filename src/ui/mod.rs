@@ -1 +1,18 @@
 pub mod app;
+pub mod calibration;
+pub mod continuum;
+pub mod correction;
+pub mod dark_flat;
+pub mod doppler;
+pub mod focus;
+pub mod histogram;
+pub mod identification;
+pub mod image_view;
+pub mod line_analysis;
+pub mod recorder;
+pub mod replay;
+pub mod resolution;
+pub mod smile;
+pub mod spectroheliogram;
+pub mod spectrum_plot;
+pub mod tilt;
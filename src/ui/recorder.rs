@@ -0,0 +1,244 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use chrono::{DateTime, Local, Utc};
+use eframe::egui;
+
+use crate::{
+    asi::camera::{CameraCommand, CameraDriver, RecordQueue, TimedFrame},
+    fits::{self, Acquisition, FitsImage},
+    frame::Frame,
+    ser::{SerMetadata, SerWriter},
+};
+
+/// Frames queued for the writer, about a second of capture.
+const QUEUE_DEPTH: usize = 32;
+
+/// SER file being written on its own thread.
+struct Recording {
+    path: PathBuf,
+    started: Instant,
+    /// Frames written so far.
+    frames: Arc<AtomicUsize>,
+    /// Frames captured while the queue was full.
+    dropped: Arc<AtomicUsize>,
+    handle: JoinHandle<Result<usize, String>>,
+}
+
+/// Records the raw camera frames of a scan to a SER file for later processing, or
+/// saves single frames as FITS.
+pub struct RecorderPanel {
+    pub metadata: SerMetadata,
+    /// Latest raw frame, from the camera or a replay.
+    pub frame: Option<Arc<Frame>>,
+    /// UTC time `frame` was captured, when known.
+    pub frame_time: Option<DateTime<Utc>>,
+    pub acquisition: Acquisition,
+    recording: Option<Recording>,
+    /// Result of the last recording.
+    status: Option<Result<String, String>>,
+}
+
+impl RecorderPanel {
+    pub fn new(metadata: SerMetadata) -> Self {
+        Self {
+            metadata,
+            frame: None,
+            frame_time: None,
+            acquisition: Acquisition::default(),
+            recording: None,
+            status: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    fn start(&mut self, camera: &CameraDriver, path: PathBuf) {
+        let mut writer = match SerWriter::create(&path, self.metadata.clone()) {
+            Ok(writer) => writer,
+            Err(e) => {
+                self.status = Some(Err(e.to_string()));
+                return;
+            }
+        };
+        let (sender, receiver) = mpsc::sync_channel::<TimedFrame>(QUEUE_DEPTH);
+        let frames = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let frames = frames.clone();
+            thread::spawn(move || {
+                // ends when the camera drops the sender
+                let mut result = Ok(());
+                for (frame, time) in receiver {
+                    if let Err(e) = writer.write(&frame, time) {
+                        result = Err(e);
+                        break;
+                    }
+                    frames.fetch_add(1, Ordering::Relaxed);
+                }
+                let count = writer.finish();
+                result.and(count).map_err(|e| e.to_string())
+            })
+        };
+        camera.send(CameraCommand::Record(Some(RecordQueue {
+            sender,
+            dropped: dropped.clone(),
+        })));
+        self.recording = Some(Recording {
+            path,
+            started: Instant::now(),
+            frames,
+            dropped,
+            handle,
+        });
+        self.status = None;
+    }
+
+    fn save_frame(&mut self, frame: &Frame, path: PathBuf) {
+        let mut image = FitsImage::from_frame(frame);
+        Acquisition {
+            date: self.frame_time,
+            ..self.acquisition.clone()
+        }
+        .write(&mut image.header);
+        self.status = Some(
+            image
+                .save(&path)
+                .map(|_| format!("Frame saved to {}", path.display()))
+                .map_err(|e| e.to_string()),
+        );
+    }
+
+    /// Collect the result of a finished recording, also when the camera disconnected.
+    pub fn poll(&mut self) {
+        if !self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| recording.handle.is_finished())
+        {
+            return;
+        }
+        if let Some(recording) = self.recording.take() {
+            let dropped = recording.dropped.load(Ordering::Relaxed);
+            self.status = Some(match recording.handle.join() {
+                Ok(Ok(count)) if dropped > 0 => Err(format!(
+                    "{} frames saved to {}, {} dropped as the disk was too slow",
+                    count,
+                    recording.path.display(),
+                    dropped
+                )),
+                Ok(Ok(count)) => Ok(format!(
+                    "{} frames saved to {}",
+                    count,
+                    recording.path.display()
+                )),
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err("The recording stopped unexpectedly.".to_string()),
+            });
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, camera: &CameraDriver) {
+        self.poll();
+
+        ui.add_enabled_ui(!self.is_recording(), |ui| {
+            egui::Grid::new("ser_metadata")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Observer");
+                    ui.text_edit_singleline(&mut self.metadata.observer);
+                    ui.end_row();
+                    ui.label("Instrument");
+                    ui.text_edit_singleline(&mut self.metadata.instrument);
+                    ui.end_row();
+                    ui.label("Telescope");
+                    ui.text_edit_singleline(&mut self.metadata.telescope);
+                    ui.end_row();
+                    ui.label("Site");
+                    ui.text_edit_singleline(&mut self.metadata.site);
+                    ui.end_row();
+                });
+        });
+
+        ui.add_space(5.);
+        match &self.recording {
+            Some(recording) => {
+                ui.horizontal_wrapped(|ui| {
+                    if ui.button("⏹ Stop").clicked() {
+                        camera.send(CameraCommand::Record(None));
+                    }
+                    ui.label(format!(
+                        "{} frames, {:.0} s",
+                        recording.frames.load(Ordering::Relaxed),
+                        recording.started.elapsed().as_secs_f64()
+                    ));
+                    let dropped = recording.dropped.load(Ordering::Relaxed);
+                    if dropped > 0 {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{} dropped", dropped),
+                        );
+                    }
+                });
+                ui.ctx().request_repaint();
+            }
+            None => {
+                ui.horizontal_wrapped(|ui| {
+                    let is_live = camera.status().frame.is_some();
+                    if ui
+                        .add_enabled(is_live, egui::Button::new("⏺ Record SER…"))
+                        .on_hover_text("Save every raw camera frame with its UTC time")
+                        .clicked()
+                    {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("SER", &["ser"])
+                            .set_file_name(format!(
+                                "scan_{}.ser",
+                                Local::now().format("%Y%m%d_%H%M%S")
+                            ))
+                            .save_file()
+                        {
+                            self.start(camera, path);
+                        }
+                    }
+                    if let Some(frame) = self.frame.clone() {
+                        if ui
+                            .button("Save frame FITS…")
+                            .on_hover_text("Save the latest raw frame with the camera settings")
+                            .clicked()
+                        {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("FITS", &fits::EXTENSIONS)
+                                .set_file_name(format!(
+                                    "frame_{}.fits",
+                                    Local::now().format("%Y%m%d_%H%M%S")
+                                ))
+                                .save_file()
+                            {
+                                self.save_frame(&frame, path);
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(error)) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            None => {}
+        }
+    }
+}
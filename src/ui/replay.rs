@@ -0,0 +1,195 @@
+use std::{path::Path, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+
+use crate::{
+    fits::{self, FitsImage},
+    frame::{Frame, FrameSource},
+    replay::{ReplayCommand, ReplayDriver},
+};
+
+/// Single frame read from a FITS file.
+struct Still {
+    name: String,
+    frame: Arc<Frame>,
+    date: Option<DateTime<Utc>>,
+}
+
+/// Plays back a recorded SER file or shows a saved FITS frame in place of the live
+/// camera.
+pub struct ReplayPanel {
+    driver: Option<ReplayDriver>,
+    still: Option<Still>,
+    /// Files opened so far, the frame count of the stills.
+    opened: u64,
+    error: Option<String>,
+}
+
+impl ReplayPanel {
+    pub fn new() -> Self {
+        Self {
+            driver: None,
+            still: None,
+            opened: 0,
+            error: None,
+        }
+    }
+
+    /// Whether a file takes the place of the camera.
+    pub fn is_active(&self) -> bool {
+        self.driver.is_some() || self.still.is_some()
+    }
+
+    fn close(&mut self) {
+        self.driver = None;
+        self.still = None;
+    }
+
+    fn open(&mut self, path: &Path, ctx: &egui::Context) -> Result<(), String> {
+        self.close();
+        self.opened += 1;
+        if fits::is_fits(path) {
+            let image = FitsImage::open(path).map_err(|e| e.to_string())?;
+            let frame = image
+                .to_frame()
+                .ok_or("The FITS file is not a two dimensional image.")?;
+            self.still = Some(Still {
+                name: path
+                    .file_name()
+                    .map_or(String::new(), |name| name.to_string_lossy().to_string()),
+                frame: Arc::new(frame),
+                date: image.date(),
+            });
+        } else {
+            self.driver = Some(ReplayDriver::open(path, ctx.clone())?);
+        }
+        Ok(())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            if ui
+                .button("📂 Open…")
+                .on_hover_text("Replay a SER recording or show a FITS frame instead of the camera")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("SER or FITS", &["ser", "fits", "fit", "fts"])
+                    .pick_file()
+                {
+                    self.error = self.open(&path, ui.ctx()).err();
+                }
+            }
+            if self.is_active() && ui.button("⏹ Close").clicked() {
+                self.close();
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some(still) = &self.still {
+            ui.add_space(5.);
+            ui.label(&still.name);
+            ui.label(format!("{} × {}", still.frame.width, still.frame.height));
+            if let Some(date) = still.date {
+                ui.label(date.format("%Y-%m-%d %H:%M:%S UT").to_string());
+            }
+        }
+        let Some(driver) = &self.driver else {
+            return;
+        };
+        let status = driver.status().clone();
+        let total = status.header.frame_count;
+
+        ui.add_space(5.);
+        ui.label(&status.name);
+        ui.horizontal(|ui| {
+            if status.playing {
+                if ui.button("⏸").on_hover_text("Pause").clicked() {
+                    driver.send(ReplayCommand::Pause);
+                }
+            } else if ui.button("▶").on_hover_text("Play").clicked() {
+                driver.send(ReplayCommand::Play);
+            }
+            if ui.button("⏮").on_hover_text("First frame").clicked() {
+                driver.send(ReplayCommand::Seek(0));
+            }
+            if ui
+                .add_enabled(!status.playing, egui::Button::new("⏴"))
+                .on_hover_text("Previous frame")
+                .clicked()
+            {
+                driver.send(ReplayCommand::Seek(status.position.saturating_sub(1)));
+            }
+            if ui
+                .add_enabled(!status.playing, egui::Button::new("⏵"))
+                .on_hover_text("Next frame")
+                .clicked()
+            {
+                driver.send(ReplayCommand::Seek(status.position + 1));
+            }
+        });
+
+        let mut position = status.position + 1;
+        if ui
+            .add(egui::Slider::new(&mut position, 1..=total).text(format!("/ {}", total)))
+            .changed()
+        {
+            driver.send(ReplayCommand::Seek(position - 1));
+        }
+
+        egui::Grid::new("replay_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Speed");
+                let mut speed = status.speed;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut speed)
+                            .clamp_range(0.05..=20.)
+                            .speed(0.05)
+                            .suffix("×"),
+                    )
+                    .changed()
+                {
+                    driver.send(ReplayCommand::Speed(speed));
+                }
+                ui.end_row();
+
+                ui.label("Loop");
+                let mut looping = status.looping;
+                if ui.checkbox(&mut looping, "").changed() {
+                    driver.send(ReplayCommand::Looping(looping));
+                }
+                ui.end_row();
+
+                ui.label("Frame size");
+                ui.label(format!(
+                    "{} × {}, {} bit",
+                    status.header.width, status.header.height, status.header.pixel_depth
+                ));
+                ui.end_row();
+
+                if let Some(time) = status.time {
+                    ui.label("Time");
+                    ui.label(time.format("%Y-%m-%d %H:%M:%S%.3f UT").to_string());
+                    ui.end_row();
+                }
+            });
+        if let Some(error) = &status.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
+
+impl FrameSource for ReplayPanel {
+    fn latest(&self) -> (Option<Arc<Frame>>, Option<DateTime<Utc>>, u64) {
+        match (&self.driver, &self.still) {
+            (Some(driver), _) => driver.latest(),
+            (None, Some(still)) => (Some(still.frame.clone()), still.date, self.opened),
+            (None, None) => (None, None, 0),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use eframe::egui;
+use egui_plot::{Legend, Points};
+
+use crate::{
+    frame::Frame,
+    spectrum::{
+        dispersion::Dispersion,
+        line_fit::LineShape,
+        profile::SlitOrientation,
+        resolution::{self, BandResolution, ResolutionRecord},
+    },
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum ResolutionPlot {
+    Fwhm,
+    ResolvingPower,
+}
+
+/// Measures the width of isolated lines in several bands along the slit and keeps a history
+/// of the results.
+pub struct ResolutionPanel {
+    pub history: Vec<ResolutionRecord>,
+    /// Measure emission lines of a lamp instead of solar absorption lines.
+    emission: bool,
+    shape: LineShape,
+    half_window: usize,
+    /// Number of bands the slit is split into.
+    band_count: usize,
+    bands: Vec<BandResolution>,
+    frame: Option<Arc<Frame>>,
+    orientation: SlitOrientation,
+    plot: ResolutionPlot,
+    show_all: bool,
+    error: Option<String>,
+}
+
+impl ResolutionPanel {
+    pub fn new(history: Vec<ResolutionRecord>) -> Self {
+        Self {
+            history,
+            emission: false,
+            shape: LineShape::Gaussian,
+            half_window: 8,
+            band_count: 5,
+            bands: vec![],
+            frame: None,
+            orientation: SlitOrientation::Vertical,
+            plot: ResolutionPlot::Fwhm,
+            show_all: false,
+            error: None,
+        }
+    }
+
+    /// Feed a frame once corrected, the measurement is made on the latest one.
+    pub fn update(&mut self, frame: &Arc<Frame>, orientation: SlitOrientation) {
+        self.frame = Some(frame.clone());
+        self.orientation = orientation;
+    }
+
+    /// `configuration` describes the instrument setup the history is kept for.
+    pub fn ui(&mut self, ui: &mut egui::Ui, dispersion: Option<&Dispersion>, configuration: &str) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Lines");
+            ui.radio_value(&mut self.emission, false, "Solar");
+            ui.radio_value(&mut self.emission, true, "Lamp");
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Profile");
+            ui.radio_value(&mut self.shape, LineShape::Gaussian, "Gaussian");
+            ui.radio_value(&mut self.shape, LineShape::Voigt, "Voigt");
+            ui.add(
+                egui::DragValue::new(&mut self.half_window)
+                    .clamp_range(3..=100)
+                    .prefix("window ±")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.band_count)
+                    .clamp_range(1..=15)
+                    .suffix(" bands"),
+            )
+            .on_hover_text("Bands the slit is split into, measured separately");
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(self.frame.is_some(), |ui| {
+                if ui.button("Measure").clicked() {
+                    if let Some(frame) = &self.frame {
+                        self.bands = resolution::measure_bands(
+                            frame,
+                            self.orientation,
+                            self.band_count,
+                            self.emission,
+                            self.shape,
+                            self.half_window,
+                            dispersion,
+                        );
+                        self.error = self
+                            .bands
+                            .is_empty()
+                            .then(|| "No isolated line was fitted.".to_string());
+                    }
+                }
+            });
+            ui.add_enabled_ui(!self.bands.is_empty(), |ui| {
+                if ui
+                    .button("Save to history")
+                    .on_hover_text(configuration)
+                    .clicked()
+                {
+                    if let Some(record) =
+                        ResolutionRecord::new(&self.bands, self.shape, configuration.to_string())
+                    {
+                        self.history.push(record);
+                    }
+                }
+            });
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some(record) =
+            ResolutionRecord::new(&self.bands, self.shape, configuration.to_string())
+        {
+            ui.add_space(5.);
+            egui::Grid::new("resolution_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Lines");
+                    ui.label(record.lines.to_string());
+                    ui.end_row();
+                    ui.label("Median FWHM");
+                    ui.label(match record.fwhm_angstrom {
+                        Some(angstrom) => format!("{:.2} px  {:.3} Å", record.fwhm, angstrom),
+                        None => format!("{:.2} px", record.fwhm),
+                    });
+                    ui.end_row();
+                    if let Some(resolving_power) = record.resolving_power {
+                        ui.label("Median R");
+                        ui.label(format!("{:.0}", resolving_power));
+                        ui.end_row();
+                    }
+                    ui.label("Bands");
+                    ui.label(self.bands.len().to_string());
+                    ui.end_row();
+                    if let Some(spread) = record.spread {
+                        ui.label("FWHM spread along the slit")
+                            .on_hover_text("Range of the median FWHM of the bands");
+                        ui.label(format!("{:.2} px", spread));
+                        ui.end_row();
+                    }
+                });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.radio_value(&mut self.plot, ResolutionPlot::Fwhm, "FWHM");
+                ui.add_enabled_ui(dispersion.is_some(), |ui| {
+                    ui.radio_value(&mut self.plot, ResolutionPlot::ResolvingPower, "R");
+                });
+            });
+            egui_plot::Plot::new("resolution_plot")
+                .height(150.)
+                .legend(Legend::default())
+                .x_axis_label("Position [px]")
+                .y_axis_label(match self.plot {
+                    ResolutionPlot::Fwhm => "FWHM [px]",
+                    ResolutionPlot::ResolvingPower => "R",
+                })
+                .show(ui, |plot_ui| {
+                    for band in &self.bands {
+                        let points: Vec<[f64; 2]> = match self.plot {
+                            ResolutionPlot::Fwhm => band
+                                .lines
+                                .iter()
+                                .map(|line| [line.fit.centre, line.fit.fwhm])
+                                .collect(),
+                            ResolutionPlot::ResolvingPower => band
+                                .lines
+                                .iter()
+                                .filter_map(|line| Some([line.fit.centre, line.resolving_power()?]))
+                                .collect(),
+                        };
+                        plot_ui.points(
+                            Points::new(points)
+                                .radius(3.)
+                                .name(format!("Slit {:.0} px", band.slit)),
+                        );
+                    }
+                });
+
+            egui::CollapsingHeader::new("Lines")
+                .id_source("resolution_lines")
+                .show(ui, |ui| {
+                    egui::Grid::new("resolution_lines_grid")
+                        .num_columns(5)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Slit");
+                            ui.strong("Position");
+                            ui.strong("FWHM");
+                            ui.strong("R");
+                            ui.strong("RMS");
+                            ui.end_row();
+                            for (band, line) in self
+                                .bands
+                                .iter()
+                                .flat_map(|band| band.lines.iter().map(move |line| (band, line)))
+                            {
+                                let fit = &line.fit;
+                                ui.label(format!("{:.0}", band.slit));
+                                ui.label(format!("{:.2} ± {:.2}", fit.centre, fit.centre_error));
+                                let fwhm = format!("{:.2} ± {:.2} px", fit.fwhm, fit.fwhm_error);
+                                let response = ui.label(match line.fwhm_angstrom {
+                                    Some(angstrom) => format!("{}  {:.3} Å", fwhm, angstrom),
+                                    None => fwhm,
+                                });
+                                if self.shape == LineShape::Voigt {
+                                    response.on_hover_text(format!(
+                                        "Lorentzian fraction {:.2}",
+                                        fit.eta
+                                    ));
+                                }
+                                ui.label(
+                                    line.resolving_power()
+                                        .map(|r| format!("{:.0}", r))
+                                        .unwrap_or_default(),
+                                );
+                                ui.label(format!("{:.3}", fit.rms));
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+
+        ui.add_space(5.);
+        ui.horizontal(|ui| {
+            ui.strong("History");
+            ui.checkbox(&mut self.show_all, "All configurations");
+        });
+        if !self.show_all {
+            ui.label(egui::RichText::new(configuration).weak());
+        }
+        let mut remove = None;
+        egui::Grid::new("resolution_history")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, record) in self.history.iter().enumerate().rev() {
+                    if !self.show_all && record.configuration != configuration {
+                        continue;
+                    }
+                    ui.label(record.time.format("%Y-%m-%d %H:%M").to_string())
+                        .on_hover_text(&record.configuration);
+                    let fwhm = ui.label(format!("{:.2} px", record.fwhm));
+                    if let Some(spread) = record.spread {
+                        fwhm.on_hover_text(format!("{:.2} px spread along the slit", spread));
+                    }
+                    ui.label(
+                        record
+                            .fwhm_angstrom
+                            .map(|angstrom| format!("{:.3} Å", angstrom))
+                            .unwrap_or_default(),
+                    );
+                    ui.label(
+                        record
+                            .resolving_power
+                            .map(|r| format!("R {:.0}", r))
+                            .unwrap_or_default(),
+                    );
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = remove {
+            self.history.remove(i);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::{
+    frame::Frame,
+    spectrum::{
+        focus,
+        profile::{Profile, SlitOrientation},
+        smile::Smile,
+    },
+};
+
+use super::image_view::ImageOverlay;
+
+/// Measures the curvature of a spectral line along the slit and straightens the frames.
+pub struct SmilePanel {
+    pub smile: Option<Smile>,
+    /// Resample the live frames so that the lines are straight.
+    pub straighten: bool,
+    pub show_overlay: bool,
+    /// Measure on every frame.
+    live: bool,
+    /// Guessed core position of the measured line in pixels.
+    line: Option<f64>,
+    half_window: usize,
+    degree: usize,
+    band: usize,
+    frame: Option<Arc<Frame>>,
+    orientation: SlitOrientation,
+    error: Option<String>,
+}
+
+impl SmilePanel {
+    pub fn new(smile: Option<Smile>) -> Self {
+        Self {
+            line: smile.as_ref().map(|smile| smile.centre(smile.reference)),
+            smile,
+            straighten: false,
+            show_overlay: true,
+            live: false,
+            half_window: 15,
+            degree: 2,
+            band: 8,
+            frame: None,
+            orientation: SlitOrientation::Vertical,
+            error: None,
+        }
+    }
+
+    /// Feed a frame as delivered by the camera, before straightening.
+    pub fn update(&mut self, frame: &Arc<Frame>, orientation: SlitOrientation) {
+        self.frame = Some(frame.clone());
+        self.orientation = orientation;
+        if self.live {
+            self.measure();
+        }
+    }
+
+    fn measure(&mut self) {
+        let (Some(frame), Some(line)) = (&self.frame, self.line) else {
+            return;
+        };
+        match Smile::measure(
+            frame,
+            self.orientation,
+            line,
+            self.half_window,
+            self.degree,
+            self.band,
+        ) {
+            Ok(smile) => {
+                // follow the line when the spectrum drifts
+                self.line = Some(smile.centre(smile.reference));
+                self.smile = Some(smile);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// Smile the frames are straightened with, if straightening is enabled and applies to
+    /// frames in `orientation`.
+    pub fn correction(&self, orientation: SlitOrientation) -> Option<Smile> {
+        self.smile
+            .clone()
+            .filter(|smile| self.straighten && smile.orientation == orientation)
+    }
+
+    /// Fitted curve and measured points in image coordinates.
+    pub fn overlays(&self) -> Vec<ImageOverlay> {
+        let (Some(smile), Some(frame)) = (&self.smile, &self.frame) else {
+            return vec![];
+        };
+        if !self.show_overlay {
+            return vec![];
+        }
+        let (_, slit_len) = smile.orientation.dimensions(frame);
+        let to_image = |slit: f64, dispersion: f64| match smile.orientation {
+            SlitOrientation::Vertical => egui::pos2(dispersion as f32, slit as f32),
+            SlitOrientation::Horizontal => egui::pos2(slit as f32, dispersion as f32),
+        };
+        // once straightened the line runs at its reference position
+        let straightened = self.straighten && smile.orientation == self.orientation;
+        let curve = (0..=slit_len)
+            .step_by(4)
+            .map(|slit| {
+                let slit = slit as f64;
+                let centre = if straightened {
+                    smile.centre(smile.reference)
+                } else {
+                    smile.centre(slit)
+                };
+                to_image(slit, centre)
+            })
+            .collect();
+
+        let mut overlays = vec![ImageOverlay {
+            points: curve,
+            color: egui::Color32::from_rgb(0, 200, 255),
+            connected: true,
+        }];
+        if !straightened {
+            overlays.push(ImageOverlay {
+                points: smile
+                    .points
+                    .iter()
+                    .map(|(slit, centre)| to_image(*slit, *centre))
+                    .collect(),
+                color: egui::Color32::from_rgb(255, 200, 0),
+                connected: false,
+            });
+        }
+        overlays
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, profile: Option<&Profile>) {
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(profile.is_some(), |ui| {
+                if ui
+                    .button("Pick deepest line")
+                    .on_hover_text("Measure the curvature of the deepest absorption line")
+                    .clicked()
+                {
+                    if let Some(line) = profile.and_then(|p| focus::deepest_line(p, &[], 0)) {
+                        self.line = Some(line as f64);
+                        self.measure();
+                    }
+                }
+            });
+            ui.add_enabled_ui(self.line.is_some() && self.frame.is_some(), |ui| {
+                if ui.button("Measure").clicked() {
+                    self.measure();
+                }
+                ui.toggle_value(&mut self.live, "Live");
+            });
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.half_window)
+                    .clamp_range(3..=200)
+                    .prefix("window ±")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.band)
+                    .clamp_range(1..=100)
+                    .prefix("band ")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.degree)
+                    .clamp_range(1..=4)
+                    .prefix("degree "),
+            );
+        });
+
+        if let Some(line) = self.line {
+            ui.label(format!("Line at {:.1} px", line));
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some(smile) = &self.smile {
+            egui::Grid::new("smile_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, coefficient) in smile.coefficients.iter().enumerate() {
+                        ui.label(format!("c{}", i));
+                        ui.label(format!("{:.4e}", coefficient));
+                        ui.end_row();
+                    }
+                    if let Some(frame) = &self.frame {
+                        let (_, slit_len) = smile.orientation.dimensions(frame);
+                        ui.label("Sagitta");
+                        ui.label(format!("{:.2} px", smile.sagitta(slit_len)));
+                        ui.end_row();
+                    }
+                    ui.label("RMS");
+                    ui.label(format!("{:.3} px", smile.rms));
+                    ui.end_row();
+                    ui.label("Points");
+                    ui.label(smile.points.len().to_string());
+                    ui.end_row();
+                });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.show_overlay, "Show overlay");
+                ui.checkbox(&mut self.straighten, "Straighten frames");
+                if ui.button("Clear").clicked() {
+                    self.smile = None;
+                    self.straighten = false;
+                    self.live = false;
+                }
+            });
+        }
+    }
+}
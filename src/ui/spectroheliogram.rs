@@ -0,0 +1,1532 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use egui_plot::Line;
+
+use super::image_view::{ImageOverlay, ImageView};
+use crate::{
+    fits::{self, Acquisition, FitsError, FitsImage},
+    frame::Frame,
+    imaging::{
+        colormap::{self, Palette},
+        dark_flat::Masters,
+        enhancement::{Disk, Enhancement},
+        geometry::{self, Ellipse, Geometry},
+        line_maps::{self, LineMaps, LineModel},
+        presentation::{self, Presentation},
+        rgb_image::RgbImage,
+        solar_image::SolarImage,
+        spectroheliogram::{self, Extraction, ScanSnapshot, Spectroheliogram},
+        transversalium,
+    },
+    ser::{SerMetadata, SerReader},
+    spectrum::{
+        dispersion::Dispersion, lines::FRAUNHOFER, profile::SlitOrientation, smile::Smile,
+        tilt::Tilt,
+    },
+};
+
+/// Shortest time between two previews of a scan in progress.
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Line fitted at every pixel while a recorded scan is reconstructed.
+struct MapSettings {
+    model: LineModel,
+    half_window: usize,
+}
+
+/// Line maps filled by the worker threads of an offline job.
+struct MapJob {
+    maps: Arc<Mutex<LineMaps>>,
+    /// Frames fitted so far.
+    fitted: Arc<AtomicUsize>,
+}
+
+/// Reconstruction of a recorded scan on a worker thread, and the line fits on one
+/// thread per core when requested.
+struct OfflineJob {
+    name: String,
+    total: usize,
+    /// Start of the recording from the SER header.
+    date: Option<DateTime<Utc>>,
+    engine: Arc<Mutex<Spectroheliogram>>,
+    maps: Option<MapJob>,
+    cancel: Arc<AtomicBool>,
+    handle: JoinHandle<Result<(), String>>,
+}
+
+impl OfflineJob {
+    fn start(
+        path: &Path,
+        extraction: Extraction,
+        masters: Masters,
+        tilt: Option<Tilt>,
+        map_settings: Option<MapSettings>,
+    ) -> Result<Self, String> {
+        let reader = SerReader::open(path).map_err(|e| e.to_string())?;
+        let total = reader.header.frame_count;
+        let date = reader.header.date;
+        let engine = Arc::new(Mutex::new(Spectroheliogram::new(extraction.clone())));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let maps = map_settings.as_ref().map(|_| MapJob {
+            maps: Arc::new(Mutex::new(LineMaps::default())),
+            fitted: Arc::new(AtomicUsize::new(0)),
+        });
+
+        // frames are read once here and handed to the fitting workers
+        let (sender, workers) = match (&maps, map_settings) {
+            (Some(job), Some(settings)) => {
+                let count = thread::available_parallelism().map_or(1, |n| n.get());
+                let (sender, receiver) = mpsc::sync_channel::<(usize, Frame)>(2 * count);
+                let receiver = Arc::new(Mutex::new(receiver));
+                let workers: Vec<JoinHandle<()>> = (0..count)
+                    .map(|_| {
+                        let receiver = receiver.clone();
+                        let extraction = extraction.clone();
+                        let maps = job.maps.clone();
+                        let fitted = job.fitted.clone();
+                        let cancel = cancel.clone();
+                        thread::spawn(move || loop {
+                            let next = receiver.lock().unwrap().recv();
+                            let Ok((index, frame)) = next else {
+                                break;
+                            };
+                            if cancel.load(Ordering::Relaxed) {
+                                continue;
+                            }
+                            let column = line_maps::measure(
+                                &frame,
+                                &extraction,
+                                settings.half_window,
+                                settings.model,
+                            );
+                            maps.lock().unwrap().set_column(index, &column);
+                            fitted.fetch_add(1, Ordering::Relaxed);
+                        })
+                    })
+                    .collect();
+                (Some(sender), workers)
+            }
+            _ => (None, vec![]),
+        };
+
+        let handle = {
+            let engine = engine.clone();
+            let maps = maps.as_ref().map(|job| job.maps.clone());
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                let mut result = Ok(());
+                for index in 0..total {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let frame = match reader.frame(index) {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            result = Err(e.to_string());
+                            break;
+                        }
+                    };
+                    // the same corrections as the live frames the smile was measured on
+                    let frame = if masters.is_empty() {
+                        frame
+                    } else {
+                        match masters.correct(&frame) {
+                            Ok(frame) => frame,
+                            Err(e) => {
+                                result = Err(e.to_string());
+                                break;
+                            }
+                        }
+                    };
+                    let frame = match &tilt {
+                        Some(tilt) => tilt.correct(&frame),
+                        None => frame,
+                    };
+                    if let Err(e) = engine.lock().unwrap().push(&frame) {
+                        result = Err(e.to_string());
+                        break;
+                    }
+                    if let (Some(sender), Some(maps)) = (&sender, &maps) {
+                        if index == 0 {
+                            let (_, slit_len) = extraction.orientation.dimensions(&frame);
+                            *maps.lock().unwrap() = LineMaps::new(total, slit_len);
+                        }
+                        if sender.send((index, frame)).is_err() {
+                            break;
+                        }
+                    }
+                }
+                // the workers stop once the queue is empty
+                drop(sender);
+                for worker in workers {
+                    let _ = worker.join();
+                }
+                result
+            })
+        };
+        Ok(Self {
+            name: path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string()),
+            total,
+            date,
+            engine,
+            maps,
+            cancel,
+            handle,
+        })
+    }
+}
+
+/// Images built from one scan. The first four are read at their own offset, in this
+/// order, the Doppler image is computed from the wings and the last three are the line
+/// maps of a recorded scan.
+#[derive(Clone, Copy, PartialEq)]
+enum Product {
+    Core,
+    BlueWing,
+    RedWing,
+    Continuum,
+    Doppler,
+    Velocity,
+    LineWidth,
+    LineDepth,
+}
+
+impl Product {
+    fn is_map(self) -> bool {
+        matches!(
+            self,
+            Product::Velocity | Product::LineWidth | Product::LineDepth
+        )
+    }
+}
+
+/// Corrected image of the displayed product.
+enum Display {
+    Intensity(SolarImage),
+    /// Signed values with the magnitude shown at full colour, positive values in blue
+    /// when reversed.
+    Signed(SolarImage, f32, bool),
+}
+
+impl Display {
+    fn image(&self) -> &SolarImage {
+        match self {
+            Display::Intensity(image) | Display::Signed(image, ..) => image,
+        }
+    }
+}
+
+/// Corrections and presentation of the displayed image, taken by the processor.
+struct ProcessSettings {
+    product: Product,
+    remove_banding: bool,
+    banding_strength: f32,
+    show_banding: bool,
+    correct_geometry: bool,
+    manual_geometry: bool,
+    geometry: Geometry,
+    enhancement: Enhancement,
+    /// Å per pixel and wavelength of the scanned line, for the velocities.
+    scale: Option<(f64, f64)>,
+    presentation: Presentation,
+    palette: Palette,
+    scan_date: Option<DateTime<Utc>>,
+    line_name: String,
+    /// Instrument and observer of the labels.
+    metadata: SerMetadata,
+}
+
+struct ProcessRequest {
+    /// Changes with every scan, the images are assembled again when it or the number
+    /// of frames changes.
+    scan_id: u64,
+    scan: ScanSnapshot,
+    maps: Option<Arc<LineMaps>>,
+    settings: ProcessSettings,
+}
+
+struct Processed {
+    display: Display,
+    fit: Option<(Ellipse, Geometry, f64)>,
+    geometry_error: Option<String>,
+    disk: Option<Disk>,
+    banding: Vec<f32>,
+    legend: Option<String>,
+    /// Shown in the window, rendered when previewing the presentation.
+    frame: Frame,
+    overlays: Vec<ImageOverlay>,
+}
+
+/// Assembles the images of a scan and corrects them on a worker thread, dropping the
+/// requests it can not keep up with.
+struct Processor {
+    sender: Sender<ProcessRequest>,
+    processed: Arc<Mutex<Option<Processed>>>,
+}
+
+impl Processor {
+    fn new(ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let processed = Arc::new(Mutex::new(None));
+        let output = processed.clone();
+        thread::spawn(move || process_loop(receiver, &output, &ctx));
+        Self { sender, processed }
+    }
+
+    fn send(&self, request: ProcessRequest) {
+        let _ = self.sender.send(request);
+    }
+}
+
+fn process_loop(
+    receiver: Receiver<ProcessRequest>,
+    output: &Mutex<Option<Processed>>,
+    ctx: &egui::Context,
+) {
+    // images of the last request, kept while only the settings change
+    let mut assembled: Option<((u64, usize), Vec<SolarImage>)> = None;
+    while let Ok(mut request) = receiver.recv() {
+        while let Ok(newer) = receiver.try_recv() {
+            request = newer;
+        }
+        let key = (request.scan_id, request.scan.len());
+        let images = match assembled.take() {
+            Some((assembled_key, images)) if assembled_key == key => images,
+            _ => request.scan.images(),
+        };
+        let processed = process(&images, request.maps.as_deref(), &request.settings);
+        assembled = Some((key, images));
+        *output.lock().unwrap() = Some(processed);
+        ctx.request_repaint();
+    }
+}
+
+/// Banding removal and geometric correction of one image, with its banding. Ratios
+/// such as the line maps are not affected by the banding and are only `deband`ed
+/// when it is set.
+fn correct(
+    image: &SolarImage,
+    settings: &ProcessSettings,
+    geometry: Option<Geometry>,
+    centre: (f64, f64),
+    deband: bool,
+) -> (SolarImage, Vec<f32>) {
+    let banding = if deband {
+        transversalium::banding(image)
+    } else {
+        vec![]
+    };
+    let debanded;
+    let image = if deband && settings.remove_banding && !settings.show_banding {
+        debanded = transversalium::remove(image, &banding, settings.banding_strength);
+        &debanded
+    } else {
+        image
+    };
+    let corrected = match geometry.filter(|_| settings.correct_geometry) {
+        Some(geometry) => geometry.correct(image, centre),
+        None => image.clone(),
+    };
+    (corrected, banding)
+}
+
+/// Apply the corrections to the reconstructed images, in the order of `Product`, and
+/// prepare the selected one for display.
+fn process(
+    images: &[SolarImage],
+    maps: Option<&LineMaps>,
+    settings: &ProcessSettings,
+) -> Processed {
+    // the limb is sharpest in the continuum
+    let reference = &images[Product::Continuum as usize];
+    let ellipse = Ellipse::fit(&geometry::limb_points(reference));
+    let geometry_error = ellipse.as_ref().err().map(|e| e.to_string());
+    let fit = ellipse.ok().map(|ellipse| {
+        let (geometry, radius) = Geometry::from_ellipse(&ellipse);
+        (ellipse, geometry, radius)
+    });
+    // radius of the disk when it is corrected to the fitted circle
+    let corrected_radius = fit
+        .as_ref()
+        .filter(|_| settings.correct_geometry && !settings.manual_geometry)
+        .map(|(_, _, radius)| *radius);
+
+    let centre = fit.as_ref().map_or(
+        (reference.width as f64 / 2., reference.height as f64 / 2.),
+        |(ellipse, _, _)| ellipse.centre,
+    );
+    let geometry = match &fit {
+        _ if settings.manual_geometry => Some(settings.geometry),
+        Some((_, geometry, _)) => Some(*geometry),
+        None => None,
+    };
+
+    let mut banding = vec![];
+    let (display, legend) = match (settings.product, maps) {
+        (Product::Doppler, _) => {
+            let (red, red_banding) = correct(
+                &images[Product::RedWing as usize],
+                settings,
+                geometry,
+                centre,
+                true,
+            );
+            let (blue, _) = correct(
+                &images[Product::BlueWing as usize],
+                settings,
+                geometry,
+                centre,
+                true,
+            );
+            banding = red_banding;
+            let doppler = spectroheliogram::doppler(&red, &blue);
+            let limit = symmetric_limit(&doppler);
+            // blueshifts are positive, in blue with the reversed colours
+            (
+                Display::Signed(doppler, limit, true),
+                Some(format!("±{:.3}", limit)),
+            )
+        }
+        (product, Some(maps)) if product.is_map() => {
+            let map = match product {
+                Product::Velocity => match settings.scale {
+                    Some((angstrom_per_pixel, wavelength)) => {
+                        maps.velocity(angstrom_per_pixel, wavelength)
+                    }
+                    None => maps.relative_shift(),
+                },
+                Product::LineWidth => maps.fwhm.clone(),
+                _ => maps.depth.clone(),
+            };
+            let (image, _) = correct(&map, settings, geometry, centre, false);
+            if product == Product::Velocity {
+                let limit = symmetric_limit(&image);
+                let unit = if settings.scale.is_some() {
+                    "km/s"
+                } else {
+                    "px"
+                };
+                (
+                    Display::Signed(image, limit, false),
+                    Some(format!("±{:.2} {}, approaching in blue", limit, unit)),
+                )
+            } else {
+                (Display::Intensity(image), None)
+            }
+        }
+        (product, _) => {
+            // the line maps fall back to the core until they are fitted
+            let product = if product.is_map() {
+                Product::Core
+            } else {
+                product
+            };
+            let (image, image_banding) =
+                correct(&images[product as usize], settings, geometry, centre, true);
+            banding = image_banding;
+            let enhancement = &settings.enhancement;
+            let image = if enhancement.enabled {
+                let disk = corrected_radius.map(|radius| Disk {
+                    centre: ((image.width as f64 - 1.) / 2., centre.1),
+                    radius,
+                });
+                enhancement.apply(&image, disk)
+            } else {
+                image
+            };
+            (Display::Intensity(image), None)
+        }
+    };
+
+    // the fitted limb is a circle once corrected
+    let disk = corrected_radius.map(|radius| Disk {
+        centre: ((display.image().width as f64 - 1.) / 2., centre.1),
+        radius,
+    });
+    let (frame, overlays) = view(&display, disk, settings);
+    Processed {
+        display,
+        fit,
+        geometry_error,
+        disk,
+        banding,
+        legend,
+        frame,
+        overlays,
+    }
+}
+
+/// Colour, orientation and labels of the displayed image.
+fn render(display: &Display, settings: &ProcessSettings) -> RgbImage {
+    let presentation = &settings.presentation;
+    let (colored, dark_text) = match display {
+        Display::Intensity(image) => (
+            presentation.colorize(image, settings.palette),
+            presentation.negative,
+        ),
+        Display::Signed(image, limit, reversed) => {
+            (colormap::diverging_image(image, *limit, *reversed), true)
+        }
+    };
+    let mut image = presentation.orient(&colored, settings.scan_date);
+    if presentation.annotate {
+        let fonts = egui::FontDefinitions::default();
+        if let Some(font) = fonts.font_data.get("Ubuntu-Light") {
+            let labels =
+                presentation.labels(settings.scan_date, &settings.line_name, &settings.metadata);
+            let size = (image.height as f32 / 40.).max(14.);
+            let color = if dark_text { [0; 3] } else { [255; 3] };
+            image.draw_text(&font.font, &labels, size, color);
+        }
+    }
+    image
+}
+
+/// Frame shown in the window, rendered when previewing the presentation, and the limb
+/// drawn over it.
+fn view(
+    display: &Display,
+    disk: Option<Disk>,
+    settings: &ProcessSettings,
+) -> (Frame, Vec<ImageOverlay>) {
+    let (width, height) = (display.image().width, display.image().height);
+    let preview = settings.presentation.preview;
+    let frame = if preview {
+        render(display, settings).to_frame()
+    } else {
+        match display {
+            Display::Intensity(image) => image.to_frame(),
+            Display::Signed(image, limit, reversed) => {
+                colormap::diverging_image(image, *limit, *reversed).to_frame()
+            }
+        }
+    };
+
+    let limb = disk.map(|disk| {
+        let mut points = circle(disk.centre, disk.radius);
+        if preview {
+            for point in &mut points {
+                let (x, y) = settings.presentation.orient_point(
+                    (point.x as f64, point.y as f64),
+                    width,
+                    height,
+                    settings.scan_date,
+                );
+                *point = egui::pos2(x as f32, y as f32);
+            }
+        }
+        points
+    });
+    let overlays = limb
+        .map(|points| ImageOverlay {
+            points,
+            color: egui::Color32::from_rgb(0, 200, 255),
+            connected: true,
+        })
+        .into_iter()
+        .collect();
+    (frame, overlays)
+}
+
+/// Builds solar images from a slit scan, live or from a recorded SER video.
+pub struct SpectroheliogramPanel {
+    /// Wavelength calibration, for the side of the wings and the velocities.
+    pub dispersion: Option<Dispersion>,
+    /// Line core along the dispersion axis in pixels.
+    line: Option<f64>,
+    /// Offsets of the wings on each side of the core and of the continuum in pixels.
+    wing_offset: f64,
+    continuum_offset: f64,
+    width: usize,
+    follow_smile: bool,
+    /// Scan recorded from the live frames.
+    scan: Option<Spectroheliogram>,
+    recording: bool,
+    job: Option<OfflineJob>,
+    /// Frames in the displayed image.
+    shown: usize,
+    /// Columns of the displayed scan, assembled into images by the processor.
+    snapshot: ScanSnapshot,
+    /// Changes with every scan.
+    scan_id: u64,
+    processor: Processor,
+    /// Last time the images were handed to the processor.
+    last_preview: Instant,
+    product: Product,
+    /// Fit the line at every pixel of recorded scans.
+    fit_maps: bool,
+    map_model: LineModel,
+    map_half_window: usize,
+    maps: Option<Arc<LineMaps>>,
+    /// Frames in the displayed line maps.
+    fitted: usize,
+    /// Values at full colour of the displayed signed image.
+    legend: Option<String>,
+    /// Enhancement settings of each line, by the name from `line_name`.
+    pub enhancements: BTreeMap<String, Enhancement>,
+    pub presentation: Presentation,
+    /// Settings of the live frames, set every frame.
+    pub acquisition: Acquisition,
+    /// Dark and flat of the live frames, set every frame and applied to recorded files too.
+    pub masters: Masters,
+    /// Start of the displayed scan.
+    scan_date: Option<DateTime<Utc>>,
+    /// Camera settings of the displayed scan, unknown for recordings.
+    scan_settings: Acquisition,
+    display: Option<Display>,
+    /// Disk of the displayed image when it is corrected to a circle.
+    disk: Option<Disk>,
+    export_status: Option<Result<String, String>>,
+    remove_banding: bool,
+    banding_strength: f32,
+    /// Display the image with its banding to compare.
+    show_banding: bool,
+    /// Measured multiplicative pattern of each row.
+    banding: Vec<f32>,
+    correct_geometry: bool,
+    /// Use `geometry` instead of the fitted limb, for partial disks.
+    manual_geometry: bool,
+    geometry: Geometry,
+    /// Fitted limb, the geometry it gives and the radius of the corrected disk.
+    fit: Option<(Ellipse, Geometry, f64)>,
+    geometry_error: Option<String>,
+    view: ImageView,
+    show_window: bool,
+    error: Option<String>,
+}
+
+impl SpectroheliogramPanel {
+    pub fn new(
+        ctx: egui::Context,
+        enhancements: BTreeMap<String, Enhancement>,
+        presentation: Presentation,
+    ) -> Self {
+        Self {
+            dispersion: None,
+            line: None,
+            wing_offset: 3.,
+            continuum_offset: 20.,
+            width: 1,
+            follow_smile: true,
+            scan: None,
+            recording: false,
+            job: None,
+            shown: 0,
+            snapshot: ScanSnapshot::default(),
+            scan_id: 0,
+            processor: Processor::new(ctx.clone()),
+            last_preview: Instant::now(),
+            product: Product::Core,
+            fit_maps: false,
+            map_model: LineModel::Parabola,
+            map_half_window: 8,
+            maps: None,
+            fitted: 0,
+            legend: None,
+            enhancements,
+            presentation,
+            acquisition: Acquisition::default(),
+            masters: Masters::default(),
+            scan_date: None,
+            scan_settings: Acquisition::default(),
+            display: None,
+            disk: None,
+            export_status: None,
+            remove_banding: true,
+            banding_strength: 1.,
+            show_banding: false,
+            banding: vec![],
+            correct_geometry: true,
+            manual_geometry: false,
+            geometry: Geometry::default(),
+            fit: None,
+            geometry_error: None,
+            view: ImageView::new(ctx),
+            show_window: false,
+            error: None,
+        }
+    }
+
+    fn extraction(
+        &self,
+        line: f64,
+        orientation: SlitOrientation,
+        smile: Option<&Smile>,
+    ) -> Extraction {
+        // the red wing is at negative offsets when the wavelength decreases along the axis
+        let reversed = self
+            .dispersion
+            .as_ref()
+            .is_some_and(|dispersion| dispersion.angstrom_per_pixel(line) < 0.);
+        let wing = if reversed {
+            -self.wing_offset
+        } else {
+            self.wing_offset
+        };
+        Extraction {
+            orientation,
+            line,
+            offsets: vec![0., -wing, wing, self.continuum_offset],
+            width: self.width,
+            smile: smile.filter(|_| self.follow_smile).cloned(),
+        }
+    }
+
+    /// Feed a live frame after the geometric corrections.
+    pub fn update(&mut self, frame: &Frame) {
+        let Some(scan) = self.scan.as_mut().filter(|_| self.recording) else {
+            return;
+        };
+        if let Err(e) = scan.push(frame) {
+            self.recording = false;
+            self.error = Some(e.to_string());
+        }
+        // the images are assembled and corrected a few times a second
+        if !self.recording || self.last_preview.elapsed() >= PREVIEW_INTERVAL {
+            let snapshot = scan.snapshot();
+            self.show(snapshot);
+        }
+    }
+
+    fn show(&mut self, snapshot: ScanSnapshot) {
+        self.shown = snapshot.len();
+        self.snapshot = snapshot;
+        self.process();
+    }
+
+    /// Corrections and presentation of the displayed image.
+    fn settings(&self) -> ProcessSettings {
+        ProcessSettings {
+            product: self.product,
+            remove_banding: self.remove_banding,
+            banding_strength: self.banding_strength,
+            show_banding: self.show_banding,
+            correct_geometry: self.correct_geometry,
+            manual_geometry: self.manual_geometry,
+            geometry: self.geometry,
+            enhancement: self.enhancement(),
+            scale: self
+                .dispersion
+                .as_ref()
+                .zip(self.line)
+                .map(|(dispersion, line)| {
+                    (
+                        dispersion.angstrom_per_pixel(line),
+                        dispersion.wavelength(line),
+                    )
+                }),
+            presentation: self.presentation.clone(),
+            palette: self.palette(),
+            scan_date: self.scan_date,
+            line_name: self.line_name(),
+            metadata: self.acquisition.metadata.clone(),
+        }
+    }
+
+    /// Hand the displayed scan to the processor, the result is picked up by
+    /// `poll_processed`.
+    fn process(&mut self) {
+        if self.product.is_map() && self.maps.is_none() {
+            self.product = Product::Core;
+        }
+        if self.snapshot.is_empty() {
+            return;
+        }
+        self.last_preview = Instant::now();
+        self.processor.send(ProcessRequest {
+            scan_id: self.scan_id,
+            scan: self.snapshot.clone(),
+            maps: self.maps.clone(),
+            settings: self.settings(),
+        });
+    }
+
+    fn poll_processed(&mut self) {
+        let Some(processed) = self.processor.processed.lock().unwrap().take() else {
+            return;
+        };
+        self.display = Some(processed.display);
+        self.fit = processed.fit;
+        self.geometry_error = processed.geometry_error;
+        self.disk = processed.disk;
+        self.banding = processed.banding;
+        self.legend = processed.legend;
+        self.view.overlays = processed.overlays;
+        self.view.update(Arc::new(processed.frame));
+    }
+
+    /// Colour, orientation and labels of the displayed image.
+    fn render(&self) -> Option<RgbImage> {
+        Some(render(self.display.as_ref()?, &self.settings()))
+    }
+
+    /// Write the values of the displayed image, without the colours and orientation of
+    /// the presentation.
+    fn export_fits(&self, path: &Path) -> Result<(), FitsError> {
+        let Some(display) = &self.display else {
+            return Ok(());
+        };
+        let image = display.image();
+        let mut fits = FitsImage::from_solar_image(image);
+        Acquisition {
+            date: self.scan_date,
+            wavelength: self.wavelength(),
+            metadata: self.acquisition.metadata.clone(),
+            ..self.scan_settings.clone()
+        }
+        .write(&mut fits.header);
+        fits.header.set("OBJECT", "Sun", "");
+        if self.product == Product::Velocity {
+            let unit = if self.wavelength().is_some() {
+                "km/s"
+            } else {
+                "pixel"
+            };
+            fits.header.set("BUNIT", unit, "Line of sight velocity");
+        }
+        if let Some(disk) = self.disk {
+            // FITS pixels count from 1 with the rows from the bottom
+            fits.header
+                .set("CENTER_X", disk.centre.0 + 1., "[pixel] Centre of the disk");
+            fits.header.set(
+                "CENTER_Y",
+                image.height as f64 - disk.centre.1,
+                "[pixel] Centre of the disk",
+            );
+            fits.header
+                .set("SOLAR_R", disk.radius, "[pixel] Radius of the disk");
+        }
+        fits.save(path)
+    }
+
+    /// Radius of the disk when it is corrected to the fitted circle.
+    fn corrected_radius(&self) -> Option<f64> {
+        self.fit
+            .as_ref()
+            .filter(|_| self.correct_geometry && !self.manual_geometry)
+            .map(|(_, _, radius)| *radius)
+    }
+
+    /// Name of the scanned line the enhancement settings are kept for, the nearest
+    /// Fraunhofer line when calibrated.
+    fn line_name(&self) -> String {
+        let Some(wavelength) = self.wavelength() else {
+            return "Uncalibrated".to_string();
+        };
+        FRAUNHOFER
+            .iter()
+            .map(|fraunhofer| fraunhofer.line)
+            .filter(|line| (line.wavelength - wavelength).abs() < 2.)
+            .min_by(|a, b| {
+                (a.wavelength - wavelength)
+                    .abs()
+                    .total_cmp(&(b.wavelength - wavelength).abs())
+            })
+            .map_or(format!("{:.0} Å", wavelength), |line| {
+                format!("{} {:.2}", line.name, line.wavelength)
+            })
+    }
+
+    /// Wavelength of the scanned line in Å.
+    fn wavelength(&self) -> Option<f64> {
+        self.dispersion
+            .as_ref()
+            .zip(self.line)
+            .map(|(dispersion, line)| dispersion.wavelength(line))
+    }
+
+    /// Chosen palette or the preset of the line.
+    fn palette(&self) -> Palette {
+        self.presentation.palette.unwrap_or_else(|| {
+            self.wavelength()
+                .map_or(Palette::Grey, Palette::for_wavelength)
+        })
+    }
+
+    fn enhancement(&self) -> Enhancement {
+        self.enhancements
+            .get(&self.line_name())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn enhancement_ui(&mut self, ui: &mut egui::Ui) {
+        let name = self.line_name();
+        let has_disk = self.corrected_radius().is_some();
+        let enhancement = self.enhancements.entry(name.clone()).or_default();
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            changed |= ui.checkbox(&mut enhancement.enabled, "Enhance").changed();
+            ui.weak(format!("settings of {}", name));
+        });
+        ui.add_enabled_ui(enhancement.enabled, |ui| {
+            if !has_disk {
+                ui.label("The radial steps need the limb fit and the corrected geometry.");
+            }
+            ui.add_enabled_ui(has_disk, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    changed |= ui
+                        .checkbox(&mut enhancement.coronagraph, "Coronagraph")
+                        .on_hover_text("Mask the disk to show the prominences alone")
+                        .changed();
+                    ui.add_enabled_ui(enhancement.coronagraph, |ui| {
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut enhancement.mask_radius)
+                                    .clamp_range(0.9..=1.2)
+                                    .speed(0.001)
+                                    .prefix("mask ")
+                                    .suffix(" R"),
+                            )
+                            .changed();
+                    });
+                });
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut enhancement.limb_darkening, 0. ..=1.)
+                            .text("limb darkening"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut enhancement.prominence_gain, 1. ..=50.)
+                            .logarithmic(true)
+                            .text("prominence gain"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut enhancement.prominence_gamma, 0.2..=2.)
+                            .text("prominence gamma"),
+                    )
+                    .changed();
+            });
+            changed |= ui
+                .add(egui::Slider::new(&mut enhancement.disk_gamma, 0.2..=2.).text("disk gamma"))
+                .changed();
+            ui.horizontal_wrapped(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut enhancement.sharpen)
+                            .clamp_range(0. ..=5.)
+                            .speed(0.01)
+                            .prefix("sharpen "),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut enhancement.sharpen_radius)
+                            .clamp_range(0.5..=20.)
+                            .speed(0.05)
+                            .prefix("σ ")
+                            .suffix(" px"),
+                    )
+                    .changed();
+            });
+            ui.horizontal_wrapped(|ui| {
+                changed |= ui
+                    .checkbox(&mut enhancement.clahe, "CLAHE")
+                    .on_hover_text("Contrast limited adaptive histogram equalization")
+                    .changed();
+                ui.add_enabled_ui(enhancement.clahe, |ui| {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut enhancement.clahe_tiles)
+                                .clamp_range(1..=32)
+                                .prefix("tiles "),
+                        )
+                        .changed();
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut enhancement.clahe_clip)
+                                .clamp_range(1. ..=10.)
+                                .speed(0.05)
+                                .prefix("clip "),
+                        )
+                        .changed();
+                });
+            });
+            if ui.button("Reset").clicked() {
+                *enhancement = Enhancement {
+                    enabled: true,
+                    ..Enhancement::default()
+                };
+                changed = true;
+            }
+        });
+        if changed {
+            self.process();
+        }
+    }
+
+    fn presentation_ui(&mut self, ui: &mut egui::Ui) {
+        let preset = self
+            .wavelength()
+            .map_or(Palette::Grey, Palette::for_wavelength);
+        let p_angle = self.scan_date.map(presentation::p_angle);
+        let presentation = &mut self.presentation;
+        let mut changed = false;
+        changed |= ui
+            .checkbox(&mut presentation.preview, "Preview")
+            .on_hover_text("Show the image as it is exported")
+            .changed();
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_id_source("palette")
+                .selected_text(match presentation.palette {
+                    Some(palette) => palette.name().to_string(),
+                    None => format!("Line preset ({})", preset.name()),
+                })
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut presentation.palette, None, "Line preset")
+                        .changed();
+                    for palette in Palette::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut presentation.palette,
+                                Some(palette),
+                                palette.name(),
+                            )
+                            .changed();
+                    }
+                });
+            changed |= ui
+                .checkbox(&mut presentation.negative, "Negative")
+                .changed();
+        });
+        changed |= ui
+            .add(egui::Slider::new(&mut presentation.gamma, 0.3..=3.).text("gamma"))
+            .changed();
+        ui.horizontal_wrapped(|ui| {
+            changed |= ui
+                .checkbox(&mut presentation.flip_horizontal, "Flip ↔")
+                .changed();
+            changed |= ui
+                .checkbox(&mut presentation.flip_vertical, "Flip ↕")
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut presentation.rotation)
+                        .clamp_range(-180. ..=180.)
+                        .speed(0.1)
+                        .prefix("rotation ")
+                        .suffix("°"),
+                )
+                .changed();
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(p_angle.is_some(), |ui| {
+                changed |= ui
+                    .checkbox(&mut presentation.solar_north, "Solar north up")
+                    .on_hover_text(
+                        "Rotate by the P angle of the scan date, for images with the \
+                         celestial north up",
+                    )
+                    .changed();
+            });
+            if let Some(p_angle) = p_angle {
+                ui.label(format!("P {:+.2}°", p_angle));
+            }
+        });
+        changed |= ui
+            .checkbox(&mut presentation.annotate, "Date, line and observer text")
+            .changed();
+        if presentation.annotate {
+            ui.weak("Instrument and observer from the Recording section");
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(self.display.is_some(), |ui| {
+                if ui.button("Export PNG…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("PNG", &["png"])
+                        .set_file_name("spectroheliogram.png")
+                        .save_file()
+                    {
+                        self.export_status = self.render().map(|image| {
+                            image
+                                .save_png(&path)
+                                .map(|_| format!("Saved {}", path.display()))
+                                .map_err(|e| e.to_string())
+                        });
+                    }
+                }
+                if ui
+                    .button("Export FITS…")
+                    .on_hover_text("Save the values for further processing, as they were scanned")
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("FITS", &fits::EXTENSIONS)
+                        .set_file_name("spectroheliogram.fits")
+                        .save_file()
+                    {
+                        self.export_status = Some(
+                            self.export_fits(&path)
+                                .map(|_| format!("Saved {}", path.display()))
+                                .map_err(|e| e.to_string()),
+                        );
+                    }
+                }
+            });
+            match &self.export_status {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                None => {}
+            }
+        });
+        if changed {
+            self.process();
+        }
+    }
+
+    fn product_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            for (product, name) in [
+                (Product::Core, "Core"),
+                (Product::BlueWing, "Blue wing"),
+                (Product::RedWing, "Red wing"),
+                (Product::Continuum, "Continuum"),
+                (Product::Doppler, "Doppler"),
+            ] {
+                changed |= ui.radio_value(&mut self.product, product, name).changed();
+            }
+        });
+        if self.maps.is_some() {
+            ui.horizontal_wrapped(|ui| {
+                for (product, name) in [
+                    (Product::Velocity, "Velocity"),
+                    (Product::LineWidth, "Line width"),
+                    (Product::LineDepth, "Line depth"),
+                ] {
+                    changed |= ui.radio_value(&mut self.product, product, name).changed();
+                }
+            });
+        }
+        if self.product == Product::Doppler {
+            ui.label("(red − blue) / (red + blue), blueshifts in blue");
+        }
+        if let Some(legend) = &self.legend {
+            ui.label(format!("Full colour at {}", legend));
+        }
+        if changed {
+            self.process();
+        }
+    }
+
+    fn banding_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            changed |= ui
+                .checkbox(&mut self.remove_banding, "Remove banding")
+                .on_hover_text("Streaks along the scan from dust on the slit")
+                .changed();
+            ui.add_enabled_ui(self.remove_banding, |ui| {
+                changed |= ui
+                    .add(egui::Slider::new(&mut self.banding_strength, 0. ..=1.).text("strength"))
+                    .changed();
+                changed |= ui
+                    .toggle_value(&mut self.show_banding, "Before")
+                    .on_hover_text("Show the image with its banding")
+                    .changed();
+            });
+        });
+        if !self.banding.is_empty() {
+            egui_plot::Plot::new("banding_plot")
+                .height(100.)
+                .x_axis_label("Slit position [px]")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        Line::new(
+                            self.banding
+                                .iter()
+                                .enumerate()
+                                .map(|(y, pattern)| [y as f64, *pattern as f64])
+                                .collect::<Vec<_>>(),
+                        )
+                        .name("Banding"),
+                    );
+                });
+        }
+        if changed {
+            self.process();
+        }
+    }
+
+    fn geometry_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            changed |= ui
+                .checkbox(&mut self.correct_geometry, "Correct geometry")
+                .changed();
+            ui.add_enabled_ui(self.correct_geometry, |ui| {
+                changed |= ui
+                    .radio_value(&mut self.manual_geometry, false, "Limb fit")
+                    .changed();
+                changed |= ui
+                    .radio_value(&mut self.manual_geometry, true, "Manual")
+                    .changed();
+            });
+        });
+        match &self.fit {
+            Some((_, geometry, radius)) => {
+                ui.label(format!(
+                    "Fitted tilt {:.2}°, X/Y ratio {:.3}, radius {:.1} px",
+                    geometry.tilt, geometry.ratio, radius
+                ));
+            }
+            None => {
+                if let Some(error) = &self.geometry_error {
+                    ui.colored_label(ui.visuals().warn_fg_color, error);
+                }
+            }
+        }
+        if self.correct_geometry && self.manual_geometry {
+            ui.horizontal_wrapped(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.geometry.tilt)
+                            .clamp_range(-45. ..=45.)
+                            .speed(0.01)
+                            .prefix("tilt ")
+                            .suffix("°"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.geometry.ratio)
+                            .clamp_range(0.1..=10.)
+                            .speed(0.001)
+                            .prefix("X/Y "),
+                    )
+                    .changed();
+                ui.add_enabled_ui(self.fit.is_some(), |ui| {
+                    if ui.button("Use fit").clicked() {
+                        if let Some((_, geometry, _)) = &self.fit {
+                            self.geometry = *geometry;
+                            changed = true;
+                        }
+                    }
+                });
+            });
+        }
+        if changed {
+            self.process();
+        }
+    }
+
+    /// Show the reconstructed image in its own window.
+    pub fn window(&mut self, ctx: &egui::Context) {
+        self.poll_job();
+        self.poll_processed();
+        if self.job.is_some() {
+            ctx.request_repaint();
+        }
+        let view = &mut self.view;
+        egui::Window::new("Spectroheliogram")
+            .open(&mut self.show_window)
+            .default_size([600., 600.])
+            .show(ctx, |ui| view.ui(ui));
+    }
+
+    fn poll_job(&mut self) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        // checked first so that the last frames are shown
+        let finished = job.handle.is_finished();
+        if !finished && self.last_preview.elapsed() < PREVIEW_INTERVAL {
+            return;
+        }
+        // the lock is only held to take the columns, the processor assembles them
+        let snapshot = job.engine.lock().unwrap().snapshot();
+        let maps = job
+            .maps
+            .as_ref()
+            .map(|maps| (maps.maps.clone(), maps.fitted.load(Ordering::Relaxed)));
+        let mut changed = false;
+        if let Some((maps, fitted)) = maps {
+            if fitted != self.fitted {
+                self.fitted = fitted;
+                self.maps = Some(Arc::new(maps.lock().unwrap().clone()));
+                changed = self.product.is_map();
+            }
+        }
+        if snapshot.len() != self.shown {
+            self.show(snapshot);
+        } else if changed {
+            self.process();
+        }
+
+        if finished {
+            if let Some(job) = self.job.take() {
+                self.error = match job.handle.join() {
+                    Ok(result) => result.err(),
+                    Err(_) => Some("The reconstruction stopped unexpectedly.".to_string()),
+                };
+            }
+        }
+    }
+
+    /// `selected` is the pixel of the line clicked in the spectrum plot, `smile` the
+    /// measured curvature, `straightened` whether the live frames are already straight,
+    /// and `tilt` the rotation applied to the live frames, applied to recorded files too
+    /// after [`Self::masters`].
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        orientation: SlitOrientation,
+        selected: Option<f64>,
+        smile: Option<&Smile>,
+        straightened: bool,
+        tilt: Option<&Tilt>,
+    ) {
+        if self.line.is_none() {
+            self.line = smile.map(|smile| smile.centre(smile.reference));
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(selected.is_some(), |ui| {
+                if ui
+                    .button("Use selected line")
+                    .on_hover_text("Line clicked in the spectrum plot")
+                    .clicked()
+                {
+                    self.line = selected;
+                }
+            });
+            ui.add_enabled_ui(smile.is_some(), |ui| {
+                if ui.button("Use smile line").clicked() {
+                    self.line = smile.map(|smile| smile.centre(smile.reference));
+                }
+            });
+        });
+        match self.line {
+            Some(line) => ui.label(format!("Line at {:.2} px", line)),
+            None => ui.label("Select a line in the spectrum plot."),
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.wing_offset)
+                    .clamp_range(0. ..=100.)
+                    .speed(0.1)
+                    .prefix("wings ±")
+                    .suffix(" px"),
+            )
+            .on_hover_text("Offset of the blue and red wings from the core");
+            ui.add(
+                egui::DragValue::new(&mut self.continuum_offset)
+                    .speed(0.1)
+                    .prefix("continuum ")
+                    .suffix(" px"),
+            )
+            .on_hover_text("Offset of a line free part of the spectrum");
+            ui.add(
+                egui::DragValue::new(&mut self.width)
+                    .clamp_range(1..=50)
+                    .prefix("width ")
+                    .suffix(" px"),
+            );
+            ui.add_enabled_ui(smile.is_some(), |ui| {
+                ui.checkbox(&mut self.follow_smile, "Follow smile");
+            });
+        });
+
+        let busy = self.recording || self.job.is_some();
+        ui.add_enabled_ui(!busy, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.fit_maps, "Line maps").on_hover_text(
+                    "Fit the line at every pixel of recorded scans for velocity, \
+                         width and depth maps",
+                );
+                ui.add_enabled_ui(self.fit_maps, |ui| {
+                    ui.radio_value(&mut self.map_model, LineModel::Parabola, "Parabola");
+                    ui.radio_value(&mut self.map_model, LineModel::Gaussian, "Gaussian");
+                    ui.add(
+                        egui::DragValue::new(&mut self.map_half_window)
+                            .clamp_range(3..=50)
+                            .prefix("window ±")
+                            .suffix(" px"),
+                    );
+                });
+            });
+        });
+        ui.add_space(5.);
+        ui.horizontal_wrapped(|ui| {
+            if self.recording {
+                if ui.button("⏹ Stop scan").clicked() {
+                    self.recording = false;
+                    if let Some(scan) = &self.scan {
+                        let snapshot = scan.snapshot();
+                        self.show(snapshot);
+                    }
+                }
+            } else {
+                ui.add_enabled_ui(self.line.is_some() && !busy, |ui| {
+                    if ui
+                        .button("⏺ Start scan")
+                        .on_hover_text("Add a column for every live frame")
+                        .clicked()
+                    {
+                        if let Some(line) = self.line {
+                            // straightened frames no longer need the curve
+                            let smile = smile.filter(|_| !straightened);
+                            self.scan = Some(Spectroheliogram::new(self.extraction(
+                                line,
+                                orientation,
+                                smile,
+                            )));
+                            self.recording = true;
+                            self.scan_id += 1;
+                            self.scan_date = Some(Utc::now());
+                            self.scan_settings = self.acquisition.clone();
+                            self.maps = None;
+                            self.show_window = true;
+                            self.error = None;
+                        }
+                    }
+                });
+            }
+
+            ui.add_enabled_ui(self.line.is_some() && !busy, |ui| {
+                if ui
+                    .button("Open SER…")
+                    .on_hover_text(
+                        "Reconstruct a recorded scan with the dark, flat and derotation \
+                        of the live frames",
+                    )
+                    .clicked()
+                {
+                    if let (Some(path), Some(line)) = (
+                        rfd::FileDialog::new()
+                            .add_filter("SER", &["ser"])
+                            .pick_file(),
+                        self.line,
+                    ) {
+                        let extraction = self.extraction(line, orientation, smile);
+                        let map_settings = self.fit_maps.then_some(MapSettings {
+                            model: self.map_model,
+                            half_window: self.map_half_window,
+                        });
+                        match OfflineJob::start(
+                            &path,
+                            extraction,
+                            self.masters.clone(),
+                            tilt.cloned(),
+                            map_settings,
+                        ) {
+                            Ok(job) => {
+                                self.scan_date = job.date;
+                                self.scan_settings = Acquisition::default();
+                                self.job = Some(job);
+                                self.scan = None;
+                                self.scan_id += 1;
+                                self.shown = 0;
+                                self.maps = None;
+                                self.fitted = 0;
+                                self.show_window = true;
+                                self.error = None;
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                }
+            });
+            if ui.button("Show image").clicked() {
+                self.show_window = true;
+            }
+        });
+
+        if let Some(job) = &self.job {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(self.shown as f32 / job.total.max(1) as f32)
+                        .text(format!("{} {}/{}", job.name, self.shown, job.total))
+                        .desired_width(200.),
+                );
+                if ui.button("Cancel").clicked() {
+                    job.cancel.store(true, Ordering::Relaxed);
+                }
+            });
+            if job.maps.is_some() {
+                ui.add(
+                    egui::ProgressBar::new(self.fitted as f32 / job.total.max(1) as f32)
+                        .text(format!("Line fits {}/{}", self.fitted, job.total))
+                        .desired_width(200.),
+                );
+            }
+        } else if let Some(scan) = self.scan.as_ref().filter(|_| self.recording) {
+            if scan.is_empty() {
+                ui.label("Recording, waiting for frames");
+            } else {
+                ui.label(format!("Recording, {} frames", scan.len()));
+            }
+        } else if self.shown > 0 {
+            ui.label(format!("{} frames", self.shown));
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.add_space(5.);
+        ui.strong("Image");
+        self.product_ui(ui);
+
+        ui.add_space(5.);
+        ui.strong("Transversalium");
+        self.banding_ui(ui);
+
+        ui.add_space(5.);
+        ui.strong("Geometry");
+        self.geometry_ui(ui);
+
+        ui.add_space(5.);
+        ui.strong("Enhancement");
+        self.enhancement_ui(ui);
+
+        ui.add_space(5.);
+        ui.strong("Presentation");
+        self.presentation_ui(ui);
+    }
+}
+
+/// Magnitude of a signed image at its 99th percentile, so that a few outliers do not
+/// wash out the colours.
+fn symmetric_limit(image: &SolarImage) -> f32 {
+    let mut magnitude = image.clone();
+    for value in &mut magnitude.values {
+        *value = value.abs();
+    }
+    magnitude.percentile(0.99)
+}
+
+fn circle(centre: (f64, f64), radius: f64) -> Vec<egui::Pos2> {
+    (0..=90)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::TAU / 90.;
+            egui::pos2(
+                (centre.0 + radius * angle.cos()) as f32,
+                (centre.1 + radius * angle.sin()) as f32,
+            )
+        })
+        .collect()
+}
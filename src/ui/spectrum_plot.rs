@@ -0,0 +1,357 @@
+use std::{fs::File, io::Write, path::Path};
+
+use eframe::egui;
+use egui_plot::{Legend, Line, PlotPoint, PlotPoints, Text, VLine};
+
+use crate::{
+    fits::{self, Acquisition, FitsError, FitsImage},
+    frame::Frame,
+    spectrum::{
+        dispersion::Dispersion,
+        identification::Identification,
+        profile::{Profile, SlitOrientation},
+    },
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpectrumAxis {
+    Pixels,
+    Wavelength,
+}
+
+/// Spectrum profile extracted from the live frames, shown in the bottom panel.
+pub struct SpectrumPlot {
+    pub orientation: SlitOrientation,
+    /// First position along the slit of the averaged band.
+    pub band_start: usize,
+    pub band_height: usize,
+    pub axis: SpectrumAxis,
+    pub frozen: bool,
+    pub live: Option<Profile>,
+    /// Pixel of the line clicked in the plot.
+    pub selected: Option<f64>,
+    /// Continuum or instrument response the profiles are divided by.
+    pub divisor: Option<Vec<f64>>,
+    /// Identified lines labelled on the live profile.
+    pub identifications: Vec<Identification>,
+    /// Settings of the live frames for the FITS header.
+    pub acquisition: Acquisition,
+    overlays: Vec<Profile>,
+    reference: Option<Profile>,
+    compare: bool,
+    file_error: Option<String>,
+}
+
+impl SpectrumPlot {
+    pub fn new() -> Self {
+        Self {
+            orientation: SlitOrientation::Vertical,
+            band_start: 0,
+            band_height: 0,
+            axis: SpectrumAxis::Pixels,
+            frozen: false,
+            live: None,
+            selected: None,
+            divisor: None,
+            identifications: vec![],
+            acquisition: Acquisition::default(),
+            overlays: vec![],
+            reference: None,
+            compare: false,
+            file_error: None,
+        }
+    }
+
+    pub fn update(&mut self, frame: &Frame) {
+        if self.frozen {
+            return;
+        }
+
+        let (_, slit_len) = self.orientation.dimensions(frame);
+        if self.band_height == 0 || self.band_start + self.band_height > slit_len {
+            // default to a band of 5% of the slit around its centre
+            self.band_height = (slit_len / 20).max(1);
+            self.band_start = (slit_len - self.band_height) / 2;
+        }
+
+        self.live = Some(Profile::extract(
+            frame,
+            self.orientation,
+            self.band_start..=self.band_start + self.band_height - 1,
+        ));
+    }
+
+    fn points(&self, profile: &Profile, dispersion: Option<&Dispersion>) -> PlotPoints {
+        let reference = self
+            .reference
+            .as_ref()
+            .filter(|reference| self.compare && reference.values.len() == profile.values.len());
+        let divisor = self
+            .divisor
+            .as_ref()
+            .filter(|divisor| divisor.len() == profile.values.len());
+        // references may be normalized to 1 as well as in ADU, only their empty pixels
+        // are floored
+        let floor = reference.map_or(f64::MIN_POSITIVE, |reference| {
+            (reference.values.iter().copied().fold(0., f64::max) * 1e-6).max(f64::MIN_POSITIVE)
+        });
+
+        profile
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let x = match (self.axis, dispersion) {
+                    (SpectrumAxis::Wavelength, Some(dispersion)) => dispersion.wavelength(i as f64),
+                    _ => i as f64,
+                };
+                let y = match (reference, divisor) {
+                    (Some(reference), _) => value / reference.values[i].max(floor),
+                    (None, Some(divisor)) => value / divisor[i].max(1e-6),
+                    (None, None) => *value,
+                };
+                [x, y]
+            })
+            .collect()
+    }
+
+    /// Write the live profile as CSV, in Å when the dispersion is known.
+    fn export(&self, path: &Path, dispersion: Option<&Dispersion>) -> std::io::Result<()> {
+        let Some(live) = &self.live else {
+            return Ok(());
+        };
+        let mut file = File::create(path)?;
+        let x_label = match (self.axis, dispersion) {
+            (SpectrumAxis::Wavelength, Some(_)) => "wavelength_angstrom",
+            _ => "pixel",
+        };
+        let y_label = if self.compare && self.reference.is_some() {
+            "ratio"
+        } else if self.divisor.is_some() {
+            "normalized"
+        } else {
+            "intensity_adu"
+        };
+        writeln!(file, "{},{}", x_label, y_label)?;
+        for PlotPoint { x, y } in self.points(live, dispersion).points() {
+            writeln!(file, "{},{}", x, y)?;
+        }
+        Ok(())
+    }
+
+    /// Read a FITS spectrum as the reference, resampled by wavelength to the pixels of
+    /// the live profile when both are calibrated.
+    fn load_reference(
+        &mut self,
+        path: &Path,
+        dispersion: Option<&Dispersion>,
+    ) -> Result<(), String> {
+        let image = FitsImage::open(path).map_err(|e| e.to_string())?;
+        // the first row of two dimensional spectra
+        let len = image.shape.first().copied().unwrap_or(0);
+        let values: Vec<f64> = image.data.values().into_iter().take(len).collect();
+        let target = self.live.as_ref().map_or(len, |live| live.values.len());
+        let values = match (image.dispersion(), dispersion) {
+            (Some(source), Some(dispersion)) => (0..target)
+                .map(|i| {
+                    let x = source.pixel(dispersion.wavelength(i as f64));
+                    if x < 0. || x > len as f64 - 1. {
+                        return 0.;
+                    }
+                    let i = (x as usize).min(len.saturating_sub(2));
+                    let t = x - i as f64;
+                    values[i] * (1. - t) + values[(i + 1).min(len - 1)] * t
+                })
+                .collect(),
+            _ if len == target => values,
+            _ => {
+                return Err(
+                    "The spectrum has no wavelength axis and another length than the live one."
+                        .to_string(),
+                )
+            }
+        };
+        self.reference = Some(Profile { values });
+        Ok(())
+    }
+
+    /// Write the plotted values of the live profile as a FITS spectrum, with the
+    /// wavelength axis in the header.
+    fn export_fits(&self, path: &Path, dispersion: Option<&Dispersion>) -> Result<(), FitsError> {
+        let Some(live) = &self.live else {
+            return Ok(());
+        };
+        let values: Vec<f64> = self
+            .points(live, None)
+            .points()
+            .iter()
+            .map(|point| point.y)
+            .collect();
+        let mut image = FitsImage::spectrum(&values, dispersion);
+        self.acquisition.write(&mut image.header);
+        let unit = if self.compare && self.reference.is_some() {
+            "ratio to reference"
+        } else if self.divisor.is_some() {
+            "normalized"
+        } else {
+            "ADU"
+        };
+        image.header.set("BUNIT", unit, "");
+        image.save(path)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, dispersion: Option<&Dispersion>) {
+        if dispersion.is_none() {
+            self.axis = SpectrumAxis::Pixels;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Slit");
+            ui.radio_value(&mut self.orientation, SlitOrientation::Vertical, "Vertical");
+            ui.radio_value(
+                &mut self.orientation,
+                SlitOrientation::Horizontal,
+                "Horizontal",
+            );
+
+            ui.separator();
+            ui.label("Band");
+            ui.add(egui::DragValue::new(&mut self.band_start).prefix("from "));
+            ui.add(
+                egui::DragValue::new(&mut self.band_height)
+                    .clamp_range(1..=usize::MAX)
+                    .suffix(" px"),
+            );
+
+            ui.separator();
+            ui.label("Axis");
+            ui.radio_value(&mut self.axis, SpectrumAxis::Pixels, "Pixel");
+            ui.add_enabled_ui(dispersion.is_some(), |ui| {
+                ui.radio_value(&mut self.axis, SpectrumAxis::Wavelength, "Wavelength");
+            });
+
+            ui.separator();
+            ui.toggle_value(&mut self.frozen, "Freeze ❄");
+            ui.add_enabled_ui(self.live.is_some(), |ui| {
+                if ui.button("Overlay").clicked() {
+                    self.overlays.extend(self.live.clone());
+                }
+                if ui.button("Set reference").clicked() {
+                    self.reference = self.live.clone();
+                }
+            });
+            if ui
+                .button("Load reference…")
+                .on_hover_text("Read a FITS spectrum, matched by wavelength when calibrated")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("FITS", &fits::EXTENSIONS)
+                    .pick_file()
+                {
+                    self.file_error = self.load_reference(&path, dispersion).err();
+                }
+            }
+            ui.add_enabled_ui(!self.overlays.is_empty(), |ui| {
+                if ui.button("Clear overlays").clicked() {
+                    self.overlays.clear();
+                }
+            });
+            ui.add_enabled_ui(self.reference.is_some(), |ui| {
+                ui.checkbox(&mut self.compare, "Divide by reference");
+            });
+
+            ui.separator();
+            ui.add_enabled_ui(self.live.is_some(), |ui| {
+                if ui.button("Export CSV").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .set_file_name("spectrum.csv")
+                        .save_file()
+                    {
+                        self.file_error =
+                            self.export(&path, dispersion).err().map(|e| e.to_string());
+                    }
+                }
+                if ui.button("Export FITS").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("FITS", &fits::EXTENSIONS)
+                        .set_file_name("spectrum.fits")
+                        .save_file()
+                    {
+                        self.file_error = self
+                            .export_fits(&path, dispersion)
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+                }
+            });
+            if let Some(error) = &self.file_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+        let x_label = match self.axis {
+            SpectrumAxis::Pixels => "Pixel",
+            SpectrumAxis::Wavelength => "Wavelength [Å]",
+        };
+        let y_label = if self.compare && self.reference.is_some() {
+            "Ratio to reference"
+        } else if self.divisor.is_some() {
+            "Normalized intensity"
+        } else {
+            "Intensity [ADU]"
+        };
+
+        let to_plot = |pixel: f64| match (self.axis, dispersion) {
+            (SpectrumAxis::Wavelength, Some(dispersion)) => dispersion.wavelength(pixel),
+            _ => pixel,
+        };
+        let mut clicked = None;
+        egui_plot::Plot::new("spectrum_plot")
+            .legend(Legend::default())
+            .x_axis_label(x_label)
+            .y_axis_label(y_label)
+            .show(ui, |plot_ui| {
+                if let Some(reference) = self.reference.as_ref().filter(|_| !self.compare) {
+                    plot_ui.line(Line::new(self.points(reference, dispersion)).name("Reference"));
+                }
+                for (i, overlay) in self.overlays.iter().enumerate() {
+                    plot_ui.line(
+                        Line::new(self.points(overlay, dispersion))
+                            .name(format!("Overlay {}", i + 1)),
+                    );
+                }
+                if let Some(live) = &self.live {
+                    let name = if self.frozen { "Frozen" } else { "Live" };
+                    let points = self.points(live, dispersion);
+                    let values = points.points();
+                    let color = egui::Color32::from_rgb(255, 120, 0);
+                    for line in &self.identifications {
+                        let x = to_plot(line.pixel);
+                        plot_ui.vline(VLine::new(x).color(color.gamma_multiply(0.4)));
+                        if let Some(point) = values.get(line.pixel.round().max(0.) as usize) {
+                            plot_ui.text(
+                                Text::new(PlotPoint::new(x, point.y), line.label())
+                                    .anchor(egui::Align2::CENTER_TOP)
+                                    .color(color),
+                            );
+                        }
+                    }
+                    plot_ui.line(Line::new(points).name(name));
+                }
+                if let Some(selected) = self.selected {
+                    plot_ui.vline(VLine::new(to_plot(selected)).name("Selected line"));
+                }
+                if plot_ui.response().clicked() {
+                    clicked = plot_ui.pointer_coordinate().map(|point| point.x);
+                }
+            });
+        if let Some(x) = clicked {
+            self.selected = Some(match (self.axis, dispersion) {
+                (SpectrumAxis::Wavelength, Some(dispersion)) => dispersion.pixel(x),
+                _ => x,
+            });
+        }
+    }
+}
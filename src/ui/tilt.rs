@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::{
+    frame::Frame,
+    spectrum::{
+        focus,
+        profile::{Profile, SlitOrientation},
+        tilt::Tilt,
+    },
+};
+
+use super::image_view::ImageOverlay;
+
+/// Number of lines averaged for the tilt.
+const LINE_COUNT: usize = 3;
+/// Tilt considered aligned in degrees.
+const ALIGNED: f64 = 0.05;
+/// Full scale of the alignment gauge in degrees.
+const GAUGE_RANGE: f64 = 2.;
+
+/// Measures the rotation of the camera relative to the slit and derotates the frames.
+pub struct TiltPanel {
+    pub tilt: Option<Tilt>,
+    /// Rotate the live frames so that the lines run along the slit axis.
+    pub derotate: bool,
+    pub show_overlay: bool,
+    /// Measure on every frame.
+    live: bool,
+    /// Guessed core positions of the measured lines in pixels.
+    lines: Vec<f64>,
+    half_window: usize,
+    band: usize,
+    frame: Option<Arc<Frame>>,
+    orientation: SlitOrientation,
+    error: Option<String>,
+}
+
+impl TiltPanel {
+    pub fn new(tilt: Option<Tilt>) -> Self {
+        Self {
+            tilt,
+            derotate: false,
+            show_overlay: true,
+            live: false,
+            lines: vec![],
+            half_window: 15,
+            band: 8,
+            frame: None,
+            orientation: SlitOrientation::Vertical,
+            error: None,
+        }
+    }
+
+    /// Feed a frame as delivered by the camera, before any correction.
+    pub fn update(&mut self, frame: &Arc<Frame>, orientation: SlitOrientation) {
+        self.frame = Some(frame.clone());
+        self.orientation = orientation;
+        if self.live {
+            self.measure();
+        }
+    }
+
+    fn measure(&mut self) {
+        let Some(frame) = &self.frame else {
+            return;
+        };
+        if self.lines.is_empty() {
+            return;
+        }
+        match Tilt::measure(
+            frame,
+            self.orientation,
+            &self.lines,
+            self.half_window,
+            self.band,
+        ) {
+            Ok(tilt) => {
+                // follow the lines when the spectrum drifts
+                self.lines = tilt
+                    .lines
+                    .iter()
+                    .map(|line| line.centre(line.reference))
+                    .collect();
+                self.tilt = Some(tilt);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// Tilt the frames are derotated with, if derotation is enabled and applies to frames
+    /// in `orientation`.
+    pub fn correction(&self, orientation: SlitOrientation) -> Option<Tilt> {
+        self.tilt
+            .clone()
+            .filter(|tilt| self.derotate && tilt.orientation == orientation)
+    }
+
+    /// Tangent of each measured line at the middle of the slit, in image coordinates.
+    pub fn overlays(&self) -> Vec<ImageOverlay> {
+        let (Some(tilt), Some(frame)) = (&self.tilt, &self.frame) else {
+            return vec![];
+        };
+        // derotated frames no longer show the measured tilt
+        if !self.show_overlay || (self.derotate && tilt.orientation == self.orientation) {
+            return vec![];
+        }
+        let (_, slit_len) = tilt.orientation.dimensions(frame);
+        let to_image = |slit: f64, dispersion: f64| match tilt.orientation {
+            SlitOrientation::Vertical => egui::pos2(dispersion as f32, slit as f32),
+            SlitOrientation::Horizontal => egui::pos2(slit as f32, dispersion as f32),
+        };
+        tilt.lines
+            .iter()
+            .map(|line| {
+                let centre = line.centre(line.reference);
+                let slope = line.slope(line.reference);
+                let at = |slit: f64| to_image(slit, centre + slope * (slit - line.reference));
+                ImageOverlay {
+                    points: vec![at(0.), at(slit_len as f64)],
+                    color: egui::Color32::from_rgb(255, 0, 200),
+                    connected: true,
+                }
+            })
+            .collect()
+    }
+
+    /// Needle showing the tilt, centred when aligned.
+    fn gauge(ui: &mut egui::Ui, angle: f64) {
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(ui.available_width(), 30.), egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 3., ui.visuals().extreme_bg_color);
+        let x = |angle: f64| {
+            rect.center().x + (angle / GAUGE_RANGE).clamp(-1., 1.) as f32 * rect.width() / 2.
+        };
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(x(-ALIGNED)..=x(ALIGNED), rect.y_range()),
+            0.,
+            egui::Color32::from_rgb(0, 120, 0),
+        );
+        painter.vline(
+            x(angle),
+            rect.y_range(),
+            egui::Stroke::new(3., ui.visuals().strong_text_color()),
+        );
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, profile: Option<&Profile>) {
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(profile.is_some(), |ui| {
+                if ui
+                    .button("Pick deepest lines")
+                    .on_hover_text("Measure the tilt of the deepest absorption lines")
+                    .clicked()
+                {
+                    if let Some(profile) = profile {
+                        self.lines.clear();
+                        while self.lines.len() < LINE_COUNT {
+                            let Some(line) =
+                                focus::deepest_line(profile, &self.lines, 2 * self.half_window)
+                            else {
+                                break;
+                            };
+                            self.lines.push(line as f64);
+                        }
+                        self.measure();
+                    }
+                }
+            });
+            ui.add_enabled_ui(!self.lines.is_empty() && self.frame.is_some(), |ui| {
+                if ui.button("Measure").clicked() {
+                    self.measure();
+                }
+                ui.toggle_value(&mut self.live, "Live");
+            });
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.half_window)
+                    .clamp_range(3..=200)
+                    .prefix("window ±")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.band)
+                    .clamp_range(1..=100)
+                    .prefix("band ")
+                    .suffix(" px"),
+            );
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some(tilt) = &self.tilt {
+            ui.add_space(5.);
+            let text = if tilt.angle.abs() <= ALIGNED {
+                "Aligned".to_string()
+            } else if tilt.angle > 0. {
+                format!("Rotate the image clockwise by {:.2}°", tilt.angle)
+            } else {
+                format!("Rotate the image counter-clockwise by {:.2}°", -tilt.angle)
+            };
+            ui.label(egui::RichText::new(text).strong());
+            Self::gauge(ui, tilt.angle);
+            ui.label(format!(
+                "Tilt {:.3}° ± {:.3}° over {} lines",
+                tilt.angle,
+                tilt.spread,
+                tilt.lines.len()
+            ));
+
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.show_overlay, "Show overlay");
+                ui.checkbox(&mut self.derotate, "Derotate frames");
+                if ui.button("Clear").clicked() {
+                    self.tilt = None;
+                    self.derotate = false;
+                    self.live = false;
+                }
+            });
+        }
+    }
+}